@@ -0,0 +1,142 @@
+// FFT ocean (Tessendorf).
+//
+// `spectrum` evolves the initial spectrum to the current time, then `fft` is run once per
+// Stockham radix-2 stage for the rows and then the columns.  `resolve` writes the
+// displacement and derivative textures sampled by the water material, and accumulates
+// the foam.
+//
+// Two complex signals are transformed at once (packed into a `vec4`):
+//   xy = height + i * x displacement
+//   zw = z displacement
+
+struct Ocean {
+  time: f32,
+  choppiness: f32,
+  patch_size: f32,
+  size: u32,
+  delta_time: f32,
+};
+
+struct Stage {
+  // Size of the sub-transforms merged by this stage.
+  ns: u32,
+  // 0 = rows, 1 = columns.
+  direction: u32,
+};
+
+@group(0) @binding(0) var<uniform> ocean: Ocean;
+@group(0) @binding(1) var<uniform> stage: Stage;
+// xy = h0(k), zw = conj(h0(-k))
+@group(0) @binding(2) var h0_texture: texture_2d<f32>;
+@group(0) @binding(3) var<storage, read> src: array<vec4<f32>>;
+@group(0) @binding(4) var<storage, read_write> dst: array<vec4<f32>>;
+// xyz = displacement, w = d(Dx)/dz
+@group(0) @binding(5) var displacement_texture: texture_storage_2d<rgba16float, write>;
+// x = dh/dx, y = dh/dz, z = d(Dx)/dx, w = d(Dz)/dz
+@group(0) @binding(6) var derivatives_texture: texture_storage_2d<rgba16float, write>;
+// Wave folding accumulated with the `FOAM_DECAY` rates, kept between frames.
+@group(0) @binding(7) var<storage, read_write> foam: array<vec4<f32>>;
+@group(0) @binding(8) var foam_texture: texture_storage_2d<rgba16float, write>;
+
+const PI: f32 = 3.14159265359;
+const GRAVITY: f32 = 9.81;
+// The dispersion is quantized so the simulation loops after this many seconds.
+const REPEAT_TIME: f32 = 200.0;
+// Foam decay rates (per second) of the foam channels.
+const FOAM_DECAY: vec4<f32> = vec4<f32>(0.5, 1.0, 2.0, 4.0);
+
+fn complex_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+  return vec2<f32>(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+fn wave_vector(id: vec2<u32>) -> vec2<f32> {
+  let n = i32(ocean.size);
+  let m = vec2<i32>(id);
+  let wrapped = select(m, m - n, m >= vec2<i32>(n / 2));
+  return vec2<f32>(wrapped) * (2.0 * PI / ocean.patch_size);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn spectrum(@builtin(global_invocation_id) id: vec3<u32>) {
+  if id.x >= ocean.size || id.y >= ocean.size {
+    return;
+  }
+  let h0 = textureLoad(h0_texture, vec2<i32>(id.xy), 0);
+  let k_vec = wave_vector(id.xy);
+  let k = max(length(k_vec), 0.0001);
+
+  let omega_0 = 2.0 * PI / REPEAT_TIME;
+  let m = floor(sqrt(GRAVITY * k) / omega_0);
+  let phase = 2.0 * PI * fract(m * fract(ocean.time / REPEAT_TIME));
+  let e = vec2<f32>(cos(phase), sin(phase));
+
+  let h = complex_mul(h0.xy, e) + complex_mul(h0.zw, vec2<f32>(e.x, -e.y));
+  // -i * h
+  let ih = vec2<f32>(h.y, -h.x);
+  let dx = ih * (k_vec.x / k);
+  let dz = ih * (k_vec.y / k);
+  // h + i * dx
+  let a = vec2<f32>(h.x - dx.y, h.y + dx.x);
+  dst[id.y * ocean.size + id.x] = vec4<f32>(a, dz);
+}
+
+fn line_index(line: u32, i: u32) -> u32 {
+  if stage.direction == 0u {
+    return line * ocean.size + i;
+  }
+  return i * ocean.size + line;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn fft(@builtin(global_invocation_id) id: vec3<u32>) {
+  let half = ocean.size / 2u;
+  if id.x >= half || id.y >= ocean.size {
+    return;
+  }
+  let j = id.x;
+  let line = id.y;
+  let ns = stage.ns;
+  let k = j % ns;
+
+  // Inverse transform twiddle.
+  let angle = PI * f32(k) / f32(ns);
+  let twiddle = vec2<f32>(cos(angle), sin(angle));
+
+  let v0 = src[line_index(line, j)];
+  let v1 = src[line_index(line, j + half)];
+  let t = vec4<f32>(complex_mul(v1.xy, twiddle), complex_mul(v1.zw, twiddle));
+
+  let out = (j / ns) * ns * 2u + k;
+  dst[line_index(line, out)] = v0 + t;
+  dst[line_index(line, out + ns)] = v0 - t;
+}
+
+fn load_displacement(x: i32, y: i32) -> vec3<f32> {
+  let n = i32(ocean.size);
+  let i = u32((y + n) % n) * ocean.size + u32((x + n) % n);
+  let v = src[i];
+  return vec3<f32>(ocean.choppiness * v.y, v.x, ocean.choppiness * v.z);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn resolve(@builtin(global_invocation_id) id: vec3<u32>) {
+  if id.x >= ocean.size || id.y >= ocean.size {
+    return;
+  }
+  let x = i32(id.x);
+  let y = i32(id.y);
+  let d = load_displacement(x, y);
+  let step = 2.0 * ocean.patch_size / f32(ocean.size);
+  let ddx = (load_displacement(x + 1, y) - load_displacement(x - 1, y)) / step;
+  let ddz = (load_displacement(x, y + 1) - load_displacement(x, y - 1)) / step;
+
+  textureStore(displacement_texture, vec2<i32>(id.xy), vec4<f32>(d, ddz.x));
+  textureStore(derivatives_texture, vec2<i32>(id.xy), vec4<f32>(ddx.y, ddz.y, ddx.x, ddz.z));
+
+  // The surface folds (1 - Jacobian) where the choppy displacement compresses it.
+  let folding = 1.0 - ((1.0 + ddx.x) * (1.0 + ddz.z) - ddz.x * ddx.z);
+  let i = id.y * ocean.size + id.x;
+  let accumulated = max(foam[i] * exp(-FOAM_DECAY * ocean.delta_time), vec4<f32>(folding));
+  foam[i] = accumulated;
+  textureStore(foam_texture, vec2<i32>(id.xy), accumulated);
+}
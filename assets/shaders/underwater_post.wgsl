@@ -0,0 +1,129 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+#import bevy_render::globals::Globals
+#ifdef GOD_RAYS
+#import bevy_water::caustics_functions as caustics_fn
+#endif
+
+struct Underwater {
+  inverse_view_proj: mat4x4<f32>,
+  camera_position: vec3<f32>,
+  camera_depth: f32,
+  // Plane of the wave surface at the camera.
+  surface: vec4<f32>,
+  absorption: vec3<f32>,
+  wobble: f32,
+  fog_color: vec4<f32>,
+  tint: vec4<f32>,
+  fallback_distance: f32,
+  // God rays.
+  god_ray_steps: u32,
+  god_ray_distance: f32,
+  god_ray_intensity: f32,
+  god_ray_color: vec4<f32>,
+  // Towards the light.
+  light_dir: vec3<f32>,
+  water_world_to_uv: mat4x4<f32>,
+}
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var screen_sampler: sampler;
+@group(0) @binding(2) var<uniform> underwater: Underwater;
+@group(0) @binding(3) var<uniform> globals: Globals;
+#ifdef DEPTH_PREPASS
+#ifdef MULTISAMPLED
+@group(0) @binding(4) var depth_texture: texture_depth_multisampled_2d;
+#else
+@group(0) @binding(4) var depth_texture: texture_depth_2d;
+#endif
+#endif
+#ifdef GOD_RAYS
+// Rendered by the caustics material: r = area ratio of the refracted light * 0.5.
+@group(0) @binding(5) var caustics_texture: texture_2d<f32>;
+@group(0) @binding(6) var caustics_sampler: sampler;
+#endif
+
+// Distance of the sky (no depth) for the fog.
+const SKY_DISTANCE: f32 = 1000.0;
+
+fn world_position(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+  let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+  let p = underwater.inverse_view_proj * vec4<f32>(ndc, depth, 1.0);
+  return p.xyz / p.w;
+}
+
+fn scene_distance(uv: vec2<f32>) -> f32 {
+#ifdef DEPTH_PREPASS
+  let texel = vec2<i32>(uv * vec2<f32>(textureDimensions(depth_texture)));
+  let depth = textureLoad(depth_texture, texel, 0);
+  if depth <= 0.0 {
+    return SKY_DISTANCE;
+  }
+  return length(world_position(uv, depth) - underwater.camera_position);
+#else
+  return underwater.fallback_distance;
+#endif
+}
+
+#ifdef GOD_RAYS
+// Light scattered towards the camera along the view ray, up to `distance`.
+fn god_rays(position: vec4<f32>, near: vec3<f32>, distance: f32) -> vec3<f32> {
+  let surface = underwater.surface;
+  // `caustics_fn` planes are dot(n, p) = w.
+  let plane = vec4<f32>(surface.xyz, -surface.w);
+  let light = refract(-underwater.light_dir, surface.xyz, caustics_fn::IOR);
+  let ray = normalize(near - underwater.camera_position);
+  let steps = underwater.god_ray_steps;
+  let step = min(distance, underwater.god_ray_distance) / f32(steps);
+  // Interleaved gradient noise hides the banding of the steps.
+  let jitter = fract(52.9829189 * fract(dot(position.xy, vec2<f32>(0.06711056, 0.00583715))));
+
+  var shafts = vec3<f32>(0.0);
+  for (var i = 0u; i < steps; i = i + 1u) {
+    let t = (f32(i) + jitter) * step;
+    let p = underwater.camera_position + ray * t;
+    if dot(surface.xyz, p) + surface.w >= 0.0 {
+      continue;
+    }
+    // Where the light reaching `p` went through the waves.
+    let entry = caustics_fn::line_plane_intercept(p, light, plane);
+    let uv = (underwater.water_world_to_uv * vec4<f32>(entry, 1.0)).xz;
+    if any(uv < vec2<f32>(0.0)) || any(uv >= vec2<f32>(1.0)) {
+      continue;
+    }
+    let focus = textureSampleLevel(caustics_texture, caustics_sampler, uv, 0.0).r / 0.5;
+    shafts += focus * exp(-underwater.absorption * (t + length(entry - p))) * step;
+  }
+  return underwater.god_ray_color.rgb * underwater.god_ray_intensity * shafts;
+}
+#endif
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+  let t = globals.time;
+  // Pixel on the near plane (reversed z), and its height above the water surface.
+  let near = world_position(in.uv, 1.0);
+  let waterline = sin(near.x * 3.0 + t * 1.8) * 0.01 + sin(near.z * 2.3 - t * 1.3) * 0.01;
+  let above = dot(underwater.surface.xyz, near) + underwater.surface.w - waterline;
+  if above > 0.0 {
+    return textureSampleLevel(screen_texture, screen_sampler, in.uv, 0.0);
+  }
+
+  // Refraction wobble.
+  let wobble = vec2<f32>(sin(in.uv.y * 40.0 + t * 2.0), cos(in.uv.x * 31.0 + t * 1.6)) * underwater.wobble;
+  let uv = clamp(in.uv + wobble, vec2<f32>(0.0), vec2<f32>(1.0));
+  let color = textureSampleLevel(screen_texture, screen_sampler, uv, 0.0);
+
+  // Beer-Lambert absorption towards the scene, the fog gets darker deeper down.
+  let distance = scene_distance(uv);
+  let transmittance = exp(-underwater.absorption * distance);
+  let light = exp(-underwater.absorption * max(underwater.camera_depth, 0.0));
+  var rgb = color.rgb * underwater.tint.rgb * transmittance
+    + underwater.fog_color.rgb * light * (1.0 - transmittance);
+#ifdef GOD_RAYS
+  rgb += god_rays(in.position, near, distance);
+#endif
+
+  // Darker band just below the waterline.
+  rgb *= 1.0 - 0.4 * (1.0 - smoothstep(0.0, 0.015, -above));
+  return vec4<f32>(rgb, color.a);
+}
@@ -9,7 +9,75 @@ struct WaterMaterial {
   amplitude: f32,
   clarity: f32,
   edge_scale: f32,
+  // 0 = noise, 1 = gerstner, 2 = fft.
+  wave_mode: u32,
+  wave_count: u32,
+  // Gerstner layers: xy = wave vector, z = steepness, w = speed.
+  waves: array<vec4<f32>, 8>,
+  fft_patch_size: f32,
+  // Noise waves: rotation (cos, sin) into noise space and wavelength scale.
+  noise_rotation: vec2<f32>,
+  noise_scale: f32,
+  // Noise waves faded out with the `noise_fade` weight.
+  noise_fade_rotation: vec2<f32>,
+  noise_fade_scale: f32,
+  noise_fade: f32,
+  // Screen space reflections.
+  ssr_steps: u32,
+  ssr_max_distance: f32,
+  ssr_thickness: f32,
+  sky_color: vec4<f32>,
+  // Screen space refraction.
+  refraction_strength: f32,
+  refraction_depth: f32,
+  // Planar reflection: offset by the wave normals.
+  planar_reflection_distortion: f32,
+  // Wave crest foam.
+  foam_color: vec4<f32>,
+  foam_threshold: f32,
+  foam_decay: f32,
+  // Baked depth map: xy = world offset, zw = world to texture scale.
+  depth_map_rect: vec4<f32>,
+  depth_map_max_depth: f32,
+  wave_damping_depth: f32,
+  wave_shoaling: f32,
+  // Rain rings.
+  rain_intensity: f32,
+  rain_drop_size: f32,
+  rain_strength: f32,
+  // Flow map: xy = world offset, zw = world to texture scale.
+  flow_map_rect: vec4<f32>,
+  flow_max_speed: f32,
+  flow_cycle: f32,
 };
 
 @group(2) @binding(100)
 var<uniform> material: WaterMaterial;
+
+// FFT ocean: xyz = displacement, w = d(Dx)/dz.
+@group(2) @binding(101) var fft_displacement: texture_2d<f32>;
+@group(2) @binding(102) var fft_displacement_sampler: sampler;
+// FFT ocean: x = dh/dx, y = dh/dz, z = d(Dx)/dx, w = d(Dz)/dz.
+@group(2) @binding(103) var fft_derivatives: texture_2d<f32>;
+@group(2) @binding(104) var fft_derivatives_sampler: sampler;
+// Planar reflection camera image.
+@group(2) @binding(105) var planar_reflection_texture: texture_2d<f32>;
+@group(2) @binding(106) var planar_reflection_sampler: sampler;
+// FFT ocean: wave folding accumulated with decay rates of 0.5, 1, 2 and 4 per second.
+@group(2) @binding(107) var fft_foam: texture_2d<f32>;
+@group(2) @binding(108) var fft_foam_sampler: sampler;
+// Baked water depth below the rest surface divided by `depth_map_max_depth`.
+@group(2) @binding(109) var depth_map: texture_2d<f32>;
+@group(2) @binding(110) var depth_map_sampler: sampler;
+// Ripples: x = height, y = previous height, zw = slope (dh/dx, dh/dz).
+@group(2) @binding(111) var ripples: texture_2d<f32>;
+@group(2) @binding(112) var ripples_sampler: sampler;
+// Wake trails: row 0 = points, row 1 = trails (see `water_wake`).
+@group(2) @binding(113) var wakes: texture_2d<f32>;
+// Splashes (see `water_rain`).
+@group(2) @binding(114) var splashes: texture_2d<f32>;
+// Flow velocity divided by `flow_max_speed`.
+@group(2) @binding(115) var flow_map: texture_2d<f32>;
+@group(2) @binding(116) var flow_map_sampler: sampler;
+// Area of the ripples: xy = world offset, zw = world to texture scale (1 texel).
+@group(2) @binding(117) var ripples_area: texture_2d<f32>;
@@ -0,0 +1,82 @@
+#define_import_path bevy_water::water_foam
+
+#import bevy_pbr::mesh_view_bindings::globals
+
+#import bevy_water::water_bindings::{material, fft_foam, fft_foam_sampler}
+#import bevy_water::water_functions::{
+  WAVE_MODE_GERSTNER, WAVE_MODE_FFT, get_noise_wave_height_at, fft_uv,
+}
+
+// Folding range over which the foam fades in.
+const FOAM_SOFTNESS: f32 = 0.2;
+// Wavelength (meters) of the noise waves at `noise_scale = 1.0`.
+const NOISE_WAVELENGTH: f32 = 60.0;
+const TAU: f32 = 6.283185307179586;
+
+// Folding (1 - Jacobian) of a horizontal displacement with the partial derivatives
+// `j` = (d(Dx)/dx, d(Dz)/dz, d(Dx)/dz), 0 = flat and 1 = folding over.
+fn folding(j: vec3<f32>) -> f32 {
+  return 1.0 - ((1.0 + j.x) * (1.0 + j.y) - j.z * j.z);
+}
+
+// Folding of the gerstner surface at `p` at the time `t`.
+fn gerstner_folding(p: vec2<f32>, t: f32) -> f32 {
+  var j = vec3<f32>(0.0);
+  for (var i = 0u; i < material.wave_count; i = i + 1u) {
+    let wave = material.waves[i];
+    let k = length(wave.xy);
+    if k == 0.0 {
+      continue;
+    }
+    let dir = wave.xy / k;
+    let s = wave.z * material.amplitude * sin(k * (dot(dir, p) - wave.w * t));
+    j = j - vec3<f32>(dir.x * dir.x, dir.y * dir.y, dir.x * dir.y) * s;
+  }
+  return folding(j);
+}
+
+// Folding of the noise waves at `p` at the time `t`.
+//
+// The noise waves only move up and down, they are folded as if they were displaced like
+// gerstner waves of the noise wavelength: d(D)/dx = d²(height)/dx² / k.
+fn noise_folding(p: vec2<f32>, t: f32) -> f32 {
+  let k = TAU / (NOISE_WAVELENGTH * material.noise_scale);
+  // Wide enough to ignore the fine noise detail.
+  let delta = 2.0 * material.noise_scale;
+  let dx = vec2<f32>(delta, 0.0);
+  let dz = vec2<f32>(0.0, delta);
+  let height = get_noise_wave_height_at(p, t);
+  let hxx = get_noise_wave_height_at(p + dx, t) + get_noise_wave_height_at(p - dx, t) - 2.0 * height;
+  let hzz = get_noise_wave_height_at(p + dz, t) + get_noise_wave_height_at(p - dz, t) - 2.0 * height;
+  let hxz = get_noise_wave_height_at(p + dx + dz, t) - get_noise_wave_height_at(p + dx - dz, t)
+    - get_noise_wave_height_at(p - dx + dz, t) + get_noise_wave_height_at(p - dx - dz, t);
+  let hessian = vec3<f32>(hxx, hzz, hxz * 0.25) / (delta * delta);
+  return folding(hessian * material.amplitude / k);
+}
+
+#ifdef WATER_MATERIAL
+// Folding of the FFT ocean accumulated by the compute pass, at the foam decay rate.
+fn fft_folding(p: vec2<f32>) -> f32 {
+  let foam = textureSampleLevel(fft_foam, fft_foam_sampler, fft_uv(p), 0.0);
+  // Interpolate between the decay rates of the channels (0.5, 1, 2 and 4 per second).
+  let x = clamp(log2(max(material.foam_decay, 0.5)) + 1.0, 0.0, 3.0);
+  let weights = max(vec4<f32>(1.0) - abs(vec4<f32>(x) - vec4<f32>(0.0, 1.0, 2.0, 3.0)), vec4<f32>(0.0));
+  return material.amplitude * dot(foam, weights);
+}
+#endif
+
+// Foam coverage (0 to 1) at the water position `p`.
+fn foam(p: vec2<f32>) -> f32 {
+  var folding = 0.0;
+  if material.wave_mode == WAVE_MODE_FFT {
+#ifdef WATER_MATERIAL
+    folding = fft_folding(p);
+#endif
+  } else if material.wave_mode == WAVE_MODE_GERSTNER {
+    folding = gerstner_folding(p, globals.time);
+  } else {
+    folding = noise_folding(p, globals.time);
+  }
+  let threshold = material.foam_threshold;
+  return smoothstep(threshold, threshold + FOAM_SOFTNESS, folding) * material.foam_color.a;
+}
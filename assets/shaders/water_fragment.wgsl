@@ -19,11 +19,56 @@
 
 #import bevy_water::water_bindings
 #import bevy_water::water_functions as water_fn
+#ifdef WATER_FOAM
+#import bevy_water::water_foam
+#endif
+#ifdef WATER_TRANSMISSION
+#import bevy_water::water_transmission
+#endif
+#ifdef WATER_SSR
+#import bevy_water::water_ssr
+#endif
+#ifdef WATER_PLANAR_REFLECTION
+#import bevy_water::water_planar_reflection
+#endif
+#ifdef WATER_WAKES
+#import bevy_water::water_wake
+#endif
+#ifdef WATER_RAIN
+#import bevy_water::water_rain
+#else ifdef WATER_SPLASHES
+#import bevy_water::water_rain
+#endif
 
 fn ndc_depth_to_linear(ndc_depth: f32) -> f32 {
   return -view.projection[3][2] / ndc_depth;
 }
 
+// Normal of the waves at the water position `w_pos`.
+fn wave_normal(w_pos: vec2<f32>, world_normal: vec3<f32>) -> vec3<f32> {
+  if water_bindings::material.wave_mode != water_fn::WAVE_MODE_NOISE {
+    return water_fn::get_wave_normal(w_pos);
+  }
+  let delta = 0.2;
+  let height = water_fn::get_wave_height(w_pos);
+  let height_dx = water_fn::get_wave_height(w_pos + vec2<f32>(delta, 0.0));
+  let height_dz = water_fn::get_wave_height(w_pos + vec2<f32>(0.0, delta));
+  return normalize(world_normal + (vec3<f32>(height - height_dx, delta, height - height_dz) * 8.0));
+}
+
+// Water color seen through `depth_diff_view` meters of water.
+fn depth_water_color(depth_diff_view: f32) -> vec4<f32> {
+  let deep_color = water_bindings::material.deep_color;
+  let water_clarity = water_bindings::material.clarity;
+  let shallow_color = water_bindings::material.shallow_color;
+  let edge_scale = water_bindings::material.edge_scale;
+  let edge_color = water_bindings::material.edge_color;
+
+  let beers_law = exp(-depth_diff_view * water_clarity);
+  let depth_color = vec4<f32>(mix(deep_color.xyz, shallow_color.xyz, beers_law), 1.0 - beers_law);
+  return mix(edge_color, depth_color, smoothstep(0.0, edge_scale, depth_diff_view));
+}
+
 @fragment
 fn fragment(
 	p_in: VertexOutput,
@@ -33,38 +78,86 @@ fn fragment(
   var world_position: vec4<f32> = in.world_position;
   let w_pos = water_fn::uv_to_coord(in.uv);
   // Calculate normal.
-  let delta = 0.2;
-  let height = water_fn::get_wave_height(w_pos);
-  let height_dx = water_fn::get_wave_height(w_pos + vec2<f32>(delta, 0.0));
-  let height_dz = water_fn::get_wave_height(w_pos + vec2<f32>(0.0, delta));
-  let world_normal = normalize(in.world_normal + (vec3<f32>(height - height_dx, delta, height - height_dz) * 8.0));
-  in.world_normal = world_normal;
+#ifdef WATER_FLOW_MAP
+  // Waves carried by the flow.
+  let flow = water_fn::flow_phases(w_pos, world_position.xz);
+  in.world_normal = normalize(mix(
+    wave_normal(flow.coord_a, in.world_normal),
+    wave_normal(flow.coord_b, in.world_normal),
+    flow.blend,
+  ));
+#else
+  in.world_normal = wave_normal(w_pos, in.world_normal);
+#endif
+#ifdef WATER_DEPTH_MAP
+  // Waves shaped by the water depth.
+  let wave_scale = water_fn::depth_wave_scale(world_position.xz);
+  in.world_normal = normalize(in.world_normal * vec3<f32>(wave_scale, 1.0, wave_scale));
+#endif
+#ifdef WATER_RIPPLES
+  // Tilt the normal by the slope of the ripples.
+  let ripple_slope = water_fn::get_ripples(world_position.xz).yz;
+  in.world_normal = normalize(in.world_normal - vec3<f32>(ripple_slope.x, 0.0, ripple_slope.y));
+#endif
+#ifdef WATER_WAKES
+  // Tilt the normal by the crests of the wakes.
+  let wake_surface = water_wake::wake(world_position.xz);
+  in.world_normal = normalize(in.world_normal - vec3<f32>(wake_surface.slope.x, 0.0, wake_surface.slope.y));
+#endif
+#ifdef WATER_RAIN
+  // Rings of the rain drops.
+  let rain_slope = water_rain::rain(world_position.xz);
+  in.world_normal = normalize(in.world_normal - vec3<f32>(rain_slope.x, 0.0, rain_slope.y));
+#endif
+#ifdef WATER_SPLASHES
+  let splash_rings = water_rain::splash_rings(world_position.xz);
+  in.world_normal = normalize(in.world_normal - vec3<f32>(splash_rings.slope.x, 0.0, splash_rings.slope.y));
+#endif
 
 	// get PbrInput from StandardMaterial bindings.
 	var pbr_input = pbr_input_from_standard_material(in, is_front);
 
-  let deep_color = water_bindings::material.deep_color;
-	var water_color = deep_color;
+	var water_color = water_bindings::material.deep_color;
+#ifdef WATER_DEPTH_MAP
+  // The baked depth, along the view ray.
+  let baked_depth = water_fn::baked_water_depth(world_position.xz);
+  water_color = depth_water_color(baked_depth / max(abs(pbr_input.V.y), 0.05));
+#endif
 #ifdef DEPTH_PREPASS
 #ifndef WEBGL2
-  let water_clarity = water_bindings::material.clarity;
-  let shallow_color = water_bindings::material.shallow_color;
-  let edge_scale = water_bindings::material.edge_scale;
-  let edge_color = water_bindings::material.edge_color;
-
+  // The depth prepass also has the objects moving in the water.
   let z_depth_buffer_ndc = bevy_pbr::prepass_utils::prepass_depth(in.position, 0u);
   let z_depth_buffer_view = ndc_depth_to_linear(z_depth_buffer_ndc);
   let z_fragment_view = ndc_depth_to_linear(in.position.z);
-  let depth_diff_view = z_fragment_view - z_depth_buffer_view;
-  let beers_law = exp(-depth_diff_view * water_clarity);
-  let depth_color = vec4<f32>(mix(deep_color.xyz, shallow_color.xyz, beers_law), 1.0 - beers_law);
-  water_color = mix(edge_color, depth_color, smoothstep(0.0, edge_scale, depth_diff_view));
+  water_color = depth_water_color(z_fragment_view - z_depth_buffer_view);
 #endif
 #endif
   pbr_input.material.base_color *= water_color;
+#ifdef WATER_FOAM
+  // Foam covers the water, and is rough.
+#ifdef WATER_FLOW_MAP
+  let foam = mix(water_foam::foam(flow.coord_a), water_foam::foam(flow.coord_b), flow.blend);
+#else
+  let foam = water_foam::foam(w_pos);
+#endif
+  let foam_color = vec4<f32>(water_bindings::material.foam_color.rgb, 1.0);
+  pbr_input.material.base_color = mix(pbr_input.material.base_color, foam_color, foam);
+  pbr_input.material.perceptual_roughness = mix(pbr_input.material.perceptual_roughness, 0.9, foam);
+#endif
+#ifdef WATER_WAKES
+  // Foam trails behind the wakes.
+  let wake_color = vec4<f32>(water_wake::WAKE_FOAM_COLOR, 1.0);
+  pbr_input.material.base_color = mix(pbr_input.material.base_color, wake_color, wake_surface.foam);
+  pbr_input.material.perceptual_roughness = mix(pbr_input.material.perceptual_roughness, 0.9, wake_surface.foam);
+#endif
+#ifdef WATER_SPLASHES
+  // Foam where the splashes hit.
+  let splash_color = vec4<f32>(1.0);
+  pbr_input.material.base_color = mix(pbr_input.material.base_color, splash_color, splash_rings.foam);
+#endif
 
-  //let foam_color = water_bindings::material.edge_color;
-  //let foam = mix(foam_color, depth_color, smoothstep(0.0, edge_scale, depth_diff_view));
+  // The alpha of opaque materials is discarded.
+  let water_alpha = pbr_input.material.base_color.a;
 
 	// alpha discard
   pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
@@ -80,6 +173,31 @@ fn fragment(
 		out.color = pbr_input.material.base_color;
 	}
 
+  // The lit water with the alpha of the water color.
+  var lit_water = vec4<f32>(out.color.rgb, water_alpha);
+#ifdef WATER_PLANAR_REFLECTION
+  lit_water = water_planar_reflection::apply_reflection(
+    in.position,
+    pbr_input.N,
+    pbr_input.V,
+    lit_water,
+  );
+  out.color = lit_water;
+#endif
+#ifdef WATER_TRANSMISSION
+  // Blend with the scene behind the water.
+  let background = water_transmission::background(in.position, pbr_input.N);
+  out.color = vec4<f32>(mix(background, lit_water.rgb, lit_water.a), 1.0);
+#ifdef WATER_SSR
+#ifndef WATER_PLANAR_REFLECTION
+  out.color = vec4<f32>(
+    water_ssr::apply_reflection(pbr_input.world_position.xyz, pbr_input.N, pbr_input.V, out.color.rgb),
+    1.0,
+  );
+#endif
+#endif
+#endif
+
 	// Apply PBR post processing.
 	out.color = main_pass_post_lighting_processing(pbr_input, out.color);
 #endif
@@ -2,11 +2,15 @@
 
 #import bevy_pbr::mesh_view_bindings::globals
 
-#import bevy_water::water_bindings::material
+#import bevy_water::water_bindings::{
+  material, fft_displacement, fft_displacement_sampler, fft_derivatives, fft_derivatives_sampler,
+  depth_map, depth_map_sampler, ripples, ripples_sampler, ripples_area, flow_map,
+  flow_map_sampler,
+}
 #import bevy_water::noise::fbm::fbm
 
-fn wave(p: vec2<f32>) -> f32 {
-  let time = globals.time * .5 + 23.0;
+fn wave(p: vec2<f32>, g_time: f32) -> f32 {
+  let time = g_time * .5 + 23.0;
   let time_x = time / 1.0;
   let time_y = time / 0.5;
   let wave_len_x = 5.0;
@@ -17,35 +21,215 @@ fn wave(p: vec2<f32>) -> f32 {
   return wave_y + n;
 }
 
-// fn get_wave_height(p: vec2<f32>) -> f32 {
-//   let time = globals.time / 2.0;
-//   var d = wave((p + time) * 0.4) * 0.3;
-//   d = d + wave((p - time) * 0.3) * 0.3;
-//   d = d + wave((p + time) * 0.5) * 0.2;
-//   d = d + wave((p - time) * 0.6) * 0.2;
-//   return material.amplitude * d;
-// }
+const WAVE_MODE_NOISE: u32 = 0u;
+const WAVE_MODE_GERSTNER: u32 = 1u;
+const WAVE_MODE_FFT: u32 = 2u;
 
-// fn get_wave_normal(p: vec2<f32>) -> vec3<f32> {
-//     let delta = 0.2;
-//     let height = get_wave_height(p);
-//     let height_dx = get_wave_height(p + vec2<f32>(delta, 0.0));
-//     let height_dz = get_wave_height(p + vec2<f32>(0.0, delta));
-//     return vec3<f32>(height -height_dx, delta, height - height_dz) * 8.0;
-// }
+fn get_noise_wave_height(world_p: vec2<f32>) -> f32 {
+  return get_noise_wave_height_at(world_p, globals.time);
+}
 
-fn uv_to_coord(uv: vec2<f32>) -> vec2<f32> {
-  return material.coord_offset + (uv * material.coord_scale);
+// Noise wave height (unit amplitude) at `world_p` at the time `t`.
+fn get_noise_wave_height_at(world_p: vec2<f32>, t: f32) -> f32 {
+  let height = noise_layer_height(world_p, t, material.noise_rotation, material.noise_scale);
+  if material.noise_fade <= 0.0 {
+    return height;
+  }
+  // Cross-fade the faded out waves (see `NoiseFade`).
+  let faded = noise_layer_height(world_p, t, material.noise_fade_rotation, material.noise_fade_scale);
+  return mix(height, faded, material.noise_fade);
+}
+
+fn noise_layer_height(world_p: vec2<f32>, t: f32, r: vec2<f32>, scale: f32) -> f32 {
+  // Rotate and scale into noise space, longer waves travel faster.
+  let p = vec2<f32>(r.x * world_p.x - r.y * world_p.y, r.y * world_p.x + r.x * world_p.y) / scale;
+  let g_time = t / sqrt(scale);
+  let time = g_time / 2.0;
+  var d = wave((p + time) * 0.4, g_time) * 0.3;
+  d = d + wave((p - time) * 0.3, g_time) * 0.3;
+  d = d + wave((p + time) * 0.5, g_time) * 0.2;
+  d = d + wave((p - time) * 0.6, g_time) * 0.2;
+  return d;
+}
+
+fn get_gerstner_displacement(p: vec2<f32>) -> vec3<f32> {
+  var d = vec3<f32>(0.0);
+  for (var i = 0u; i < material.wave_count; i = i + 1u) {
+    let wave = material.waves[i];
+    let k = length(wave.xy);
+    // A wave without a direction is flat (and would divide by zero).
+    if k == 0.0 {
+      continue;
+    }
+    let dir = wave.xy / k;
+    let a = wave.z / k;
+    let f = k * (dot(dir, p) - wave.w * globals.time);
+    d = d + vec3<f32>(dir.x * a * cos(f), a * sin(f), dir.y * a * cos(f));
+  }
+  return d;
+}
+
+// Analytic normal of the displaced gerstner surface at the undisturbed point `p`.
+fn get_gerstner_normal(p: vec2<f32>) -> vec3<f32> {
+  var tangent = vec3<f32>(1.0, 0.0, 0.0);
+  var binormal = vec3<f32>(0.0, 0.0, 1.0);
+  for (var i = 0u; i < material.wave_count; i = i + 1u) {
+    let wave = material.waves[i];
+    let k = length(wave.xy);
+    if k == 0.0 {
+      continue;
+    }
+    let dir = wave.xy / k;
+    let s = wave.z * material.amplitude;
+    let f = k * (dot(dir, p) - wave.w * globals.time);
+    tangent = tangent + vec3<f32>(-dir.x * dir.x * s * sin(f), dir.x * s * cos(f), -dir.x * dir.y * s * sin(f));
+    binormal = binormal + vec3<f32>(-dir.x * dir.y * s * sin(f), dir.y * s * cos(f), -dir.y * dir.y * s * sin(f));
+  }
+  return normalize(cross(binormal, tangent));
+}
+
+// The FFT textures are only bound in the `WaterMaterial` pipelines.
+#ifdef WATER_MATERIAL
+fn fft_uv(p: vec2<f32>) -> vec2<f32> {
+  // Texel centers are the simulated grid points.
+  let size = vec2<f32>(textureDimensions(fft_displacement));
+  return p / material.fft_patch_size + 0.5 / size;
+}
+
+fn get_fft_displacement(p: vec2<f32>) -> vec3<f32> {
+  return textureSampleLevel(fft_displacement, fft_displacement_sampler, fft_uv(p), 0.0).xyz;
+}
+
+fn get_fft_normal(p: vec2<f32>) -> vec3<f32> {
+  let uv = fft_uv(p);
+  let d = textureSampleLevel(fft_derivatives, fft_derivatives_sampler, uv, 0.0);
+  let dx_dz = textureSampleLevel(fft_displacement, fft_displacement_sampler, uv, 0.0).w;
+  let a = material.amplitude;
+  let tangent = vec3<f32>(1.0 + a * d.z, a * d.x, a * dx_dz);
+  let binormal = vec3<f32>(a * dx_dz, a * d.y, 1.0 + a * d.w);
+  return normalize(cross(binormal, tangent));
+}
+#endif
+
+#ifdef WATER_DEPTH_MAP
+// Baked water depth (meters) below the rest surface at the world position `p`.
+fn baked_water_depth(p: vec2<f32>) -> f32 {
+  let uv = (p - material.depth_map_rect.xy) * material.depth_map_rect.zw;
+  if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+    // Deep water outside the depth map.
+    return material.depth_map_max_depth;
+  }
+  return textureSampleLevel(depth_map, depth_map_sampler, uv, 0.0).r * material.depth_map_max_depth;
 }
 
-const FREQ: f32 = 8.0;
+// Scale of the waves at the world position `p`, see `WaterDepthMap::wave_scale`.
+//
+// The waves grow (shoaling) from twice the damping depth, and calm down in shallower water.
+fn depth_wave_scale(p: vec2<f32>) -> f32 {
+  if material.wave_damping_depth <= 0.0 {
+    return 1.0;
+  }
+  let d = max(baked_water_depth(p), 0.0) / material.wave_damping_depth;
+  let grown = 1.0 + material.wave_shoaling * saturate(2.0 - d);
+  return min(grown, (1.0 + material.wave_shoaling) * d);
+}
+#endif
+
+#ifdef WATER_RIPPLES
+// Ripples at the world position `p`: x = height, yz = slope (dh/dx, dh/dz).
+fn get_ripples(p: vec2<f32>) -> vec3<f32> {
+  // The area follows the focus, it is written every frame instead of to the material.
+  let area = textureLoad(ripples_area, vec2<i32>(0), 0);
+  let uv = (p - area.xy) * area.zw;
+  // Fade out at the edges of the simulated area.
+  let edge = min(min(uv.x, uv.y), min(1.0 - uv.x, 1.0 - uv.y));
+  let fade = saturate(edge * 10.0);
+  if fade <= 0.0 {
+    return vec3<f32>(0.0);
+  }
+  let r = textureSampleLevel(ripples, ripples_sampler, uv, 0.0);
+  return vec3<f32>(r.x, r.z, r.w) * fade;
+}
+#endif
+
+#ifdef WATER_FLOW_MAP
+// Flow velocity (m/s) at the world position `p`, still water outside the flow map.
+fn flow_velocity(p: vec2<f32>) -> vec2<f32> {
+  let uv = (p - material.flow_map_rect.xy) * material.flow_map_rect.zw;
+  if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+    return vec2<f32>(0.0);
+  }
+  return textureSampleLevel(flow_map, flow_map_sampler, uv, 0.0).xy * material.flow_max_speed;
+}
+
+// The two phases of the scrolling along the flow, see `WaterFlowMap::phases`.
+struct FlowPhases {
+  // Wave coordinates of the phases.
+  coord_a: vec2<f32>,
+  coord_b: vec2<f32>,
+  // Weight of the second phase.
+  blend: f32,
+}
+
+// Scroll the wave coordinate `coord` by the flow at the world position `p`.
+//
+// Each phase scrolls for a cycle and jumps back while the other one is shown.
+fn flow_phases(coord: vec2<f32>, p: vec2<f32>) -> FlowPhases {
+  let flow = flow_velocity(p);
+  let cycle = max(material.flow_cycle, 0.01);
+  let phase_a = fract(globals.time / cycle);
+  let phase_b = fract(phase_a + 0.5);
+  var out: FlowPhases;
+  out.coord_a = coord - flow * phase_a * cycle;
+  out.coord_b = coord - flow * phase_b * cycle;
+  out.blend = abs(1.0 - 2.0 * phase_a);
+  return out;
+}
+#endif
+
+// Displacement of the point `p` on the undisturbed water plane.
+fn get_wave_displacement(p: vec2<f32>) -> vec3<f32> {
+  if material.wave_mode == WAVE_MODE_GERSTNER {
+    return material.amplitude * get_gerstner_displacement(p);
+  }
+  if material.wave_mode == WAVE_MODE_FFT {
+#ifdef WATER_MATERIAL
+    return material.amplitude * get_fft_displacement(p);
+#else
+    return vec3<f32>(0.0);
+#endif
+  }
+  return vec3<f32>(0.0, material.amplitude * get_noise_wave_height(p), 0.0);
+}
 
 fn get_wave_height(p: vec2<f32>) -> f32 {
-    let time = globals.time;
-    return sin((p.x + time) * FREQ) * material.amplitude;
+  return get_wave_displacement(p).y;
 }
 
 fn get_wave_normal(p: vec2<f32>) -> vec3<f32> {
-    let time = globals.time;
-    return -normalize(vec3<f32>(FREQ * material.amplitude * cos((p.x + time) * FREQ), -1, 0));
+  if material.wave_mode == WAVE_MODE_GERSTNER {
+    return get_gerstner_normal(p);
+  }
+#ifdef WATER_MATERIAL
+  if material.wave_mode == WAVE_MODE_FFT {
+    return get_fft_normal(p);
+  }
+#endif
+  let delta = 0.2;
+  let height = get_wave_height(p);
+  let height_dx = get_wave_height(p + vec2<f32>(delta, 0.0));
+  let height_dz = get_wave_height(p + vec2<f32>(0.0, delta));
+  return normalize(vec3<f32>(height - height_dx, delta, height - height_dz));
+}
+
+fn uv_to_coord(uv: vec2<f32>) -> vec2<f32> {
+  return material.coord_offset + (uv * material.coord_scale);
+}
+
+// Reflectance of water at normal incidence.
+const WATER_F0: f32 = 0.02;
+
+// Schlick's approximation of the water reflectance.
+fn fresnel(n_dot_v: f32) -> f32 {
+  return WATER_F0 + (1.0 - WATER_F0) * pow(1.0 - saturate(n_dot_v), 5.0);
 }
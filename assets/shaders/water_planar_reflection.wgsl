@@ -0,0 +1,45 @@
+#define_import_path bevy_water::water_planar_reflection
+
+#import bevy_pbr::mesh_view_bindings::view
+#ifdef TONEMAP_IN_SHADER
+#import bevy_core_pipeline::tonemapping::approximate_inverse_tone_mapping
+#endif
+
+#import bevy_water::water_bindings::{
+  material, planar_reflection_texture, planar_reflection_sampler,
+}
+#import bevy_water::water_functions::fresnel
+
+// Color of the mirrored scene at the screen position `frag_coord`, offset by the normal `N`.
+fn planar_reflection(frag_coord: vec4<f32>, N: vec3<f32>) -> vec3<f32> {
+  let uv = (frag_coord.xy - view.viewport.xy) / view.viewport.zw;
+  // The reflection camera's image is mirrored horizontally.
+  let offset = N.xz * material.planar_reflection_distortion;
+  var color = textureSampleLevel(
+    planar_reflection_texture,
+    planar_reflection_sampler,
+    vec2<f32>(1.0 - uv.x, uv.y) + offset,
+    0.0,
+  );
+#ifdef TONEMAP_IN_SHADER
+  color = approximate_inverse_tone_mapping(color, view.color_grading);
+#endif
+  return color.rgb;
+}
+
+// Add the planar reflection to the lit water `color`.  The alpha is raised by the
+// reflectance, so the reflection also shows on blended water.
+fn apply_reflection(
+  frag_coord: vec4<f32>,
+  N: vec3<f32>,
+  V: vec3<f32>,
+  color: vec4<f32>,
+) -> vec4<f32> {
+  let f = fresnel(dot(N, V));
+  let alpha = color.a + (1.0 - color.a) * f;
+  if alpha <= 0.0 {
+    return color;
+  }
+  let rgb = (color.rgb * color.a * (1.0 - f) + planar_reflection(frag_coord, N) * f) / alpha;
+  return vec4<f32>(rgb, alpha);
+}
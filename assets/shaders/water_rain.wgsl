@@ -0,0 +1,97 @@
+#define_import_path bevy_water::water_rain
+
+#import bevy_pbr::mesh_view_bindings::globals
+
+#import bevy_water::water_bindings::{material, splashes}
+#import bevy_water::noise::random::random2d
+
+// Lifetime (seconds) of a rain ring.
+const RAIN_RING_DURATION: f32 = 1.0;
+// Width of a rain ring (fraction of a rain cell).
+const RAIN_RING_WIDTH: f32 = 0.06;
+// Overlapping grids of rain cells.
+const RAIN_LAYERS: u32 = 2u;
+// Speed (m/s) of the splash rings.
+const SPLASH_RING_SPEED: f32 = 1.5;
+const TAU: f32 = 6.28318530718;
+
+// Slope of a ring at `x` from its crest, ringing with a wavelength of twice `width`.
+fn ring_slope(x: f32, width: f32) -> f32 {
+  let k = TAU / (2.0 * width);
+  let envelope = exp(-x * x / (width * width));
+  return envelope * (-sin(k * x) - 2.0 * x / (k * width * width) * cos(k * x));
+}
+
+// Slope (dh/dx, dh/dz) of the rain rings at the world position `p` (x, z).
+fn rain(p: vec2<f32>) -> vec2<f32> {
+  // A cell holds one ring of up to `rain_drop_size` meters at a time.
+  let cell_size = max(material.rain_drop_size * 2.0, 0.01);
+  var slope = vec2<f32>(0.0);
+  for (var layer = 0u; layer < RAIN_LAYERS; layer = layer + 1u) {
+    let uv = p / cell_size + vec2<f32>(0.37, 0.71) * f32(layer);
+    let cell = floor(uv);
+    let f = uv - cell;
+    for (var y = -1; y <= 1; y = y + 1) {
+      for (var x = -1; x <= 1; x = x + 1) {
+        let neighbour = vec2<f32>(f32(x), f32(y));
+        let seed = cell + neighbour + 17.0 * f32(layer);
+        let cycle = globals.time / RAIN_RING_DURATION + random2d(seed);
+        let n = floor(cycle);
+        let t = cycle - n;
+        // A drop in some of the cells each cycle.
+        if random2d(seed + n * 0.618) >= material.rain_intensity {
+          continue;
+        }
+        let center = neighbour + 0.25 + 0.5 * vec2<f32>(
+          random2d(seed + vec2<f32>(3.1, n)),
+          random2d(seed + vec2<f32>(n, 7.3)),
+        );
+        let offset = f - center;
+        let d = length(offset);
+        let fade = (1.0 - t) * (1.0 - t);
+        let s = ring_slope(d - 0.5 * t, RAIN_RING_WIDTH) * fade;
+        slope = slope + offset / max(d, 1e-4) * s;
+      }
+    }
+  }
+  return slope * 0.5 * material.rain_strength;
+}
+
+struct Splashes {
+  foam: f32,
+  // Slope (dh/dx, dh/dz) of the rings.
+  slope: vec2<f32>,
+}
+
+// Foam and slope of the splashes at the world position `p` (x, z).
+//
+// Packed by `WaterSplashes`:
+// row 0: xy = position (x, z), z = age (seconds), w = radius (meters).
+// row 1: x = strength, y = duration (seconds).
+fn splash_rings(p: vec2<f32>) -> Splashes {
+  var out: Splashes;
+  out.foam = 0.0;
+  out.slope = vec2<f32>(0.0);
+  let count = i32(textureDimensions(splashes).x);
+  for (var i = 0; i < count; i = i + 1) {
+    let splash = textureLoad(splashes, vec2<i32>(i, 0), 0);
+    let radius = splash.w;
+    if radius <= 0.0 {
+      break;
+    }
+    let params = textureLoad(splashes, vec2<i32>(i, 1), 0);
+    let age = splash.z;
+    let fade = saturate(1.0 - age / max(params.y, 0.01));
+    let offset = p - splash.xy;
+    let d = length(offset);
+    // Rings spreading out and flattening.
+    let width = 0.5 * radius + 0.1 * age;
+    let crest = radius + SPLASH_RING_SPEED * age;
+    let s = ring_slope(d - crest, width) * params.x * fade * fade;
+    out.slope = out.slope + offset / max(d, 1e-4) * s;
+    // Foam where it hit, settling quickly.
+    let splash_foam = exp(-d * d / (radius * radius)) * saturate(1.0 - 2.0 * age);
+    out.foam = max(out.foam, splash_foam * min(params.x, 1.0));
+  }
+  return out;
+}
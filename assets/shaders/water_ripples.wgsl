@@ -0,0 +1,68 @@
+// Ripples: one step of the wave equation on a heightfield.
+//
+// The texels hold x = height, y = previous height, zw = slope (dh/dx, dh/dz).  The first
+// step of a frame also moves the grid with the focus and adds the disturbances.
+
+struct Ripples {
+  size: u32,
+  count: u32,
+  // Movement of the grid (texels).
+  shift: vec2<i32>,
+  // Squared courant number (speed * time step / texel size).
+  courant2: f32,
+  // Height kept per step.
+  damping: f32,
+  texel_size: f32,
+  // Largest height (meters) up or down.
+  max_height: f32,
+  // xy = texel position, z = radius (texels), w = height (meters).
+  disturbances: array<vec4<f32>, 32>,
+};
+
+@group(0) @binding(0) var<uniform> ripples: Ripples;
+@group(0) @binding(1) var src: texture_2d<f32>;
+@group(0) @binding(2) var dst: texture_storage_2d<rgba16float, write>;
+
+// Texels ripple free beyond this distance from the edge.
+const EDGE_WIDTH: f32 = 8.0;
+
+// The water is still outside the grid.
+fn load(p: vec2<i32>) -> vec4<f32> {
+  let size = i32(ripples.size);
+  if any(p < vec2<i32>(0)) || any(p >= vec2<i32>(size)) {
+    return vec4<f32>(0.0);
+  }
+  return textureLoad(src, p, 0);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn step(@builtin(global_invocation_id) id: vec3<u32>) {
+  if id.x >= ripples.size || id.y >= ripples.size {
+    return;
+  }
+  let p = vec2<i32>(id.xy) + ripples.shift;
+  let here = load(p);
+  let left = load(p - vec2<i32>(1, 0)).x;
+  let right = load(p + vec2<i32>(1, 0)).x;
+  let up = load(p - vec2<i32>(0, 1)).x;
+  let down = load(p + vec2<i32>(0, 1)).x;
+
+  let laplacian = left + right + up + down - 4.0 * here.x;
+  var height = (2.0 * here.x - here.y + ripples.courant2 * laplacian) * ripples.damping;
+
+  let center = vec2<f32>(id.xy) + 0.5;
+  for (var i = 0u; i < ripples.count; i = i + 1u) {
+    let d = ripples.disturbances[i];
+    let r = max(d.z, 0.5);
+    let offset = center - d.xy;
+    height = height + d.w * exp(-dot(offset, offset) / (r * r));
+  }
+
+  // Absorb the ripples before they reach the edges.
+  let edge = f32(min(min(id.x, id.y), min(ripples.size - 1u - id.x, ripples.size - 1u - id.y)));
+  let absorb = mix(0.9, 1.0, saturate(edge / EDGE_WIDTH));
+  height = clamp(height * absorb, -ripples.max_height, ripples.max_height);
+
+  let slope = vec2<f32>(right - left, down - up) / (2.0 * ripples.texel_size);
+  textureStore(dst, vec2<i32>(id.xy), vec4<f32>(height, here.x * absorb, slope));
+}
@@ -0,0 +1,101 @@
+#define_import_path bevy_water::water_ssr
+
+#import bevy_pbr::{
+  mesh_view_bindings::{view, light_probes},
+  prepass_utils,
+}
+
+#import bevy_water::water_bindings::material
+#import bevy_water::water_functions::fresnel
+#import bevy_water::water_transmission::scene_color
+
+// Binary search steps to refine a hit.
+const REFINE_STEPS: u32 = 4u;
+
+// Screen position (uv) and view space z of the world position `p`, w < 0 when behind the camera.
+fn project(p: vec3<f32>) -> vec4<f32> {
+  let clip = view.view_proj * vec4<f32>(p, 1.0);
+  let ndc = clip.xy / clip.w;
+  let view_z = (view.inverse_view * vec4<f32>(p, 1.0)).z;
+  return vec4<f32>(ndc * vec2<f32>(0.5, -0.5) + 0.5, view_z, clip.w);
+}
+
+#ifdef DEPTH_PREPASS
+#ifndef WEBGL2
+// How far (view space) the point `s` (from `project`) is behind the depth prepass.
+fn depth_behind(s: vec4<f32>) -> f32 {
+  // The prepass covers the whole render target, the viewport may be a part of it.
+  let frag_coord = view.viewport.xy + s.xy * view.viewport.zw;
+  let depth = prepass_utils::prepass_depth(vec4<f32>(frag_coord, 0.0, 0.0), 0u);
+  if depth <= 0.0 {
+    // Nothing was drawn here.
+    return -1.0e10;
+  }
+  let ndc = vec4<f32>(s.x * 2.0 - 1.0, 1.0 - s.y * 2.0, depth, 1.0);
+  let scene = view.inverse_projection * ndc;
+  return scene.z / scene.w - s.z;
+}
+#endif
+#endif
+
+// March the reflection ray from `origin` along `direction` through the depth prepass.
+// Returns the reflected color and the hit strength in alpha.
+fn screen_space_reflection(origin: vec3<f32>, direction: vec3<f32>) -> vec4<f32> {
+#ifdef DEPTH_PREPASS
+#ifndef WEBGL2
+  let steps = max(material.ssr_steps, 1u);
+  let step = material.ssr_max_distance / f32(steps);
+  var previous = 0.0;
+  for (var i = 1u; i <= steps; i = i + 1u) {
+    let t = f32(i) * step;
+    let s = project(origin + direction * t);
+    if s.w <= 0.0 || any(s.xy < vec2<f32>(0.0)) || any(s.xy > vec2<f32>(1.0)) {
+      // Left the screen.
+      break;
+    }
+    let behind = depth_behind(s);
+    if behind > 0.0 && behind < material.ssr_thickness + step {
+      // Refine the hit between the last two steps.
+      var near = previous;
+      var far = t;
+      for (var j = 0u; j < REFINE_STEPS; j = j + 1u) {
+        let mid = (near + far) * 0.5;
+        if depth_behind(project(origin + direction * mid)) > 0.0 {
+          far = mid;
+        } else {
+          near = mid;
+        }
+      }
+      let hit = project(origin + direction * far);
+      // Fade out at the screen edges and the end of the ray.
+      let edge = min(min(hit.x, 1.0 - hit.x), min(hit.y, 1.0 - hit.y));
+      let strength = smoothstep(0.0, 0.1, edge) * (1.0 - far / material.ssr_max_distance);
+      return vec4<f32>(scene_color(hit.xy), strength);
+    }
+    previous = t;
+  }
+#endif
+#endif
+  return vec4<f32>(0.0);
+}
+
+// Add the reflections to the water `color`.
+fn apply_reflection(
+  world_position: vec3<f32>,
+  N: vec3<f32>,
+  V: vec3<f32>,
+  color: vec3<f32>,
+) -> vec3<f32> {
+  let reflection = screen_space_reflection(world_position, reflect(-V, N));
+  var sky = material.sky_color;
+  if light_probes.view_cubemap_index >= 0 {
+    // The PBR lighting already reflects the environment map.
+    sky.a = 0.0;
+  }
+  let strength = reflection.a + (1.0 - reflection.a) * sky.a;
+  if strength <= 0.0 {
+    return color;
+  }
+  let reflected = (reflection.rgb * reflection.a + sky.rgb * sky.a * (1.0 - reflection.a)) / strength;
+  return mix(color, reflected, fresnel(dot(N, V)) * strength);
+}
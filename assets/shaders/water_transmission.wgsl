@@ -0,0 +1,60 @@
+#define_import_path bevy_water::water_transmission
+
+#import bevy_pbr::{
+  mesh_view_bindings::{view, view_transmission_texture, view_transmission_sampler},
+  prepass_utils,
+}
+#ifdef TONEMAP_IN_SHADER
+#import bevy_core_pipeline::tonemapping::approximate_inverse_tone_mapping
+#endif
+
+#import bevy_water::water_bindings::material
+
+// Color of the scene drawn before the water at the screen position `uv`.
+fn scene_color(uv: vec2<f32>) -> vec3<f32> {
+  var color = textureSampleLevel(view_transmission_texture, view_transmission_sampler, uv, 0.0);
+#ifdef TONEMAP_IN_SHADER
+  color = approximate_inverse_tone_mapping(color, view.color_grading);
+#endif
+  return color.rgb;
+}
+
+#ifdef WATER_REFRACTION
+#ifdef DEPTH_PREPASS
+#ifndef WEBGL2
+// How far (view space) the scene at the screen position `uv` is behind the water at the
+// fragment depth `frag_depth`.
+fn water_depth(uv: vec2<f32>, frag_depth: f32) -> f32 {
+  let depth = prepass_utils::prepass_depth(vec4<f32>(uv * view.viewport.zw, 0.0, 0.0), 0u);
+  if depth <= 0.0 {
+    // Nothing was drawn here.
+    return 1.0e10;
+  }
+  return -view.projection[3][2] / depth + view.projection[3][2] / frag_depth;
+}
+#endif
+#endif
+#endif
+
+// The scene behind the water at `frag_coord`, refracted by the wave normal `N`.
+fn background(frag_coord: vec4<f32>, N: vec3<f32>) -> vec3<f32> {
+  let uv = frag_coord.xy / view.viewport.zw;
+#ifdef WATER_REFRACTION
+  // The horizontal part of the normal in screen space.
+  let n = (view.inverse_view * vec4<f32>(N.x, 0.0, N.z, 0.0)).xy;
+  var offset = vec2<f32>(n.x, -n.y) * material.refraction_strength;
+#ifdef DEPTH_PREPASS
+#ifndef WEBGL2
+  // Deeper water bends the scene further.
+  offset *= saturate(water_depth(uv, frag_coord.z) / material.refraction_depth);
+  if water_depth(uv + offset, frag_coord.z) < 0.0 {
+    // Don't refract objects in front of the water.
+    offset = vec2<f32>(0.0);
+  }
+#endif
+#endif
+  return scene_color(clamp(uv + offset, vec2<f32>(0.0), vec2<f32>(1.0)));
+#else
+  return scene_color(uv);
+#endif
+}
@@ -1,5 +1,6 @@
 #import bevy_pbr::{
 	mesh_functions,
+	mesh_view_bindings::view,
 	skinning,
 	view_transformations::position_world_to_clip,
 }
@@ -12,6 +13,20 @@
 #import bevy_pbr::forward_io::{Vertex, VertexOutput}
 #endif
 
+// Intersect the view ray through the screen position `uv` with the plane y = `height`.
+fn project_grid_vertex(uv: vec2<f32>, height: f32) -> vec3<f32> {
+  let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+  let near = view.inverse_view_proj * vec4<f32>(ndc, 1.0, 1.0);
+  let far = view.inverse_view_proj * vec4<f32>(ndc, 0.5, 1.0);
+  let origin = near.xyz / near.w;
+  var dir = far.xyz / far.w - origin;
+  let above = origin.y - height;
+  let side = select(-1.0, 1.0, above >= 0.0);
+  // Rays at or beyond the horizon end on the horizon.
+  dir.y = -side * max(-side * dir.y, length(dir.xz) * 1e-3);
+  return origin + dir * (-above / dir.y);
+}
+
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
   var out: VertexOutput;
@@ -33,18 +48,45 @@ fn vertex(vertex: Vertex) -> VertexOutput {
 #endif
 #endif
 
+#ifdef WATER_PROJECTED_GRID
+  // Project the screen space grid onto the water plane.
+  let world_position = vec4<f32>(project_grid_vertex(vertex.uv, model[3].y), 1.0);
+  let w_pos = world_position.xz;
+#else
   let world_position = mesh_functions::mesh_position_local_to_world(model, vec4<f32>(vertex.position, 1.0));
-
-  // Add the wave height to the world position.
   let w_pos = water_fn::uv_to_coord(vertex.uv);
-  let height = water_fn::get_wave_height(w_pos);
+#endif
+
+  // Add the wave displacement to the world position.
+#ifdef WATER_FLOW_MAP
+  // Waves carried by the flow.
+  let flow = water_fn::flow_phases(w_pos, world_position.xz);
+  var displacement = mix(
+    water_fn::get_wave_displacement(flow.coord_a),
+    water_fn::get_wave_displacement(flow.coord_b),
+    flow.blend,
+  );
+#else
+  var displacement = water_fn::get_wave_displacement(w_pos);
+#endif
+#ifdef WATER_DEPTH_MAP
+  displacement = displacement * water_fn::depth_wave_scale(world_position.xz);
+#endif
+#ifdef WATER_RIPPLES
+  displacement.y = displacement.y + water_fn::get_ripples(world_position.xz).x;
+#endif
 
-  out.world_position = world_position + vec4<f32>((out.world_normal * height), 0.);
+  out.world_position = world_position + vec4<f32>((out.world_normal * displacement.y) + vec3<f32>(displacement.x, 0.0, displacement.z), 0.);
   out.position = position_world_to_clip(out.world_position.xyz);
 
 #ifdef VERTEX_UVS
+#ifdef WATER_PROJECTED_GRID
+  // World coordinates, the material has `coord_offset = 0` and `coord_scale = 1`.
+  out.uv = w_pos;
+#else
   out.uv = vertex.uv;
 #endif
+#endif
 
 #ifdef VERTEX_TANGENTS
   out.world_tangent = mesh_functions::mesh_tangent_local_to_world(
@@ -0,0 +1,84 @@
+#define_import_path bevy_water::water_wake
+
+#import bevy_water::water_bindings::wakes
+
+// Wake trails packed by `WaterWakes`, one trail after the other:
+// row 0: xy = position (x, z), z = distance behind the entity (meters), w = age (seconds).
+// row 1, at the first point of a trail: x = half width, y = foam, z = lifetime, w = points.
+
+// Half angle of a Kelvin wake (19.47 degrees), tan(asin(1 / 3)).
+const KELVIN_TAN: f32 = 0.35355;
+// Height (fraction of the half width) of the wake crests.
+const WAKE_CREST_HEIGHT: f32 = 0.1;
+const WAKE_FOAM_COLOR: vec3<f32> = vec3<f32>(0.9, 0.95, 1.0);
+
+struct Wake {
+  foam: f32,
+  // Slope (dh/dx, dh/dz) of the crests.
+  slope: vec2<f32>,
+}
+
+// Foam and slope of the wake trails at the world position `p` (x, z).
+fn wake(p: vec2<f32>) -> Wake {
+  var out: Wake;
+  out.foam = 0.0;
+  out.slope = vec2<f32>(0.0);
+  let width = i32(textureDimensions(wakes).x);
+  var start = 0;
+  while start < width {
+    let trail = textureLoad(wakes, vec2<i32>(start, 1), 0);
+    let count = i32(trail.w);
+    if count < 2 {
+      break;
+    }
+    let end = min(start + count, width);
+    let half_width = max(trail.x, 0.01);
+
+    // Skip the trails out of reach.
+    let head = textureLoad(wakes, vec2<i32>(start, 0), 0);
+    let trail_length = textureLoad(wakes, vec2<i32>(end - 1, 0), 0).z;
+    if distance(p, head.xy) < trail_length * (1.0 + KELVIN_TAN) + 4.0 * half_width {
+      // Closest segment of the trail.
+      var a = head;
+      var best_d = 1e9;
+      var best_offset = vec2<f32>(0.0);
+      var best = vec2<f32>(0.0);
+      for (var i = start + 1; i < end; i = i + 1) {
+        let b = textureLoad(wakes, vec2<i32>(i, 0), 0);
+        let ab = b.xy - a.xy;
+        let t = saturate(dot(p - a.xy, ab) / max(dot(ab, ab), 1e-4));
+        let offset = p - (a.xy + ab * t);
+        let d = dot(offset, offset);
+        if d < best_d {
+          best_d = d;
+          best_offset = offset;
+          // Distance behind the entity and age.
+          best = mix(a.zw, b.zw, t);
+        }
+        a = b;
+      }
+      let d = sqrt(best_d);
+      let s = best.x;
+      let fade = saturate(1.0 - best.y / max(trail.z, 0.01));
+
+      // Turbulent foam behind the hull, widening slowly.
+      let trail_width = half_width * (1.0 + 0.05 * s);
+      let turbulence = exp(-d * d / (trail_width * trail_width));
+
+      // The crests of the V, spreading from the hull and flattening out.
+      let arm_width = 0.5 * half_width + 0.02 * s;
+      let arm_offset = d - (half_width + s * KELVIN_TAN);
+      let arm = exp(-arm_offset * arm_offset / (arm_width * arm_width))
+        * saturate(s / half_width) / (1.0 + 0.02 * s);
+
+      out.foam = max(out.foam, trail.y * fade * max(turbulence, 0.5 * arm));
+      // Slope across the crest, away from the track.
+      let across = best_offset / max(d, 1e-4);
+      let height = WAKE_CREST_HEIGHT * half_width * fade * arm;
+      out.slope = out.slope - across * (2.0 * arm_offset / (arm_width * arm_width)) * height;
+    }
+    start = end;
+  }
+  out.foam = saturate(out.foam);
+  return out;
+}
@@ -37,6 +37,7 @@ fn main() {
       // amplitude: 0.5,
       amplitude: 0.1,
       // amplitude: 10.0,
+      clipmap: None,
       spawn_tiles: None,
       ..default()
     })
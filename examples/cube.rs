@@ -20,6 +20,7 @@ fn main() {
     .add_plugins(DefaultPlugins)
     .insert_resource(WaterSettings {
       amplitude: 1.0,
+      clipmap: None,
       spawn_tiles: None,
       ..default()
     })
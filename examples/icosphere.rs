@@ -25,6 +25,7 @@ fn main() {
   app
     .insert_resource(WaterSettings {
       amplitude: 0.4,
+      clipmap: None,
       spawn_tiles: None,
       ..default()
     })
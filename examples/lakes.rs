@@ -0,0 +1,160 @@
+//! Two lakes at different elevations using `WaterBody`, with a current in the lower one
+//! and a `RiverSpline` flowing between them.
+
+#[cfg(feature = "depth_prepass")]
+use bevy::core_pipeline::prepass::DepthPrepass;
+
+use bevy::prelude::*;
+
+use bevy_water::material::{Refraction, StandardWaterMaterial};
+use bevy_water::*;
+
+fn main() {
+  let mut app = App::new();
+
+  app
+    .add_plugins(DefaultPlugins)
+    .insert_resource(WaterSettings {
+      clipmap: None,
+      spawn_tiles: None,
+      // Only the lakes and the river, dry land elsewhere.
+      global_water: false,
+      ..default()
+    })
+    .add_plugins(WaterPlugin)
+    .add_systems(Startup, setup)
+    .add_systems(Update, float_cubes);
+
+  app.run();
+}
+
+#[derive(Component)]
+struct Floating;
+
+/// set up two lakes with a floating cube each.
+fn setup(
+  mut commands: Commands,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut materials: ResMut<Assets<StandardMaterial>>,
+  mut water_materials: ResMut<Assets<StandardWaterMaterial>>,
+  mut images: ResMut<Assets<Image>>,
+) {
+  // Calm mountain lake, with planar reflections and its depth baked from the ground.
+  let shore = vec![
+    Vec2::new(-38.0, -6.0),
+    Vec2::new(-30.0, -18.0),
+    Vec2::new(-12.0, -16.0),
+    Vec2::new(-2.0, -4.0),
+    Vec2::new(-6.0, 18.0),
+    Vec2::new(-22.0, 10.0),
+    Vec2::new(-34.0, 14.0),
+  ];
+  commands.spawn((
+    WaterBodyBundle::polygon(
+      WaterBody {
+        height: 10.0,
+        amplitude: 0.3,
+        deep_color: Color::rgba(0.1, 0.3, 0.3, 1.0),
+        ..default()
+      },
+      shore,
+      &mut meshes,
+      &mut water_materials,
+    ),
+    PlanarReflection::default(),
+    BakeWaterDepth::default(),
+  ));
+  // Valley lake, refracting the lake bed, with a current circling around its centre.
+  let rect = Rect::new(10.0, -30.0, 70.0, 30.0);
+  let centre = rect.center();
+  let current = |p: Vec2| (p - centre).perp() * 0.1;
+  commands.spawn(WaterBodyBundle::rect(
+    WaterBody {
+      height: 0.0,
+      amplitude: 1.0,
+      refraction: Some(Refraction::default()),
+      flow_map: Some(WaterFlowMap::from_fn(
+        rect,
+        UVec2::splat(32),
+        3.0,
+        current,
+        &mut images,
+      )),
+      ..default()
+    },
+    rect,
+    &mut meshes,
+    &mut water_materials,
+  ));
+  // River from the mountain lake down to the valley lake.
+  commands.spawn(RiverBundle::new(
+    WaterBody {
+      amplitude: 0.2,
+      ..default()
+    },
+    RiverSpline {
+      points: vec![
+        RiverPoint::new(Vec3::new(-5.0, 10.0, 16.0), 6.0, 2.0),
+        RiverPoint::new(Vec3::new(1.0, 9.0, 26.0), 5.0, 1.5),
+        RiverPoint::new(Vec3::new(8.0, 4.0, 32.0), 4.0, 1.0),
+        RiverPoint::new(Vec3::new(16.0, 0.0, 26.0), 6.0, 2.0),
+      ],
+      speed: 2.5,
+      ..default()
+    },
+  ));
+
+  // Ground around the lakes.
+  commands.spawn(PbrBundle {
+    mesh: meshes.add(Cuboid::new(44.0, 10.0, 44.0)),
+    material: materials.add(Color::rgb(0.4, 0.5, 0.3)),
+    transform: Transform::from_xyz(-20.0, 4.0, 0.0),
+    ..default()
+  });
+
+  // Floating cubes.
+  for x in [-20.0, 55.0] {
+    commands.spawn((
+      PbrBundle {
+        mesh: meshes.add(Cuboid::from_size(Vec3::splat(2.0))),
+        material: materials.add(Color::rgb(0.8, 0.7, 0.6)),
+        transform: Transform::from_xyz(x, 0.0, 0.0),
+        ..default()
+      },
+      Floating,
+    ));
+  }
+
+  // light
+  commands.spawn(DirectionalLightBundle {
+    transform: Transform::from_xyz(4.0, 20.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    directional_light: DirectionalLight {
+      shadows_enabled: true,
+      ..default()
+    },
+    ..default()
+  });
+
+  // camera
+  let mut cam = commands.spawn(Camera3dBundle {
+    transform: Transform::from_xyz(10.0, 40.0, 80.0).looking_at(Vec3::new(10.0, 0.0, 0.0), Vec3::Y),
+    ..default()
+  });
+  #[cfg(feature = "depth_prepass")]
+  {
+    // This will write the depth buffer to a texture that you can use in the main pass
+    cam.insert(DepthPrepass);
+  }
+
+  cam.insert(Name::new("Camera"));
+}
+
+/// Keep the cubes on the surface of their lake, drifting with the current.
+fn float_cubes(water: WaterParam, mut cubes: Query<&mut Transform, With<Floating>>) {
+  for mut transform in cubes.iter_mut() {
+    // Sample from above, so the lake surface is found.
+    let position = Vec3::new(transform.translation.x, 100.0, transform.translation.z);
+    transform.translation += water.flow_velocity(position) * water.time.delta_seconds();
+    transform.translation.y = water.wave_height(position);
+  }
+}
@@ -25,7 +25,7 @@ use bevy_panorbit_camera::{PanOrbitCamera, PanOrbitCameraPlugin};
 #[cfg(feature = "debug")]
 use bevy_prototype_debug_lines::{DebugLines, DebugLinesPlugin};
 
-use bevy_water::*;
+use bevy_water::{material::Foam, *};
 
 #[cfg(not(feature = "atmosphere"))]
 const SKYBOX_NAME: &str = "textures/table_mountain_2_puresky_4k_cubemap.jpg";
@@ -56,7 +56,9 @@ fn main() {
     );
 
   #[cfg(feature = "debug")]
-  app.add_plugins(DebugLinesPlugin::with_depth_test(true));
+  app
+    .add_plugins(DebugLinesPlugin::with_depth_test(true))
+    .add_systems(Update, debug_ships);
 
   #[cfg(feature = "inspector")]
   app.add_plugins(bevy_inspector_egui::quick::WorldInspectorPlugin::new());
@@ -71,18 +73,22 @@ fn main() {
     // Water
     .insert_resource(WaterSettings {
       height: WATER_HEIGHT,
+      foam: Some(Foam::default()),
+      ripples: Some(WaterRipples::default()),
       ..default()
     })
     .add_plugins(WaterPlugin)
     // Ship Physics.
-    .add_systems(Update, update_ships)
+    .add_plugins(BuoyancyPlugin)
     // Setup
     .add_systems(Startup, setup)
     .add_systems(Update, handle_quit)
     // Wireframe
     .add_plugins(WireframePlugin)
     .init_resource::<UiState>()
-    .add_systems(Update, toggle_wireframe);
+    .add_systems(Update, toggle_wireframe)
+    // Rain and splashes.
+    .add_systems(Update, rain_and_splashes);
 
   // Atmosphere + daylight cycle.
   #[cfg(feature = "atmosphere")]
@@ -108,6 +114,24 @@ fn handle_quit(input: Res<ButtonInput<KeyCode>>, mut exit: EventWriter<AppExit>)
   }
 }
 
+fn rain_and_splashes(
+  input: Res<ButtonInput<KeyCode>>,
+  mut rain: ResMut<WaterRain>,
+  cameras: Query<&GlobalTransform, With<Camera3d>>,
+  mut splashes: EventWriter<SplashEvent>,
+) {
+  if input.just_pressed(KeyCode::KeyT) {
+    rain.intensity = if rain.intensity > 0.0 { 0.0 } else { 0.6 };
+  }
+  if input.just_pressed(KeyCode::KeyF) {
+    // Splash in front of the camera.
+    for transform in cameras.iter() {
+      let position = transform.translation() + transform.forward() * 10.0;
+      splashes.send(SplashEvent::new(position, 1.0));
+    }
+  }
+}
+
 #[derive(Resource, Clone, Debug, Default)]
 struct UiState {
   show_wireframe: bool,
@@ -242,72 +266,27 @@ fn daylight_cycle(
 
 #[derive(Bundle, Default)]
 struct ShipBundle {
-  ship: Ship,
+  buoyancy: BuoyancyBundle,
+  disturber: WaterDisturber,
   name: Name,
   scene: SceneBundle,
 }
 
-#[derive(Component, Default, Clone)]
-struct Ship {
-  water_line: f32,
-  front: Vec3,
-  back_left: Vec3,
-  back_right: Vec3,
-}
-
-impl Ship {
-  fn new(water_line: f32, front: f32, back: f32, left: f32, right: f32) -> Self {
-    Self {
-      water_line,
-      front: Vec3::new(0.0, 0.0, front),
-      back_left: Vec3::new(left, 0.0, back),
-      back_right: Vec3::new(right, 0.0, back),
-    }
-  }
-
-  fn update(
-    &self,
-    water: &WaterParam,
-    pos: Vec3,
-    transform: &mut Transform,
-    #[cfg(feature = "debug")] lines: &mut DebugLines,
-  ) {
-    let (yaw, _pitch, _roll) = transform.rotation.to_euler(EulerRot::YXZ);
-    let global = Transform::from_translation(pos).with_rotation(Quat::from_rotation_y(yaw));
-
-    // Get the wave position at the front, back_left and back_right.
-    let mut front = water.wave_point(global.transform_point(self.front));
-    let left = water.wave_point(global.transform_point(self.back_left));
-    let right = water.wave_point(global.transform_point(self.back_right));
-    let normal = (left - front).cross(right - front).normalize();
-
-    // Debug lines.
-    #[cfg(feature = "debug")]
-    {
-      lines.line(front, front + normal, 0.0);
-      lines.line_colored(front, right, 0.0, Color::RED);
-      lines.line(right, left, 0.0);
-      lines.line_colored(left, front, 0.0, Color::GREEN);
-    }
-
-    front.y += self.water_line - 0.2;
-    transform.look_at(front, normal);
-
-    transform.translation.y = ((front.y + left.y + right.y) / 3.0) + self.water_line;
-  }
-}
-
-fn update_ships(
+#[cfg(feature = "debug")]
+fn debug_ships(
   water: WaterParam,
-  mut ships: Query<(&Ship, &mut Transform, &GlobalTransform)>,
-  #[cfg(feature = "debug")] mut lines: ResMut<DebugLines>,
+  ships: Query<(&Buoyancy, &GlobalTransform)>,
+  mut lines: ResMut<DebugLines>,
 ) {
-  for (ship, mut transform, global) in ships.iter_mut() {
-    let pos = global.translation();
-    #[cfg(not(feature = "debug"))]
-    ship.update(&water, pos, &mut transform);
-    #[cfg(feature = "debug")]
-    ship.update(&water, pos, &mut transform, &mut lines);
+  for (buoyancy, global) in ships.iter() {
+    // Centre of buoyancy and centre of mass.
+    let (volume, center) = buoyancy.submerged(&water, global);
+    if volume > 0.0 {
+      lines.line_colored(center, center + Vec3::Y * volume.cbrt(), 0.0, Color::GREEN);
+    }
+    let center_of_mass = global.transform_point(buoyancy.center_of_mass);
+    let up = global.compute_transform().rotation * Vec3::Y;
+    lines.line_colored(center_of_mass, center_of_mass + up, 0.0, Color::RED);
   }
 }
 
@@ -437,6 +416,8 @@ fn setup(
       ),
       ..default()
     },
+    // Fog and tint when the camera dives below the waves.
+    UnderwaterPostProcess::default(),
   ));
 
   #[cfg(feature = "panorbit")]
@@ -469,14 +450,31 @@ fn setup(
 
   // Spawn ships.
   let scene = asset_server.load("models/Kenney_pirate/ship_dark.gltf#Scene0");
-  let ship = Ship::new(-0.400, -3.8, 2.5, -1.4, 1.4);
+  // Box hull of the ship model, 77% submerged so the model's origin floats 0.4m below the water.
+  let hull_half_extents = Vec3::new(1.4, 0.75, 3.15);
+  let hull_volume = hull_half_extents.x * hull_half_extents.y * hull_half_extents.z * 8.0;
+  let ship = Buoyancy::from_hull(
+    Vec3::new(0.0, 0.0, -0.65),
+    hull_half_extents,
+    UVec3::new(3, 3, 8),
+    1025.0 * hull_volume * 0.77,
+  )
+  .with_center_of_mass(Vec3::new(0.0, -0.45, -0.65));
 
   // "Randomly" place the ships.
   for x in 1..18 {
     let f = (x as f32) * 1.20;
     let f2 = ((x % 6) as f32) * -10.90;
     commands.spawn(ShipBundle {
-      ship: ship.clone(),
+      buoyancy: BuoyancyBundle {
+        buoyancy: ship.clone(),
+        ..default()
+      },
+      // The bobbing hull makes ripples.
+      disturber: WaterDisturber {
+        radius: 1.5,
+        ..default()
+      },
       name: Name::new(format!("Ship {x}")),
       scene: SceneBundle {
         scene: scene.clone(),
@@ -492,4 +490,5 @@ fn setup(
   info!("Use Left Shift and Spacebar for vertical movement");
   info!("Use the mouse to look around");
   info!("Press Esc to hide or show the mouse cursor");
+  info!("Press T to toggle the rain, F to splash in front of the camera");
 }
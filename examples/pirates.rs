@@ -55,7 +55,9 @@ fn main() {
     );
 
   #[cfg(feature = "debug")]
-  app.add_plugins(DebugLinesPlugin::with_depth_test(true));
+  app
+    .add_plugins(DebugLinesPlugin::with_depth_test(true))
+    .add_systems(Update, debug_ships);
 
   #[cfg(feature = "inspector")]
   app.add_plugins(bevy_inspector_egui::quick::WorldInspectorPlugin::new());
@@ -74,7 +76,8 @@ fn main() {
     })
     .add_plugins((WaterPlugin, ImageUtilsPlugin))
     // Ship Physics.
-    .add_systems(Update, update_ships)
+    .add_plugins(BuoyancyPlugin)
+    .add_systems(Update, sail)
     // Setup
     .add_systems(Startup, setup)
     .add_systems(Update, handle_quit)
@@ -236,75 +239,50 @@ fn daylight_cycle(
   }
 }
 
-#[derive(Bundle, Default)]
-struct ShipBundle {
-  ship: Ship,
-  name: Name,
-  spatial: SpatialBundle,
-}
-
-#[derive(Component, Default, Clone)]
-struct Ship {
-  water_line: f32,
-  front: Vec3,
-  back_left: Vec3,
-  back_right: Vec3,
+/// Sails the ship forward, turning slowly.
+#[derive(Component, Clone, Copy, Debug, Default)]
+struct Sail {
+  /// Speed (m/s).
+  speed: f32,
+  /// Turn rate (radians per second).
+  turn: f32,
 }
 
-impl Ship {
-  fn new(water_line: f32, front: f32, back: f32, left: f32, right: f32) -> Self {
-    Self {
-      water_line,
-      front: Vec3::new(0.0, 0.0, front),
-      back_left: Vec3::new(left, 0.0, back),
-      back_right: Vec3::new(right, 0.0, back),
-    }
+fn sail(mut ships: Query<(&Sail, &Transform, &mut BuoyancyVelocity)>) {
+  for (sail, transform, mut velocity) in ships.iter_mut() {
+    let forward = transform.rotation * Vec3::NEG_Z;
+    let forward = Vec3::new(forward.x, 0.0, forward.z).normalize_or_zero();
+    // Keep the vertical motion from the waves.
+    velocity.linear.x = forward.x * sail.speed;
+    velocity.linear.z = forward.z * sail.speed;
+    velocity.angular.y = sail.turn;
   }
+}
 
-  fn update(
-    &self,
-    water: &WaterParam,
-    pos: Vec3,
-    transform: &mut Transform,
-    #[cfg(feature = "debug")] lines: &mut DebugLines,
-  ) {
-    let (yaw, _pitch, _roll) = transform.rotation.to_euler(EulerRot::YXZ);
-    let global = Transform::from_translation(pos).with_rotation(Quat::from_rotation_y(yaw));
-
-    // Get the wave position at the front, back_left and back_right.
-    let mut front = water.wave_point(global.transform_point(self.front));
-    let left = water.wave_point(global.transform_point(self.back_left));
-    let right = water.wave_point(global.transform_point(self.back_right));
-    let normal = (left - front).cross(right - front).normalize();
-
-    // Debug lines.
-    #[cfg(feature = "debug")]
-    {
-      lines.line(front, front + normal, 0.0);
-      lines.line_colored(front, right, 0.0, Color::RED);
-      lines.line(right, left, 0.0);
-      lines.line_colored(left, front, 0.0, Color::GREEN);
-      lines.line(transform.translation, transform.translation + normal, 0.0);
-    }
-
-    front.y += self.water_line - 0.2;
-    transform.look_at(front, normal);
-
-    transform.translation.y = ((front.y + left.y + right.y) / 3.0) + self.water_line;
-  }
+#[derive(Bundle, Default)]
+struct ShipBundle {
+  buoyancy: BuoyancyBundle,
+  sail: Sail,
+  wake: Wake,
+  name: Name,
+  spatial: SpatialBundle,
 }
 
-fn update_ships(
+#[cfg(feature = "debug")]
+fn debug_ships(
   water: WaterParam,
-  mut ships: Query<(&Ship, &mut Transform, &GlobalTransform)>,
-  #[cfg(feature = "debug")] mut lines: ResMut<DebugLines>,
+  ships: Query<(&Buoyancy, &GlobalTransform)>,
+  mut lines: ResMut<DebugLines>,
 ) {
-  for (ship, mut transform, global) in ships.iter_mut() {
-    let pos = global.translation();
-    #[cfg(not(feature = "debug"))]
-    ship.update(&water, pos, &mut transform);
-    #[cfg(feature = "debug")]
-    ship.update(&water, pos, &mut transform, &mut lines);
+  for (buoyancy, global) in ships.iter() {
+    // Centre of buoyancy and centre of mass.
+    let (volume, center) = buoyancy.submerged(&water, global);
+    if volume > 0.0 {
+      lines.line_colored(center, center + Vec3::Y * volume.cbrt(), 0.0, Color::GREEN);
+    }
+    let center_of_mass = global.transform_point(buoyancy.center_of_mass);
+    let up = global.compute_transform().rotation * Vec3::Y;
+    lines.line_colored(center_of_mass, center_of_mass + up, 0.0, Color::RED);
   }
 }
 
@@ -481,7 +459,16 @@ fn setup(
 
   // Spawn ships.
   let scene = asset_server.load("models/dutch_ship_medium_1k/dutch_ship_medium_1k.gltf#Scene0");
-  let ship = Ship::new(-0.400, -8.0, 9.0, -2.0, 2.0);
+  // Box hull of the ship model, 70% submerged so the model's origin floats 0.4m below the water.
+  let hull_half_extents = Vec3::new(2.0, 1.0, 8.5);
+  let hull_volume = hull_half_extents.x * hull_half_extents.y * hull_half_extents.z * 8.0;
+  let ship = Buoyancy::from_hull(
+    Vec3::new(0.0, 0.0, 0.5),
+    hull_half_extents,
+    UVec3::new(3, 4, 12),
+    1025.0 * hull_volume * 0.7,
+  )
+  .with_center_of_mass(Vec3::new(0.0, -0.6, 0.5));
 
   // "Randomly" place the ships.
   for x in 1..10 {
@@ -489,7 +476,20 @@ fn setup(
     let f2 = ((x % 6) as f32) * -20.90;
     commands
       .spawn(ShipBundle {
-        ship: ship.clone(),
+        buoyancy: BuoyancyBundle {
+          buoyancy: ship.clone(),
+          ..default()
+        },
+        sail: Sail {
+          speed: 2.0 + (x % 3) as f32,
+          turn: 0.02 * (x % 5) as f32 - 0.04,
+        },
+        wake: Wake {
+          half_width: 2.0,
+          lifetime: 20.0,
+          spacing: 4.0,
+          ..default()
+        },
         name: Name::new(format!("Dutch Ship {x}")),
         spatial: SpatialBundle {
           transform: Transform::from_xyz(-10.0 + (f * 7.8), 0.0, 30.0 + f2)
@@ -32,6 +32,7 @@ fn main() {
     .insert_resource(WaterSettings {
       amplitude: 0.1,
       // amplitude: 10.0,
+      clipmap: None,
       spawn_tiles: None,
       ..default()
     })
@@ -42,7 +43,7 @@ fn main() {
     // Wireframe
     .add_plugins(WireframePlugin)
     .add_plugins(PanOrbitCameraPlugin)
-    .add_systems(Startup, (setup, setup_caustics))
+    .add_systems(Startup, (setup, setup_caustics).chain())
     .add_systems(
       Update,
       toggle_wireframe.run_if(common_conditions::input_just_pressed(KeyCode::KeyR)),
@@ -61,6 +62,7 @@ fn setup_caustics(
   mut caustics_materials: ResMut<Assets<CausticsWaterMaterial>>,
   mut underwater_materials: ResMut<Assets<UnderwaterMaterial>>,
   mut images: ResMut<Assets<Image>>,
+  cameras: Query<Entity, With<PanOrbitCamera>>,
 ) {
   // let size = Extent3d {
   //   width: 512,
@@ -79,7 +81,8 @@ fn setup_caustics(
       size,
       dimension: TextureDimension::D2,
       // format: TextureFormat::Bgra8UnormSrgb,
-      format: TextureFormat::Rgba32Float,
+      // Filterable, the god rays sample it linearly.
+      format: TextureFormat::Rgba16Float,
       mip_level_count: 1,
       sample_count: 1,
       usage: TextureUsages::TEXTURE_BINDING
@@ -183,6 +186,21 @@ fn setup_caustics(
     },
     caustics_pass_layer,
   ));
+
+  // Fog and light shafts from the caustics below the water.
+  for camera in cameras.iter() {
+    commands.entity(camera).insert((
+      UnderwaterPostProcess::default(),
+      UnderwaterGodRays {
+        caustics_texture: image_handle.clone(),
+        water_world_to_uv: Mat4::from_translation(Vec3::new(0.5, 0.0, 0.5))
+          * Mat4::from_scale(Vec3::new(1.0 / size, 1.0, 1.0 / size)),
+        intensity: 0.5,
+        max_distance: 5.0,
+        ..default()
+      },
+    ));
+  }
 }
 
 fn toggle_wireframe(
@@ -19,6 +19,7 @@ fn main() {
   app
     .add_plugins(DefaultPlugins)
     .insert_resource(WaterSettings {
+      clipmap: None,
       spawn_tiles: None,
       ..default()
     })
@@ -0,0 +1,356 @@
+//! Buoyancy and water drag for floating bodies.
+//!
+//! A `Buoyancy` body is sampled as a set of small cubes (`BuoyancyPoint`) against
+//! `WaterParam::try_wave_height`.  Each submerged cube pushes the body up at its position,
+//! which gives the force and torque that make boats pitch, roll and settle.
+//!
+//! `BuoyancyPlugin` integrates bodies that have a `BuoyancyVelocity`.  The forces can
+//! also be used with a physics engine, see `Buoyancy::forces_with` and the `physics` module.
+use bevy::prelude::*;
+
+use crate::WaterParam;
+
+/// Largest time step used when integrating the bodies.
+const MAX_STEP: f32 = 1.0 / 60.0;
+
+/// Global buoyancy settings.
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct BuoyancySettings {
+  /// Gravity applied by `BuoyancyPlugin` to the bodies it integrates.
+  pub gravity: Vec3,
+  /// Water density (kg/m^3).
+  pub water_density: f32,
+}
+
+impl Default for BuoyancySettings {
+  fn default() -> Self {
+    Self {
+      gravity: Vec3::new(0.0, -9.81, 0.0),
+      water_density: 1025.0,
+    }
+  }
+}
+
+/// A sample of the body's volume: a cube centred at `position`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub struct BuoyancyPoint {
+  /// Local position of the cube centre.
+  pub position: Vec3,
+  /// Volume of the cube (m^3).
+  pub volume: f32,
+}
+
+impl BuoyancyPoint {
+  /// Fraction of the cube below the water `height` when its centre is at `y`.
+  pub fn submerged_fraction(&self, y: f32, height: f32) -> f32 {
+    let size = self.volume.cbrt().max(f32::EPSILON);
+    ((height - (y - size / 2.0)) / size).clamp(0.0, 1.0)
+  }
+
+  /// Fraction of the cube below the waves with its centre at the world `position`,
+  /// 0.0 where there is no water.
+  fn submerged_fraction_in(&self, water: &WaterParam, position: Vec3) -> f32 {
+    water
+      .try_wave_height(position)
+      .map_or(0.0, |height| self.submerged_fraction(position.y, height))
+  }
+}
+
+/// A floating body.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Buoyancy {
+  /// Samples of the body's volume.
+  pub points: Vec<BuoyancyPoint>,
+  /// Mass of the body (kg).
+  pub mass: f32,
+  /// Local centre of mass.
+  pub center_of_mass: Vec3,
+  /// Moments of inertia around the local axes (through the centre of mass).
+  pub inertia: Vec3,
+  /// Linear water drag (1/s) when fully submerged.
+  pub linear_drag: f32,
+  /// Angular water drag (1/s) when fully submerged.
+  pub angular_drag: f32,
+}
+
+impl Default for Buoyancy {
+  fn default() -> Self {
+    Self::from_hull(Vec3::ZERO, Vec3::splat(0.5), UVec3::splat(2), 500.0)
+  }
+}
+
+impl Buoyancy {
+  /// A body sampled at the given local points, each one representing `volume` of the hull.
+  pub fn from_points(points: impl IntoIterator<Item = Vec3>, volume: f32, mass: f32) -> Self {
+    let points: Vec<_> = points
+      .into_iter()
+      .map(|position| BuoyancyPoint { position, volume })
+      .collect();
+    let count = points.len().max(1) as f32;
+    let center_of_mass = points.iter().map(|p| p.position).sum::<Vec3>() / count;
+    let mut buoyancy = Self {
+      points,
+      mass,
+      center_of_mass,
+      inertia: Vec3::ZERO,
+      linear_drag: 1.0,
+      angular_drag: 1.0,
+    };
+    buoyancy.inertia = buoyancy.points_inertia();
+    buoyancy
+  }
+
+  /// A box shaped hull centred at `center`, sampled by a grid of `resolution` cubes.
+  pub fn from_hull(center: Vec3, half_extents: Vec3, resolution: UVec3, mass: f32) -> Self {
+    let resolution = resolution.max(UVec3::ONE);
+    let cell = half_extents * 2.0 / resolution.as_vec3();
+    let corner = center - half_extents + cell / 2.0;
+    let points = (0..resolution.x).flat_map(|x| {
+      (0..resolution.y).flat_map(move |y| {
+        (0..resolution.z).map(move |z| corner + UVec3::new(x, y, z).as_vec3() * cell)
+      })
+    });
+    let size = half_extents * 2.0;
+    let mut buoyancy = Self::from_points(points, cell.x * cell.y * cell.z, mass);
+    buoyancy.center_of_mass = center;
+    buoyancy.inertia = Vec3::new(
+      size.y * size.y + size.z * size.z,
+      size.x * size.x + size.z * size.z,
+      size.x * size.x + size.y * size.y,
+    ) * (mass / 12.0);
+    buoyancy
+  }
+
+  /// A body filling the parts of the box `min`..`max` (local space) where `contains` is true,
+  /// sampled by a grid of `resolution` cubes.
+  ///
+  /// Used to sample the shape of physics colliders.
+  pub fn from_shape(
+    min: Vec3,
+    max: Vec3,
+    resolution: UVec3,
+    mass: f32,
+    contains: impl Fn(Vec3) -> bool,
+  ) -> Self {
+    let mut buoyancy = Self::from_hull((min + max) / 2.0, (max - min) / 2.0, resolution, mass);
+    buoyancy.points.retain(|point| contains(point.position));
+    buoyancy
+  }
+
+  /// Set the centre of mass.  A low centre of mass keeps the body upright.
+  pub fn with_center_of_mass(mut self, center_of_mass: Vec3) -> Self {
+    self.center_of_mass = center_of_mass;
+    self
+  }
+
+  /// Set the linear and angular water drag.
+  pub fn with_drag(mut self, linear: f32, angular: f32) -> Self {
+    self.linear_drag = linear;
+    self.angular_drag = angular;
+    self
+  }
+
+  /// Inertia of the points as equal point masses around the centre of mass.
+  fn points_inertia(&self) -> Vec3 {
+    let mass = self.mass / self.points.len().max(1) as f32;
+    self.points.iter().fold(Vec3::ZERO, |inertia, point| {
+      let r = point.position - self.center_of_mass;
+      inertia
+        + Vec3::new(
+          r.y * r.y + r.z * r.z,
+          r.x * r.x + r.z * r.z,
+          r.x * r.x + r.y * r.y,
+        ) * mass
+    })
+  }
+
+  /// Total volume of the body.
+  pub fn volume(&self) -> f32 {
+    self.points.iter().map(|point| point.volume).sum()
+  }
+
+  /// Submerged volume of the body and its world space centre (centre of buoyancy).
+  pub fn submerged(&self, water: &WaterParam, transform: &GlobalTransform) -> (f32, Vec3) {
+    let mut volume = 0.0;
+    let mut center = Vec3::ZERO;
+    for point in &self.points {
+      let position = transform.transform_point(point.position);
+      let submerged = point.volume * point.submerged_fraction_in(water, position);
+      volume += submerged;
+      center += position * submerged;
+    }
+    if volume > 0.0 {
+      center /= volume;
+    }
+    (volume, center)
+  }
+
+  /// Buoyant and drag force and torque (around the world space centre of mass).
+  ///
+  /// Gravity isn't included.
+  pub fn forces(
+    &self,
+    water: &WaterParam,
+    settings: &BuoyancySettings,
+    transform: &GlobalTransform,
+    velocity: &BuoyancyVelocity,
+  ) -> BuoyancyForces {
+    let mass = WorldMassProperties {
+      mass: self.mass,
+      center_of_mass: transform.transform_point(self.center_of_mass),
+      inertia: world_inertia(transform, self.inertia),
+    };
+    self.forces_with(water, settings, transform, &mass, velocity)
+  }
+
+  /// Like `forces`, but with the mass properties of a physics engine instead of the ones
+  /// of the `Buoyancy`.
+  pub fn forces_with(
+    &self,
+    water: &WaterParam,
+    settings: &BuoyancySettings,
+    transform: &GlobalTransform,
+    mass: &WorldMassProperties,
+    velocity: &BuoyancyVelocity,
+  ) -> BuoyancyForces {
+    let center_of_mass = mass.center_of_mass;
+    let up = -settings.gravity.normalize_or_zero();
+    let gravity = settings.gravity.length();
+    let total_volume = self.volume().max(f32::EPSILON);
+
+    let mut forces = BuoyancyForces::default();
+    let mut submerged_volume = 0.0;
+    for point in &self.points {
+      let position = transform.transform_point(point.position);
+      let fraction = point.submerged_fraction_in(water, position);
+      if fraction <= 0.0 {
+        continue;
+      }
+      let volume = point.volume * fraction;
+      submerged_volume += volume;
+
+      let r = position - center_of_mass;
+      // Drag relative to the water, so the flow carries the body along.
+      let point_velocity = velocity.linear + velocity.angular.cross(r);
+      let relative_velocity = point_velocity - water.flow_velocity(position);
+      let buoyant = up * (settings.water_density * gravity * volume);
+      let drag = -relative_velocity * (self.linear_drag * mass.mass * volume / total_volume);
+      let force = buoyant + drag;
+      forces.force += force;
+      forces.torque += r.cross(force);
+    }
+
+    let submerged = submerged_volume / total_volume;
+    forces.torque -= mass.inertia * velocity.angular * (self.angular_drag * submerged);
+    forces.submerged = submerged;
+    forces
+  }
+}
+
+/// Mass properties of a body in world space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldMassProperties {
+  /// Mass (kg).
+  pub mass: f32,
+  /// World space centre of mass.
+  pub center_of_mass: Vec3,
+  /// World space inertia tensor.
+  pub inertia: Mat3,
+}
+
+/// Forces on a `Buoyancy` body.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BuoyancyForces {
+  /// World space force.
+  pub force: Vec3,
+  /// World space torque around the centre of mass.
+  pub torque: Vec3,
+  /// Submerged fraction of the body's volume.
+  pub submerged: f32,
+}
+
+/// World space velocity of a `Buoyancy` body integrated by `BuoyancyPlugin`.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct BuoyancyVelocity {
+  /// Velocity of the centre of mass.
+  pub linear: Vec3,
+  /// Angular velocity (axis * radians per second).
+  pub angular: Vec3,
+}
+
+/// Inertia tensor in world space.
+pub(crate) fn world_inertia(transform: &GlobalTransform, inertia: Vec3) -> Mat3 {
+  let rotation = Mat3::from_quat(transform.compute_transform().rotation);
+  rotation * Mat3::from_diagonal(inertia) * rotation.transpose()
+}
+
+/// Inverse of the inertia tensor in world space.
+///
+/// Axes without inertia don't rotate, small bodies still do.
+fn world_inverse_inertia(transform: &GlobalTransform, inertia: Vec3) -> Mat3 {
+  let rotation = Mat3::from_quat(transform.compute_transform().rotation);
+  let inverse = Vec3::select(inertia.cmpeq(Vec3::ZERO), Vec3::ZERO, inertia.recip());
+  rotation * Mat3::from_diagonal(inverse) * rotation.transpose()
+}
+
+/// Bundle of a floating body integrated by `BuoyancyPlugin`.
+#[derive(Bundle, Default)]
+pub struct BuoyancyBundle {
+  pub buoyancy: Buoyancy,
+  pub velocity: BuoyancyVelocity,
+}
+
+/// Integrate the floating bodies.
+///
+/// The bodies should be root entities, their `Transform` is moved.
+fn update_buoyancy(
+  time: Res<Time>,
+  water: WaterParam,
+  settings: Res<BuoyancySettings>,
+  mut bodies: Query<(&Buoyancy, &mut BuoyancyVelocity, &mut Transform)>,
+) {
+  let delta = time.delta_seconds();
+  if delta <= 0.0 {
+    return;
+  }
+  let steps = (delta / MAX_STEP).ceil().min(8.0);
+  let dt = delta / steps;
+  for (buoyancy, mut velocity, mut transform) in bodies.iter_mut() {
+    if buoyancy.mass <= 0.0 {
+      continue;
+    }
+    for _ in 0..steps as u32 {
+      let global = GlobalTransform::from(*transform);
+      let forces = buoyancy.forces(&water, &settings, &global, &velocity);
+
+      velocity.linear += (forces.force / buoyancy.mass + settings.gravity) * dt;
+      velocity.angular +=
+        world_inverse_inertia(&global, buoyancy.inertia) * forces.torque * dt;
+
+      // Move and rotate around the centre of mass.
+      let center_of_mass = global.transform_point(buoyancy.center_of_mass) + velocity.linear * dt;
+      transform.rotation =
+        (Quat::from_scaled_axis(velocity.angular * dt) * transform.rotation).normalize();
+      transform.translation =
+        center_of_mass - transform.rotation * (buoyancy.center_of_mass * transform.scale);
+    }
+  }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct BuoyancyPlugin;
+
+impl Plugin for BuoyancyPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<BuoyancySettings>()
+      .register_type::<BuoyancySettings>()
+      .register_type::<Buoyancy>()
+      .register_type::<BuoyancyPoint>()
+      .register_type::<BuoyancyVelocity>()
+      .add_systems(Update, update_buoyancy);
+  }
+}
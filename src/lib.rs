@@ -9,3 +9,8 @@ pub use wave::*;
 
 mod param;
 pub use param::WaterParam;
+
+mod buoyancy;
+pub use buoyancy::*;
+
+pub mod physics;
@@ -1,15 +1,75 @@
 use bevy::{ecs::system::SystemParam, math::Vec3Swizzles, prelude::*};
 
-use crate::{water::WaterSettings, wave::get_wave_height_2d};
+use crate::{
+  water::{
+    fft::FftOceanState, RiverSurface, WaterBody, WaterDepthMap, WaterFlowMap, WaterSettings,
+  },
+  wave::WaveModel,
+};
 
-/// A system parameter used to calculate wave height and point based on global WaterSettings and Time resources.
+/// A system parameter used to calculate wave height and point based on the `WaterBody`
+/// components, the global WaterSettings and Time resources.
 #[derive(SystemParam)]
-pub struct WaterParam<'w> {
+pub struct WaterParam<'w, 's> {
   pub settings: Res<'w, WaterSettings>,
   pub time: Res<'w, Time>,
+  /// CPU side of the FFT ocean, used by `WaveModel::Fft`.
+  pub fft: Option<Res<'w, FftOceanState>>,
+  /// The water bodies, with the surface of the rivers.
+  pub bodies: Query<'w, 's, (&'static WaterBody, Option<&'static RiverSurface>)>,
+  /// Images of the `WaterDepthMap`s and `WaterFlowMap`s.
+  pub images: Option<Res<'w, Assets<Image>>>,
 }
 
-impl<'w> WaterParam<'w> {
+impl<'w, 's> WaterParam<'w, 's> {
+  /// The water body covering the given position.
+  ///
+  /// When bodies overlap, this is the lowest one with its surface (`height + amplitude`)
+  /// above the position, otherwise the highest one.  `None` if no body covers the
+  /// position, the global `WaterSettings` are used there (see `has_water`).
+  pub fn water_body(&self, position: Vec3) -> Option<&WaterBody> {
+    self.covering_body(position).map(|(body, _)| body)
+  }
+
+  /// The water body covering the position, and its rest height there.
+  fn covering_body(&self, position: Vec3) -> Option<(&WaterBody, f32)> {
+    let mut covering = self.bodies.iter().filter_map(|(body, river)| {
+      if !body.bounds.contains(position.xz()) {
+        return None;
+      }
+      // Rivers follow the height of their spline.
+      let height = match river {
+        Some(river) => river.sample(position.xz())?.height(),
+        None => body.height,
+      };
+      Some((body, height))
+    });
+    let first = covering.next()?;
+    let above = |(body, height): (&WaterBody, f32)| height + body.amplitude.abs() >= position.y;
+    Some(
+      covering.fold(first, |best, body| match (above(best), above(body)) {
+        (true, true) if body.1 < best.1 => body,
+        (false, true) => body,
+        (false, false) if body.1 > best.1 => body,
+        _ => best,
+      }),
+    )
+  }
+
+  /// Is there water at the position (`x`, `z`).
+  ///
+  /// Always with `WaterSettings::global_water`, otherwise only inside the `WaterBody`s.
+  pub fn has_water(&self, position: Vec3) -> bool {
+    self.settings.global_water || self.covering_body(position).is_some()
+  }
+
+  /// Height of the undisturbed water surface at the position.
+  fn rest_height(&self, position: Vec3) -> f32 {
+    self
+      .covering_body(position)
+      .map_or(self.settings.height, |(_, height)| height)
+  }
+
   /// Calculates the height of the waves at the given position.
   ///
   /// # Arguments
@@ -18,10 +78,104 @@ impl<'w> WaterParam<'w> {
   ///
   /// # Returns
   ///
-  /// The height of the waves at the given global position.
+  /// The height of the waves at the given global position.  Where there is no water
+  /// (see `has_water`) this is the `WaterSettings::height`, use `try_wave_height` to tell.
   pub fn wave_height(&self, position: Vec3) -> f32 {
+    self
+      .try_wave_height(position)
+      .unwrap_or(self.settings.height)
+  }
+
+  /// The height of the waves at the given position, `None` where there is no water.
+  pub fn try_wave_height(&self, position: Vec3) -> Option<f32> {
+    let (height, mut amplitude, wave_model) = match self.covering_body(position) {
+      Some((body, height)) => (height, body.amplitude, &body.wave_model),
+      None if !self.settings.global_water => return None,
+      None => (
+        self.settings.height,
+        self.settings.amplitude,
+        &self.settings.wave_model,
+      ),
+    };
+    // Waves shaped by the water depth, like the water shader.
+    if let Some((map, depth)) = self.baked_depth(position) {
+      amplitude *= map.wave_scale(depth);
+    }
     let time = self.time.elapsed_seconds_wrapped();
-    self.settings.height + self.settings.amplitude * get_wave_height_2d(time, position.xz())
+    let wave = |p: Vec2| match wave_model {
+      WaveModel::Fft => self
+        .fft
+        .as_ref()
+        .map_or(0.0, |fft| fft.height(amplitude, p)),
+      model => model.height(time, amplitude, p),
+    };
+    // Waves carried by the flow, like the water shader.
+    if let Some((map, velocity)) = self.flow(position) {
+      let phases = map.phases(velocity, time);
+      let [p0, p1] = phases.offsets.map(|offset| position.xz() + offset);
+      return Some(height + wave(p0).lerp(wave(p1), phases.blend));
+    }
+    Some(height + wave(position.xz()))
+  }
+
+  /// Depth (meters) of the position below the wave surface.
+  ///
+  /// Negative above the surface, and `None` where there is no water.
+  pub fn depth_below_surface(&self, position: Vec3) -> Option<f32> {
+    Some(self.try_wave_height(position)? - position.y)
+  }
+
+  /// Is the position below the wave surface.
+  ///
+  /// A position exactly on the surface, or where there is no water, isn't under water.
+  pub fn is_underwater(&self, position: Vec3) -> bool {
+    self
+      .depth_below_surface(position)
+      .is_some_and(|depth| depth > 0.0)
+  }
+
+  /// The flow map of the water at the position, and the flow velocity (`x`, `z`).
+  fn flow(&self, position: Vec3) -> Option<(&WaterFlowMap, Vec2)> {
+    let map = match self.water_body(position) {
+      Some(body) => body.flow_map.as_ref(),
+      None if self.settings.global_water => self.settings.flow_map.as_ref(),
+      None => None,
+    }?;
+    let velocity = map.velocity(self.images.as_deref()?, position.xz())?;
+    Some((map, velocity))
+  }
+
+  /// Velocity (m/s) of the water flow at the given position.
+  ///
+  /// Horizontal, and zero without a `WaterFlowMap` for the position.
+  pub fn flow_velocity(&self, position: Vec3) -> Vec3 {
+    self.flow(position).map_or(Vec3::ZERO, |(_, velocity)| {
+      Vec3::new(velocity.x, 0.0, velocity.y)
+    })
+  }
+
+  /// The depth map of the water at the position, and the baked depth below the rest surface.
+  fn baked_depth(&self, position: Vec3) -> Option<(&WaterDepthMap, f32)> {
+    let map = match self.water_body(position) {
+      Some(body) => body.depth_map.as_ref(),
+      None if self.settings.global_water => self.settings.depth_map.as_ref(),
+      None => None,
+    }?;
+    let depth = map.depth(self.images.as_deref()?, position.xz())?;
+    Some((map, depth))
+  }
+
+  /// Depth of the water at the given position, from the wave surface down to the ground.
+  ///
+  /// 0.0 on dry land, and the baked depth is clamped at the `max_depth` of the map.
+  /// `None` without a baked `WaterDepthMap` for the position.
+  pub fn water_depth(&self, position: Vec3) -> Option<f32> {
+    if !self.has_water(position) {
+      return Some(0.0);
+    }
+    let (_, depth) = self.baked_depth(position)?;
+    let ground = self.rest_height(position) - depth;
+    Some((self.wave_height(position) - ground).max(0.0))
   }
 
   /// Calculates the point of the waves at the given position.
@@ -32,12 +186,19 @@ impl<'w> WaterParam<'w> {
   ///
   /// # Returns
   ///
-  /// A point on the surface of the waves at the given global position.
+  /// A point on the surface of the waves at the given global position, at the
+  /// `WaterSettings::height` where there is no water.
   pub fn wave_point(&self, mut position: Vec3) -> Vec3 {
     position.y = self.wave_height(position);
     position
   }
 
+  /// The point of the waves at the given position, `None` where there is no water.
+  pub fn try_wave_point(&self, mut position: Vec3) -> Option<Vec3> {
+    position.y = self.try_wave_height(position)?;
+    Some(position)
+  }
+
   /// Calculates the normal vector for a given point on the water surface.
   ///
   /// # Arguments
@@ -51,10 +212,15 @@ impl<'w> WaterParam<'w> {
   /// # Details
   ///
   /// Uses first order forward difference with step size 1 to calculate the change in wave position.
+  /// Up where there is no water.
   pub fn wave_normal(&self, position: Vec3) -> Vec3 {
-    let h = self.wave_height(position);
-    let h_dx = self.wave_height(position + Vec3::X);
-    let h_dz = self.wave_height(position + Vec3::Z);
+    let Some(h) = self.try_wave_height(position) else {
+      return Vec3::Y;
+    };
+    // Flat towards the shore.
+    let height = |p: Vec3| self.try_wave_height(p).unwrap_or(h);
+    let h_dx = height(position + Vec3::X);
+    let h_dz = height(position + Vec3::Z);
 
     // negative cross product of the change in wave position
     Vec3::new(h - h_dx, 1., h - h_dz).normalize()
@@ -0,0 +1,34 @@
+//! Buoyancy for physics engines.
+//!
+//! Enable the `rapier` (`bevy_rapier3d`) or `xpbd` (`bevy_xpbd_3d`) feature and add the
+//! matching plugin.  Rigid bodies with a `Buoyancy` get the buoyant force, torque and water
+//! drag as external forces every frame, using the engine's mass properties and velocity.
+//! Add `ColliderBuoyancy` to a body to generate its `Buoyancy` samples from its collider.
+use bevy::prelude::*;
+
+#[cfg(feature = "rapier")]
+pub mod rapier;
+#[cfg(feature = "xpbd")]
+pub mod xpbd;
+
+/// Generate the `Buoyancy` of a rigid body from its collider.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct ColliderBuoyancy {
+  /// Number of samples along each axis of the collider's bounding box.
+  pub resolution: UVec3,
+  /// Linear water drag (1/s) when fully submerged.
+  pub linear_drag: f32,
+  /// Angular water drag (1/s) when fully submerged.
+  pub angular_drag: f32,
+}
+
+impl Default for ColliderBuoyancy {
+  fn default() -> Self {
+    Self {
+      resolution: UVec3::splat(4),
+      linear_drag: 1.0,
+      angular_drag: 1.0,
+    }
+  }
+}
@@ -0,0 +1,106 @@
+//! `bevy_rapier3d` adapter.
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::ColliderBuoyancy;
+use crate::{Buoyancy, BuoyancySettings, BuoyancyVelocity, WaterParam, WorldMassProperties};
+
+/// Give rigid bodies with a `ColliderBuoyancy` their `Buoyancy` and the components used
+/// to apply it.
+#[allow(clippy::type_complexity)]
+fn add_collider_buoyancy(
+  mut commands: Commands,
+  bodies: Query<
+    (
+      Entity,
+      &ColliderBuoyancy,
+      &Collider,
+      Has<ExternalForce>,
+      Has<Velocity>,
+      Has<ReadMassProperties>,
+    ),
+    Without<Buoyancy>,
+  >,
+) {
+  for (entity, settings, collider, has_force, has_velocity, has_mass) in bodies.iter() {
+    let aabb = collider.raw.compute_local_aabb();
+    let buoyancy = Buoyancy::from_shape(
+      aabb.mins.into(),
+      aabb.maxs.into(),
+      settings.resolution,
+      0.0,
+      |point| collider.contains_point(Vec3::ZERO, Quat::IDENTITY, point),
+    )
+    .with_drag(settings.linear_drag, settings.angular_drag);
+
+    let mut entity = commands.entity(entity);
+    entity.insert(buoyancy);
+    if !has_force {
+      entity.insert(ExternalForce::default());
+    }
+    if !has_velocity {
+      entity.insert(Velocity::default());
+    }
+    if !has_mass {
+      entity.insert(ReadMassProperties::default());
+    }
+  }
+}
+
+/// Apply the buoyancy as the `ExternalForce` of the rigid bodies.
+///
+/// The `ExternalForce` is overwritten every frame.
+fn apply_buoyancy(
+  water: WaterParam,
+  settings: Res<BuoyancySettings>,
+  config: Res<RapierConfiguration>,
+  mut bodies: Query<(
+    &Buoyancy,
+    &GlobalTransform,
+    &Velocity,
+    &ReadMassProperties,
+    &mut ExternalForce,
+  )>,
+) {
+  let settings = BuoyancySettings {
+    gravity: config.gravity,
+    ..settings.clone()
+  };
+  for (buoyancy, transform, velocity, mass, mut external) in bodies.iter_mut() {
+    let mass = mass.get();
+    let rotation = transform.compute_transform().rotation;
+    let frame = Mat3::from_quat(rotation * mass.principal_inertia_local_frame);
+    let mass = WorldMassProperties {
+      mass: mass.mass,
+      center_of_mass: transform.transform_point(mass.local_center_of_mass),
+      inertia: frame * Mat3::from_diagonal(mass.principal_inertia) * frame.transpose(),
+    };
+    let velocity = BuoyancyVelocity {
+      linear: velocity.linvel,
+      angular: velocity.angvel,
+    };
+    let forces = buoyancy.forces_with(&water, &settings, transform, &mass, &velocity);
+    external.force = forces.force;
+    external.torque = forces.torque;
+  }
+}
+
+/// Apply `Buoyancy` to `bevy_rapier3d` rigid bodies.
+///
+/// The buoyant force uses the gravity of the `RapierConfiguration`.
+#[derive(Default, Clone, Debug)]
+pub struct RapierBuoyancyPlugin;
+
+impl Plugin for RapierBuoyancyPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<BuoyancySettings>()
+      .register_type::<ColliderBuoyancy>()
+      .add_systems(
+        PostUpdate,
+        (add_collider_buoyancy, apply_buoyancy)
+          .chain()
+          .before(PhysicsSet::SyncBackend),
+      );
+  }
+}
@@ -0,0 +1,116 @@
+//! `bevy_xpbd_3d` adapter.
+use bevy::prelude::*;
+use bevy_xpbd_3d::prelude::*;
+
+use super::ColliderBuoyancy;
+use crate::{Buoyancy, BuoyancySettings, BuoyancyVelocity, WaterParam, WorldMassProperties};
+
+/// Give rigid bodies with a `ColliderBuoyancy` their `Buoyancy` and the components used
+/// to apply it.
+#[allow(clippy::type_complexity)]
+fn add_collider_buoyancy(
+  mut commands: Commands,
+  bodies: Query<
+    (
+      Entity,
+      &ColliderBuoyancy,
+      &Collider,
+      Has<ExternalForce>,
+      Has<ExternalTorque>,
+    ),
+    Without<Buoyancy>,
+  >,
+) {
+  for (entity, settings, collider, has_force, has_torque) in bodies.iter() {
+    let aabb = collider.shape_scaled().compute_local_aabb();
+    let buoyancy = Buoyancy::from_shape(
+      aabb.mins.into(),
+      aabb.maxs.into(),
+      settings.resolution,
+      0.0,
+      |point| collider.contains_point(Position::default(), Rotation::default(), point),
+    )
+    .with_drag(settings.linear_drag, settings.angular_drag);
+
+    let mut entity = commands.entity(entity);
+    entity.insert(buoyancy);
+    if !has_force {
+      entity.insert(ExternalForce::default());
+    }
+    if !has_torque {
+      entity.insert(ExternalTorque::default());
+    }
+  }
+}
+
+/// Apply the buoyancy as the `ExternalForce` and `ExternalTorque` of the rigid bodies.
+///
+/// The external force and torque are overwritten every frame.
+#[allow(clippy::type_complexity)]
+fn apply_buoyancy(
+  water: WaterParam,
+  settings: Res<BuoyancySettings>,
+  gravity: Res<Gravity>,
+  mut bodies: Query<(
+    &Buoyancy,
+    &GlobalTransform,
+    &LinearVelocity,
+    &AngularVelocity,
+    &Mass,
+    &Inertia,
+    &CenterOfMass,
+    &mut ExternalForce,
+    &mut ExternalTorque,
+  )>,
+) {
+  let settings = BuoyancySettings {
+    gravity: gravity.0,
+    ..settings.clone()
+  };
+  for (
+    buoyancy,
+    transform,
+    linear,
+    angular,
+    mass,
+    inertia,
+    center_of_mass,
+    mut force,
+    mut torque,
+  ) in bodies.iter_mut()
+  {
+    let rotation = Mat3::from_quat(transform.compute_transform().rotation);
+    let mass = WorldMassProperties {
+      mass: mass.0,
+      center_of_mass: transform.transform_point(center_of_mass.0),
+      inertia: rotation * inertia.0 * rotation.transpose(),
+    };
+    let velocity = BuoyancyVelocity {
+      linear: linear.0,
+      angular: angular.0,
+    };
+    let forces = buoyancy.forces_with(&water, &settings, transform, &mass, &velocity);
+    force.set_force(forces.force);
+    torque.set_torque(forces.torque);
+  }
+}
+
+/// Apply `Buoyancy` to `bevy_xpbd_3d` rigid bodies.
+///
+/// The buoyant force uses the `Gravity` of the physics world.
+#[derive(Default, Clone, Debug)]
+pub struct XpbdBuoyancyPlugin;
+
+impl Plugin for XpbdBuoyancyPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .init_resource::<BuoyancySettings>()
+      .register_type::<ColliderBuoyancy>()
+      .add_systems(
+        PostUpdate,
+        (add_collider_buoyancy, apply_buoyancy)
+          .chain()
+          .before(PhysicsSet::Prepare),
+      );
+  }
+}
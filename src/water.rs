@@ -1,12 +1,49 @@
 use bevy::pbr::NotShadowCaster;
 use bevy::prelude::*;
+use bevy::render::camera::{camera_system, CameraProjectionPlugin, CameraUpdateSystem};
+use bevy::render::view::{update_frusta, VisibilitySystems};
+use bevy::transform::TransformSystem;
 
+use crate::wave::WaveModel;
+
+pub mod body;
+pub mod bounds;
 pub mod caustics;
+pub mod caustics_parallax;
+pub mod clipmap;
+pub mod data_texture;
+pub mod depth_map;
+pub mod fft;
+pub mod flow_map;
 pub mod material;
+pub mod planar_reflection;
+pub mod polygon;
+pub mod projected_grid;
+pub mod rain;
+pub mod river;
+pub mod ripples;
+pub mod sea_state;
+pub mod sensor;
 pub mod underwater;
-pub mod caustics_parallax;
+pub mod underwater_post;
+pub mod wake;
 use material::*;
 
+pub use body::*;
+pub use clipmap::*;
+pub use data_texture::{WaterDataImage, WaterDataTexture};
+pub use depth_map::*;
+pub use flow_map::*;
+pub use planar_reflection::*;
+pub use polygon::*;
+pub use projected_grid::*;
+pub use rain::*;
+pub use river::*;
+pub use ripples::*;
+pub use sensor::*;
+pub use underwater_post::*;
+pub use wake::*;
+
 pub const WATER_SIZE: u32 = 256;
 pub const WATER_HALF_SIZE: f32 = WATER_SIZE as f32 / 2.0;
 pub const WATER_GRID_SIZE: u32 = 6;
@@ -19,7 +56,13 @@ pub struct WaterSettings {
   /// Base water height.
   pub height: f32,
   /// Wave amplitude.
+  ///
+  /// Driven by the `SeaState` resource when it exists.
   pub amplitude: f32,
+  /// Wave model used by the water material and `WaterParam`.
+  ///
+  /// The `SeaState` resource (if it exists) sets the wave directions and lengths.
+  pub wave_model: WaveModel,
   /// The `StandardMaterial` base_color field.  This is the base color of the water.
   /// When using `DepthPrepass` it is recommended to use the `deep_color` and `shallow_color` fields.
   pub base_color: Color,
@@ -33,10 +76,36 @@ pub struct WaterSettings {
   pub edge_scale: f32,
   /// Color of the edge effect.
   pub edge_color: Color,
+  /// Screen space reflections, see `ScreenSpaceReflections`.
+  pub ssr: Option<ScreenSpaceReflections>,
+  /// Screen space refraction of the scene below the water, see `Refraction`.
+  ///
+  /// Replaces the `alpha_mode` blending.
+  pub refraction: Option<Refraction>,
+  /// Foam on the wave crests, see `Foam`.
+  pub foam: Option<Foam>,
+  /// Baked water depth for the shallow color, the shore edge and the wave damping without
+  /// a depth prepass, see `WaterDepthMap`.
+  pub depth_map: Option<WaterDepthMap>,
+  /// Simulate ripples around the camera, made by `WaterDisturber`s, see `WaterRipples`.
+  pub ripples: Option<WaterRipples>,
+  /// Scroll the waves along the flow of rivers and streams, see `WaterFlowMap`.
+  pub flow_map: Option<WaterFlowMap>,
+  /// There is water outside the `WaterBody`s, with these settings.
+  ///
+  /// Turn it off for inland water, `WaterParam` then finds no water outside the bodies
+  /// and the `clipmap`, `projected_grid` and `spawn_tiles` ocean isn't spawned.
+  pub global_water: bool,
   /// Update all `WaterMaterial`s from the global `WaterSettings` resource when it changes.
   ///
   /// This allows easy editing all materials.
   pub update_materials: bool,
+  /// During startup, spawn an ocean that follows the camera.
+  pub clipmap: Option<WaterClipmap>,
+  /// During startup, spawn a screen space grid projected onto the water plane.
+  ///
+  /// Usually used instead of the `clipmap`.
+  pub projected_grid: Option<WaterProjectedGrid>,
   /// During startup, spawn a 2d grid of water tiles.
   pub spawn_tiles: Option<UVec2>,
 }
@@ -47,14 +116,24 @@ impl Default for WaterSettings {
       alpha_mode: AlphaMode::Blend,
       height: 1.0,
       amplitude: 1.0,
+      wave_model: WaveModel::default(),
       clarity: 0.25,
       base_color: Color::rgba(1.0, 1.0, 1.0, 1.0),
       deep_color: Color::rgba(0.2, 0.41, 0.54, 1.0),
       shallow_color: Color::rgba(0.45, 0.78, 0.81, 1.0),
       edge_scale: 0.1,
       edge_color: Color::rgba(1.0, 1.0, 1.0, 1.0),
+      ssr: None,
+      refraction: None,
+      foam: None,
+      depth_map: None,
+      ripples: None,
+      flow_map: None,
+      global_water: true,
       update_materials: true,
-      spawn_tiles: Some(UVec2::new(WATER_GRID_SIZE, WATER_GRID_SIZE)),
+      clipmap: Some(WaterClipmap::default()),
+      projected_grid: None,
+      spawn_tiles: None,
     }
   }
 }
@@ -108,6 +187,22 @@ fn setup_water(
   mut meshes: ResMut<Assets<Mesh>>,
   mut materials: ResMut<Assets<StandardWaterMaterial>>,
 ) {
+  // Inland water only has its `WaterBody` meshes.
+  if !settings.global_water {
+    return;
+  }
+  if let Some(clipmap) = &settings.clipmap {
+    setup_clipmap(
+      &mut commands,
+      &settings,
+      clipmap,
+      &mut meshes,
+      &mut materials,
+    );
+  }
+  if let Some(grid) = &settings.projected_grid {
+    setup_projected_grid(&mut commands, &settings, grid, &mut meshes, &mut materials);
+  }
   let grid = match settings.spawn_tiles {
     Some(grid) => grid,
     None => {
@@ -115,6 +210,7 @@ fn setup_water(
     }
   };
   let water_height = settings.height;
+  let global = WaterBody::from(&*settings);
   // Generate mesh for water.
   let mesh: Handle<Mesh> = meshes.add(Mesh::from(shape::Plane {
     size: WATER_SIZE as f32,
@@ -134,25 +230,8 @@ fn setup_water(
           let y = (y * WATER_SIZE) as f32 - grid_center;
           // UV starts at (0,0) at the corner.
           let coord_offset = Vec2::new(x, y);
-          // Water material.
-          let material = materials.add(StandardWaterMaterial {
-            base: StandardMaterial {
-              base_color: settings.base_color,
-              perceptual_roughness: 0.22,
-              ..default()
-            },
-            extension: WaterMaterial {
-              amplitude: settings.amplitude,
-              clarity: settings.clarity,
-              deep_color: settings.deep_color,
-              shallow_color: settings.shallow_color,
-              edge_color: settings.edge_color,
-              edge_scale: settings.edge_scale,
-              coord_offset,
-              coord_scale: Vec2::new(WATER_SIZE as f32, WATER_SIZE as f32),
-              ..default()
-            },
-          });
+          let rect = Rect::from_corners(coord_offset, coord_offset + WATER_SIZE as f32);
+          let material = materials.add(global.material(rect));
 
           parent.spawn((
             WaterTileBundle::new(mesh.clone(), material, water_height, coord_offset),
@@ -165,21 +244,22 @@ fn setup_water(
 
 fn update_materials(
   settings: Res<WaterSettings>,
+  bodies: Query<Entity, With<WaterBody>>,
+  children: Query<&Children>,
+  handles: Query<&Handle<StandardWaterMaterial>>,
   mut materials: ResMut<Assets<StandardWaterMaterial>>,
 ) {
   if !settings.update_materials {
     // Don't update water materials.
     return;
   }
-  for (_, mat) in materials.iter_mut() {
-    mat.base.base_color = settings.base_color;
-    mat.base.alpha_mode = settings.alpha_mode;
-    mat.extension.amplitude = settings.amplitude;
-    mat.extension.clarity = settings.clarity;
-    mat.extension.deep_color = settings.deep_color;
-    mat.extension.shallow_color = settings.shallow_color;
-    mat.extension.edge_color = settings.edge_color;
-    mat.extension.edge_scale = settings.edge_scale;
+  // The materials of water bodies are updated from their `WaterBody`.
+  let owned = body::owned_materials(&bodies, &children, &handles);
+  let global = WaterBody::from(&*settings);
+  for (id, mat) in materials.iter_mut() {
+    if !owned.contains(&id) {
+      global.update_material(mat);
+    }
   }
 }
 
@@ -191,11 +271,77 @@ impl Plugin for WaterPlugin {
     app
       .init_resource::<WaterSettings>()
       .register_type::<WaterSettings>()
-      .add_plugins(WaterMaterialPlugin)
+      .register_type::<WaveModel>()
+      .register_type::<crate::wave::GerstnerWave>()
+      .register_type::<crate::wave::NoiseWaves>()
+      .register_type::<ScreenSpaceReflections>()
+      .register_type::<Refraction>()
+      .register_type::<Foam>()
+      .register_type::<WaterDepthMap>()
+      .register_type::<BakeWaterDepth>()
+      .register_type::<WaterFlowMap>()
+      .register_type::<RiverSpline>()
+      .register_type::<RiverSurface>()
+      .register_type::<WaterBody>()
+      .register_type::<WaterBounds>()
+      .register_type::<WaterClipmap>()
+      .register_type::<WaterClipmapFocus>()
+      .register_type::<WaterClipmapLevel>()
+      .register_type::<WaterProjectedGrid>()
+      .register_type::<WaterProjectedGridSurface>()
+      .register_type::<PlanarReflection>()
+      .register_type::<PlanarReflectionCamera>()
+      .add_plugins((
+        WaterMaterialPlugin,
+        fft::FftOceanPlugin,
+        sea_state::SeaStatePlugin,
+        ripples::WaterRipplesPlugin,
+        wake::WaterWakePlugin,
+        rain::WaterRainPlugin,
+        underwater_post::UnderwaterPostProcessPlugin,
+        sensor::WaterSensorPlugin,
+        CameraProjectionPlugin::<PlanarReflectionProjection>::default(),
+      ))
       .add_systems(Startup, setup_water)
       .add_systems(
         Update,
-        update_materials.run_if(resource_changed::<WaterSettings>),
+        update_materials
+          .run_if(resource_changed::<WaterSettings>)
+          .after(sea_state::update_sea_state),
+      )
+      .add_systems(
+        Update,
+        (
+          depth_map::bake_water_depth.before(body::update_body_materials),
+          river::update_rivers.before(body::update_body_materials),
+          body::update_body_materials,
+          material::warn_ignored_foam_decay,
+          clipmap::update_clipmap.run_if(resource_exists::<WaterClipmapMeshes>),
+          projected_grid::update_projected_grid.run_if(resource_changed::<WaterSettings>),
+          planar_reflection::setup_planar_reflections,
+          planar_reflection::update_planar_reflection_materials,
+          planar_reflection::cleanup_planar_reflections,
+        ),
+      )
+      .add_systems(
+        PostUpdate,
+        bounds::update_water_aabbs
+          .after(VisibilitySystems::CalculateBounds)
+          .after(TransformSystem::TransformPropagate)
+          .before(VisibilitySystems::CheckVisibility),
+      )
+      .add_systems(
+        PostUpdate,
+        (
+          planar_reflection::update_planar_reflections
+            .after(TransformSystem::TransformPropagate)
+            .before(CameraUpdateSystem),
+          update_frusta::<PlanarReflectionProjection>
+            .after(camera_system::<PlanarReflectionProjection>)
+            .after(TransformSystem::TransformPropagate)
+            .before(VisibilitySystems::CheckVisibility),
+          planar_reflection::hide_reflecting_bodies.after(VisibilitySystems::CheckVisibility),
+        ),
       );
   }
 }
@@ -0,0 +1,336 @@
+//! Per-entity bodies of water.
+//!
+//! A `WaterBody` has its own height, waves and colors, so a scene can contain several
+//! lakes at different elevations.  `WaterParam` uses the body that covers the queried
+//! position, and falls back to the global `WaterSettings` outside all bodies.
+use bevy::{
+  pbr::NotShadowCaster,
+  prelude::*,
+  render::{
+    mesh::{Indices, PrimitiveTopology},
+    render_asset::RenderAssetUsages,
+  },
+  utils::HashSet,
+};
+
+use super::{
+  depth_map::WaterDepthMap, flow_map::WaterFlowMap, material::*, polygon::water_polygon_mesh,
+  WaterSettings,
+};
+use crate::wave::WaveModel;
+
+/// Distance between the vertices of the generated water meshes.
+pub(crate) const GRID_SPACING: f32 = 4.0;
+
+/// The area covered by a `WaterBody` on the XZ plane.
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+pub enum WaterBounds {
+  /// Covers everything.
+  #[default]
+  Unbounded,
+  /// World space rectangle (`x`, `z`).
+  Rect(Rect),
+  /// World space polygon (`x`, `z`), like the outline of a river.
+  Polygon(Vec<Vec2>),
+}
+
+impl WaterBounds {
+  /// Is the world space point `p` (`x`, `z`) inside the bounds.
+  pub fn contains(&self, p: Vec2) -> bool {
+    match self {
+      Self::Unbounded => true,
+      Self::Rect(rect) => rect.contains(p),
+      Self::Polygon(points) => polygon_contains(points, p),
+    }
+  }
+
+  /// World space rectangle (`x`, `z`) around the bounds, `None` if unbounded.
+  pub fn rect(&self) -> Option<Rect> {
+    match self {
+      Self::Unbounded => None,
+      Self::Rect(rect) => Some(*rect),
+      Self::Polygon(points) => {
+        let first = *points.first()?;
+        Some(
+          points
+            .iter()
+            .fold(Rect::from_corners(first, first), |rect, p| {
+              rect.union_point(*p)
+            }),
+        )
+      }
+    }
+  }
+}
+
+/// Is the point `p` inside the polygon (even-odd rule).
+fn polygon_contains(points: &[Vec2], p: Vec2) -> bool {
+  let mut inside = false;
+  let mut j = points.len().wrapping_sub(1);
+  for (i, a) in points.iter().enumerate() {
+    let b = points[j];
+    if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y) {
+      inside = !inside;
+    }
+    j = i;
+  }
+  inside
+}
+
+/// A body of water (lake, pool, ...) with its own height, waves and colors.
+///
+/// The water materials of the entity and its descendants are updated when the body changes.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct WaterBody {
+  /// StandardMaterial setting.
+  pub alpha_mode: AlphaMode,
+  /// Base water height.
+  pub height: f32,
+  /// Wave amplitude.
+  pub amplitude: f32,
+  /// Wave model.
+  pub wave_model: WaveModel,
+  /// The `StandardMaterial` base_color field.
+  pub base_color: Color,
+  /// Water clarity, 0.0 = invisible.
+  pub clarity: f32,
+  /// Water color at deepest level.
+  pub deep_color: Color,
+  /// Water color at shallow areas.
+  pub shallow_color: Color,
+  /// Scale of the water edge effect.
+  pub edge_scale: f32,
+  /// Color of the edge effect.
+  pub edge_color: Color,
+  /// Screen space reflections.
+  pub ssr: Option<ScreenSpaceReflections>,
+  /// Screen space refraction.
+  pub refraction: Option<Refraction>,
+  /// Wave crest foam.
+  pub foam: Option<Foam>,
+  /// Baked water depth.
+  pub depth_map: Option<WaterDepthMap>,
+  /// Flow of the water.
+  pub flow_map: Option<WaterFlowMap>,
+  /// Area covered by the body.
+  pub bounds: WaterBounds,
+}
+
+impl Default for WaterBody {
+  fn default() -> Self {
+    Self::from(&WaterSettings::default())
+  }
+}
+
+impl From<&WaterSettings> for WaterBody {
+  fn from(settings: &WaterSettings) -> Self {
+    Self {
+      alpha_mode: settings.alpha_mode,
+      height: settings.height,
+      amplitude: settings.amplitude,
+      wave_model: settings.wave_model.clone(),
+      base_color: settings.base_color,
+      clarity: settings.clarity,
+      deep_color: settings.deep_color,
+      shallow_color: settings.shallow_color,
+      edge_scale: settings.edge_scale,
+      edge_color: settings.edge_color,
+      ssr: settings.ssr,
+      refraction: settings.refraction,
+      foam: settings.foam,
+      depth_map: settings.depth_map.clone(),
+      flow_map: settings.flow_map.clone(),
+      bounds: WaterBounds::Unbounded,
+    }
+  }
+}
+
+impl WaterBody {
+  /// Copy the body's settings into a water material.
+  pub fn update_material(&self, mat: &mut StandardWaterMaterial) {
+    mat.base.base_color = self.base_color;
+    mat.base.alpha_mode = self.alpha_mode;
+    mat.extension.amplitude = self.amplitude;
+    mat.extension.wave_model = self.wave_model.clone();
+    mat.extension.clarity = self.clarity;
+    mat.extension.deep_color = self.deep_color;
+    mat.extension.shallow_color = self.shallow_color;
+    mat.extension.edge_color = self.edge_color;
+    mat.extension.edge_scale = self.edge_scale;
+    mat.extension.ssr = self.ssr;
+    mat.extension.refraction = self.refraction;
+    mat.extension.foam = self.foam;
+    mat.extension.depth_map = self.depth_map.as_ref().map(|map| map.image.clone());
+    if let Some(map) = &self.depth_map {
+      mat.extension.depth_map_rect = map.rect;
+      mat.extension.depth_map_max_depth = map.max_depth;
+      mat.extension.wave_damping_depth = map.wave_damping_depth;
+      mat.extension.wave_shoaling = map.shoaling;
+    }
+    mat.extension.flow_map = self.flow_map.as_ref().map(|map| map.image.clone());
+    if let Some(map) = &self.flow_map {
+      mat.extension.flow_map_rect = map.rect;
+      mat.extension.flow_max_speed = map.max_speed;
+      mat.extension.flow_cycle = map.cycle;
+    }
+    if self.ssr.is_some() || self.refraction.is_some() {
+      // Draw in the transmissive pass, the shader blends with the scene itself.
+      mat.base.alpha_mode = AlphaMode::Opaque;
+      mat.base.specular_transmission = mat.base.specular_transmission.max(WATER_TRANSMISSION);
+    } else if mat.base.specular_transmission == WATER_TRANSMISSION {
+      mat.base.specular_transmission = 0.0;
+    }
+  }
+
+  /// A water material for a mesh covering `rect` (with the mesh UVs spanning the rect).
+  pub fn material(&self, rect: Rect) -> StandardWaterMaterial {
+    let mut mat = StandardWaterMaterial {
+      base: StandardMaterial {
+        perceptual_roughness: 0.22,
+        ..default()
+      },
+      extension: WaterMaterial {
+        coord_offset: rect.min,
+        coord_scale: rect.size(),
+        ..default()
+      },
+    };
+    self.update_material(&mut mat);
+    mat
+  }
+}
+
+/// A flat water mesh of `size` centred on the origin, with UVs from (0, 0) at the
+/// -X/-Z corner to (1, 1).
+pub fn water_grid_mesh(size: Vec2) -> Mesh {
+  let cells = (size / GRID_SPACING).ceil().max(Vec2::ONE).as_uvec2();
+  let (x_count, z_count) = (cells.x + 1, cells.y + 1);
+
+  let mut positions = Vec::with_capacity((x_count * z_count) as usize);
+  let mut uvs = Vec::with_capacity((x_count * z_count) as usize);
+  for z in 0..z_count {
+    for x in 0..x_count {
+      let uv = Vec2::new(x as f32 / cells.x as f32, z as f32 / cells.y as f32);
+      let position = (uv - 0.5) * size;
+      positions.push([position.x, 0.0, position.y]);
+      uvs.push(uv.to_array());
+    }
+  }
+  let mut indices = Vec::with_capacity((cells.x * cells.y * 6) as usize);
+  for z in 0..cells.y {
+    for x in 0..cells.x {
+      let quad = z * x_count + x;
+      indices.extend([quad + x_count + 1, quad + 1, quad + x_count]);
+      indices.extend([quad, quad + x_count, quad + 1]);
+    }
+  }
+  let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+
+  Mesh::new(
+    PrimitiveTopology::TriangleList,
+    RenderAssetUsages::default(),
+  )
+  .with_inserted_indices(Indices::U32(indices))
+  .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+  .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+  .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+}
+
+#[derive(Bundle, Default)]
+pub struct WaterBodyBundle {
+  pub name: Name,
+  pub body: WaterBody,
+  pub mesh: MaterialMeshBundle<StandardWaterMaterial>,
+  pub not_shadow_caster: NotShadowCaster,
+}
+
+impl WaterBodyBundle {
+  /// A rectangular body of water covering `rect` (world space `x`, `z`).
+  pub fn rect(
+    mut body: WaterBody,
+    rect: Rect,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardWaterMaterial>,
+  ) -> Self {
+    body.bounds = WaterBounds::Rect(rect);
+    let center = rect.center();
+    Self {
+      name: Name::new("Water Body"),
+      mesh: MaterialMeshBundle {
+        mesh: meshes.add(water_grid_mesh(rect.size())),
+        material: materials.add(body.material(rect)),
+        transform: Transform::from_xyz(center.x, body.height, center.y),
+        ..default()
+      },
+      body,
+      ..default()
+    }
+  }
+
+  /// A body of water filling the polygon `points` (world space `x`, `z`), like a lake
+  /// shore or the `mesh_outline` of a pool.
+  pub fn polygon(
+    mut body: WaterBody,
+    points: Vec<Vec2>,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardWaterMaterial>,
+  ) -> Self {
+    let mesh = water_polygon_mesh(&points);
+    body.bounds = WaterBounds::Polygon(points);
+    let rect = body.bounds.rect().unwrap_or_default();
+    let center = rect.center();
+    Self {
+      name: Name::new("Water Body"),
+      mesh: MaterialMeshBundle {
+        mesh: meshes.add(mesh),
+        material: materials.add(body.material(rect)),
+        transform: Transform::from_xyz(center.x, body.height, center.y),
+        ..default()
+      },
+      body,
+      ..default()
+    }
+  }
+}
+
+/// The water materials used by the entity and its descendants.
+pub(crate) fn body_materials(
+  entity: Entity,
+  children: &Query<&Children>,
+  handles: &Query<&Handle<StandardWaterMaterial>>,
+) -> Vec<AssetId<StandardWaterMaterial>> {
+  std::iter::once(entity)
+    .chain(children.iter_descendants(entity))
+    .filter_map(|entity| handles.get(entity).ok())
+    .map(|handle| handle.id())
+    .collect()
+}
+
+/// The water materials owned by a `WaterBody`, these aren't updated from `WaterSettings`.
+pub(crate) fn owned_materials(
+  bodies: &Query<Entity, With<WaterBody>>,
+  children: &Query<&Children>,
+  handles: &Query<&Handle<StandardWaterMaterial>>,
+) -> HashSet<AssetId<StandardWaterMaterial>> {
+  bodies
+    .iter()
+    .flat_map(|entity| body_materials(entity, children, handles))
+    .collect()
+}
+
+/// Update the water materials of the bodies that changed.
+pub(crate) fn update_body_materials(
+  bodies: Query<(Entity, &WaterBody), Changed<WaterBody>>,
+  children: Query<&Children>,
+  handles: Query<&Handle<StandardWaterMaterial>>,
+  mut materials: ResMut<Assets<StandardWaterMaterial>>,
+) {
+  for (entity, body) in bodies.iter() {
+    for id in body_materials(entity, &children, &handles) {
+      if let Some(mat) = materials.get_mut(id) {
+        body.update_material(mat);
+      }
+    }
+  }
+}
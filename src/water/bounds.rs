@@ -0,0 +1,100 @@
+//! Bounds of the water meshes, including the wave displacement.
+//!
+//! The vertex shader moves the water surface by up to the largest displacement of the
+//! wave model, so the flat mesh `Aabb` is expanded by it to keep frustum culling correct.
+use bevy::{
+  math::{Mat3A, Vec3A},
+  prelude::*,
+  render::{primitives::Aabb, view::NoFrustumCulling},
+};
+
+use super::{fft::FftOceanState, material::StandardWaterMaterial, WaterRipples, WaterSettings};
+use crate::wave::WaveModel;
+
+/// Bounds of the undisplaced mesh.
+#[derive(Component, Clone, Copy, Debug)]
+pub(crate) struct WaterMeshAabb {
+  mesh: AssetId<Mesh>,
+  aabb: Aabb,
+}
+
+/// Largest world space displacement of a water material's surface on each axis.
+///
+/// `ripples` are the `WaterSettings::ripples`, they move the surface up to their `max_height`.
+pub fn max_water_displacement(
+  mat: &StandardWaterMaterial,
+  fft: Option<&FftOceanState>,
+  ripples: Option<&WaterRipples>,
+) -> Vec3 {
+  let amplitude = mat.extension.amplitude;
+  let d = match (&mat.extension.wave_model, fft) {
+    (WaveModel::Fft, Some(fft)) => fft.max_displacement() * amplitude.abs(),
+    (model, _) => model.max_displacement(amplitude),
+  };
+  // The waves grow up to `1.0 + shoaling` times over a depth map.
+  let d = match mat.extension.depth_map {
+    Some(_) => d * (1.0 + mat.extension.wave_shoaling).max(1.0),
+    None => d,
+  };
+  let d = d + Vec3::Y * ripples.map_or(0.0, |ripples| ripples.max_height.abs());
+  // The vertical displacement is along the mesh normal, which isn't always up.
+  Vec3::new(d.x, 0.0, d.z) + Vec3::splat(d.y)
+}
+
+type WaterMesh<'a> = (
+  Entity,
+  &'a Handle<Mesh>,
+  &'a Handle<StandardWaterMaterial>,
+  &'a GlobalTransform,
+  &'a mut Aabb,
+  Option<&'a WaterMeshAabb>,
+);
+
+/// Expand the `Aabb` of the water meshes by the wave displacement.
+pub(crate) fn update_water_aabbs(
+  mut commands: Commands,
+  fft: Option<Res<FftOceanState>>,
+  settings: Res<WaterSettings>,
+  meshes: Res<Assets<Mesh>>,
+  materials: Res<Assets<StandardWaterMaterial>>,
+  mut entities: Query<WaterMesh, Without<NoFrustumCulling>>,
+) {
+  for (entity, mesh, material, global, mut aabb, mesh_aabb) in entities.iter_mut() {
+    let Some(mat) = materials.get(material) else {
+      continue;
+    };
+    let base = match mesh_aabb {
+      Some(mesh_aabb) if mesh_aabb.mesh == mesh.id() => mesh_aabb.aabb,
+      _ => {
+        let Some(base) = meshes.get(mesh).and_then(|mesh| mesh.compute_aabb()) else {
+          continue;
+        };
+        commands.entity(entity).insert(WaterMeshAabb {
+          mesh: mesh.id(),
+          aabb: base,
+        });
+        base
+      }
+    };
+
+    // World space displacement to local space.
+    let matrix = global.affine().matrix3;
+    if matrix.determinant() == 0.0 {
+      continue;
+    }
+    let inverse = matrix.inverse();
+    let abs_inverse = Mat3A::from_cols(
+      inverse.x_axis.abs(),
+      inverse.y_axis.abs(),
+      inverse.z_axis.abs(),
+    );
+    let displacement = max_water_displacement(mat, fft.as_deref(), settings.ripples.as_ref());
+    let expanded = Aabb {
+      center: base.center,
+      half_extents: base.half_extents + abs_inverse * Vec3A::from(displacement),
+    };
+    if *aabb != expanded {
+      *aabb = expanded;
+    }
+  }
+}
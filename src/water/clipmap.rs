@@ -0,0 +1,256 @@
+//! Camera-following ocean (geometry clipmap).
+//!
+//! The ocean is a set of concentric square levels centred on the camera.  Level 0 is a
+//! full grid of `WaterClipmap::grid_size` cells, each following level is a ring with
+//! twice the cell size around the previous one.  The levels snap to their cell grid,
+//! so the vertices don't swim over the waves as the camera moves.
+use bevy::{
+  math::Vec3Swizzles,
+  pbr::NotShadowCaster,
+  prelude::*,
+  render::{
+    camera::RenderTarget,
+    mesh::{Indices, PrimitiveTopology},
+    render_asset::RenderAssetUsages,
+  },
+};
+
+use super::{material::*, WaterBody, WaterBundle, WaterSettings};
+
+/// Settings of the camera-following ocean.
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct WaterClipmap {
+  /// Number of levels.  The ocean covers `grid_size * cell_size * 2^(levels - 1)` meters.
+  pub levels: u32,
+  /// Cells along each side of a level (rounded up to a multiple of 4).
+  pub grid_size: u32,
+  /// Cell size (meters) of the finest level.
+  pub cell_size: f32,
+}
+
+impl Default for WaterClipmap {
+  fn default() -> Self {
+    Self {
+      levels: 8,
+      grid_size: 64,
+      cell_size: 1.0,
+    }
+  }
+}
+
+impl WaterClipmap {
+  fn grid_size(&self) -> u32 {
+    self.grid_size.max(4).next_multiple_of(4)
+  }
+
+  /// Cell size of `level`.
+  pub fn level_cell_size(&self, level: u32) -> f32 {
+    self.cell_size * (1u32 << level) as f32
+  }
+
+  /// World space centre (`x`, `z`) of `level` when the camera is at `camera`.
+  pub fn level_center(&self, level: u32, camera: Vec2) -> Vec2 {
+    // Snap to every other vertex, so the hole in the next level stays on its grid.
+    let snap = self.level_cell_size(level) * 2.0;
+    (camera / snap).round() * snap
+  }
+
+  /// Total size (meters) of the ocean.
+  pub fn size(&self) -> f32 {
+    self.grid_size() as f32 * self.level_cell_size(self.levels.max(1) - 1)
+  }
+}
+
+/// Marks the entity the clipmap follows.
+///
+/// Without it the clipmap follows the first active 3d camera rendering to a window.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct WaterClipmapFocus;
+
+/// A level of the camera-following ocean.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct WaterClipmapLevel {
+  pub level: u32,
+}
+
+/// Clipmap meshes, in cell units.
+#[derive(Resource, Clone, Debug)]
+pub(crate) struct WaterClipmapMeshes {
+  /// Full grid, used by level 0.
+  full: Handle<Mesh>,
+  /// Rings, indexed by the offset of their hole (-1..=1 cells on each axis).
+  rings: [Handle<Mesh>; 9],
+}
+
+impl WaterClipmapMeshes {
+  fn ring(&self, hole: IVec2) -> Handle<Mesh> {
+    let hole = hole.clamp(IVec2::NEG_ONE, IVec2::ONE) + IVec2::ONE;
+    self.rings[(hole.y * 3 + hole.x) as usize].clone()
+  }
+}
+
+/// A clipmap level mesh of `size` x `size` cells (of size 1) centred on the origin.
+///
+/// The UVs span the whole level.  Rings have a hole of half the size at `hole` cells
+/// from the centre.  Every other vertex on the outer edge is merged with its neighbour,
+/// so the edge matches the vertices of the next (coarser) level without cracks.
+pub fn clipmap_mesh(size: u32, hole: Option<IVec2>) -> Mesh {
+  let count = size + 1;
+  let half = (size / 2) as i32;
+  let mut positions = Vec::with_capacity((count * count) as usize);
+  let mut uvs = Vec::with_capacity((count * count) as usize);
+  for z in 0..count {
+    for x in 0..count {
+      // Merge odd outer edge vertices with the previous vertex.
+      let edge_z = z == 0 || z == size;
+      let edge_x = x == 0 || x == size;
+      let vx = if edge_z && x % 2 == 1 { x - 1 } else { x };
+      let vz = if edge_x && z % 2 == 1 { z - 1 } else { z };
+      let uv = Vec2::new(vx as f32, vz as f32) / size as f32;
+      let position = (uv - 0.5) * size as f32;
+      positions.push([position.x, 0.0, position.y]);
+      uvs.push(uv.to_array());
+    }
+  }
+
+  let in_hole = |x: i32, z: i32| {
+    hole.is_some_and(|hole| {
+      let min = hole - half / 2;
+      let max = hole + half / 2;
+      x >= min.x && x < max.x && z >= min.y && z < max.y
+    })
+  };
+  let mut indices = Vec::with_capacity((size * size * 6) as usize);
+  for z in 0..size {
+    for x in 0..size {
+      if in_hole(x as i32 - half, z as i32 - half) {
+        continue;
+      }
+      let quad = z * count + x;
+      indices.extend([quad + count + 1, quad + 1, quad + count]);
+      indices.extend([quad, quad + count, quad + 1]);
+    }
+  }
+  let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+
+  Mesh::new(
+    PrimitiveTopology::TriangleList,
+    RenderAssetUsages::default(),
+  )
+  .with_inserted_indices(Indices::U32(indices))
+  .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+  .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+  .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+}
+
+/// Spawn the clipmap levels.
+pub(crate) fn setup_clipmap(
+  commands: &mut Commands,
+  settings: &WaterSettings,
+  clipmap: &WaterClipmap,
+  meshes: &mut Assets<Mesh>,
+  materials: &mut Assets<StandardWaterMaterial>,
+) {
+  let size = clipmap.grid_size();
+  let clipmap_meshes = WaterClipmapMeshes {
+    full: meshes.add(clipmap_mesh(size, None)),
+    rings: std::array::from_fn(|i| {
+      let hole = IVec2::new(i as i32 % 3, i as i32 / 3) - IVec2::ONE;
+      meshes.add(clipmap_mesh(size, Some(hole)))
+    }),
+  };
+
+  let global = WaterBody::from(settings);
+  commands
+    .spawn(WaterBundle {
+      name: Name::new("Water"),
+      ..default()
+    })
+    .with_children(|parent| {
+      for level in 0..clipmap.levels.max(1) {
+        let cell_size = clipmap.level_cell_size(level);
+        let extent = Vec2::splat(cell_size * size as f32);
+        let material = materials.add(
+          global.material(Rect::from_center_size(Vec2::ZERO, extent)),
+        );
+        let mesh = if level == 0 {
+          clipmap_meshes.full.clone()
+        } else {
+          clipmap_meshes.ring(IVec2::ZERO)
+        };
+        parent.spawn((
+          Name::new(format!("Water Clipmap Level {level}")),
+          WaterClipmapLevel { level },
+          MaterialMeshBundle {
+            mesh,
+            material,
+            transform: Transform::from_xyz(0.0, settings.height, 0.0)
+              .with_scale(Vec3::new(cell_size, 1.0, cell_size)),
+            ..default()
+          },
+          NotShadowCaster,
+        ));
+      }
+    });
+  commands.insert_resource(clipmap_meshes);
+}
+
+/// Move the clipmap levels with the camera.
+pub(crate) fn update_clipmap(
+  settings: Res<WaterSettings>,
+  clipmap_meshes: Res<WaterClipmapMeshes>,
+  focus: Query<&GlobalTransform, With<WaterClipmapFocus>>,
+  cameras: Query<(&GlobalTransform, &Camera), With<Camera3d>>,
+  mut levels: Query<(
+    &WaterClipmapLevel,
+    &mut Transform,
+    &mut Handle<Mesh>,
+    &Handle<StandardWaterMaterial>,
+  )>,
+  mut materials: ResMut<Assets<StandardWaterMaterial>>,
+) {
+  let Some(clipmap) = &settings.clipmap else {
+    return;
+  };
+  let camera = focus.iter().next().or_else(|| {
+    cameras
+      .iter()
+      .find(|(_, camera)| camera.is_active && matches!(camera.target, RenderTarget::Window(_)))
+      .map(|(transform, _)| transform)
+  });
+  let Some(camera) = camera.map(|transform| transform.translation().xz()) else {
+    return;
+  };
+
+  let size = clipmap.grid_size() as f32;
+  for (level, mut transform, mut mesh, material) in levels.iter_mut() {
+    let cell_size = clipmap.level_cell_size(level.level);
+    let center = clipmap.level_center(level.level, camera);
+    let translation = Vec3::new(center.x, settings.height, center.y);
+    if transform.translation != translation {
+      transform.translation = translation;
+    }
+
+    if level.level > 0 {
+      // The hole follows the centre of the previous level.
+      let inner = clipmap.level_center(level.level - 1, camera);
+      let hole = ((inner - center) / cell_size).round().as_ivec2();
+      let ring = clipmap_meshes.ring(hole);
+      if *mesh != ring {
+        *mesh = ring;
+      }
+    }
+
+    let coord_offset = center - cell_size * size / 2.0;
+    if materials
+      .get(material)
+      .is_some_and(|mat| mat.extension.coord_offset != coord_offset)
+    {
+      if let Some(mat) = materials.get_mut(material) {
+        mat.extension.coord_offset = coord_offset;
+      }
+    }
+  }
+}
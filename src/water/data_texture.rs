@@ -0,0 +1,157 @@
+//! Data textures.
+//!
+//! Data of the frame for the water shader (wake trails, splashes, the ripples area) is
+//! written to small `Rgba32Float` textures every frame, so the water materials don't
+//! change while it changes.  The shaders read them with `textureLoad`, this works on WebGL2.
+use std::marker::PhantomData;
+
+use bevy::{
+  prelude::*,
+  render::{
+    extract_resource::{ExtractResource, ExtractResourcePlugin},
+    render_asset::{RenderAssetUsages, RenderAssets},
+    render_resource::{
+      Extent3d, ImageCopyTexture, ImageDataLayout, Origin3d, TextureAspect, TextureDimension,
+      TextureFormat,
+    },
+    renderer::RenderQueue,
+    Render, RenderApp, RenderSet,
+  },
+};
+
+/// A resource written to a data texture every frame.
+pub trait WaterDataTexture: Resource {
+  /// Size (texels) of the texture.
+  const SIZE: UVec2;
+
+  /// The texels, row by row, `SIZE.x * SIZE.y` of them.
+  fn texels(&self) -> Vec<Vec4>;
+}
+
+/// The image the `T` resource is written to.
+#[derive(Resource)]
+pub struct WaterDataImage<T> {
+  pub image: Handle<Image>,
+  marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for WaterDataImage<T> {
+  fn clone(&self) -> Self {
+    Self {
+      image: self.image.clone(),
+      marker: PhantomData,
+    }
+  }
+}
+
+impl<T: WaterDataTexture> ExtractResource for WaterDataImage<T> {
+  type Source = Self;
+
+  fn extract_resource(source: &Self::Source) -> Self {
+    source.clone()
+  }
+}
+
+/// The texels of the frame.
+#[derive(Resource)]
+struct ExtractedTexels<T> {
+  texels: Vec<Vec4>,
+  marker: PhantomData<fn() -> T>,
+}
+
+impl<T: WaterDataTexture> ExtractResource for ExtractedTexels<T> {
+  type Source = T;
+
+  fn extract_resource(source: &Self::Source) -> Self {
+    Self {
+      texels: source.texels(),
+      marker: PhantomData,
+    }
+  }
+}
+
+/// Writes the `T` resource to its `WaterDataImage<T>`.
+pub struct WaterDataTexturePlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for WaterDataTexturePlugin<T> {
+  fn default() -> Self {
+    Self(PhantomData)
+  }
+}
+
+impl<T: WaterDataTexture> Plugin for WaterDataTexturePlugin<T> {
+  fn build(&self, app: &mut App) {
+    app.add_plugins((
+      ExtractResourcePlugin::<WaterDataImage<T>>::default(),
+      ExtractResourcePlugin::<ExtractedTexels<T>>::default(),
+    ));
+    let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+      return;
+    };
+    render_app.add_systems(
+      Render,
+      write_data_texture::<T>.in_set(RenderSet::PrepareResources),
+    );
+  }
+
+  fn finish(&self, app: &mut App) {
+    let Some(mut images) = app.world.get_resource_mut::<Assets<Image>>() else {
+      return;
+    };
+    let image = images.add(Image::new_fill(
+      extent(T::SIZE),
+      TextureDimension::D2,
+      &[0; 16],
+      TextureFormat::Rgba32Float,
+      RenderAssetUsages::RENDER_WORLD,
+    ));
+    app.insert_resource(WaterDataImage::<T> {
+      image,
+      marker: PhantomData,
+    });
+  }
+}
+
+fn extent(size: UVec2) -> Extent3d {
+  Extent3d {
+    width: size.x,
+    height: size.y,
+    depth_or_array_layers: 1,
+  }
+}
+
+fn write_data_texture<T: WaterDataTexture>(
+  texels: Option<Res<ExtractedTexels<T>>>,
+  data_image: Option<Res<WaterDataImage<T>>>,
+  gpu_images: Res<RenderAssets<Image>>,
+  render_queue: Res<RenderQueue>,
+) {
+  let (Some(texels), Some(data_image)) = (texels, data_image) else {
+    return;
+  };
+  let Some(image) = gpu_images.get(&data_image.image) else {
+    return;
+  };
+  let mut bytes: Vec<u8> = texels
+    .texels
+    .iter()
+    .flat_map(|texel| texel.to_array())
+    .flat_map(f32::to_le_bytes)
+    .collect();
+  bytes.resize((T::SIZE.x * T::SIZE.y) as usize * 16, 0);
+  render_queue.write_texture(
+    ImageCopyTexture {
+      texture: &image.texture,
+      mip_level: 0,
+      origin: Origin3d::ZERO,
+      aspect: TextureAspect::All,
+    },
+    &bytes,
+    ImageDataLayout {
+      offset: 0,
+      bytes_per_row: Some(T::SIZE.x * 16),
+      rows_per_image: None,
+    },
+    extent(T::SIZE),
+  );
+}
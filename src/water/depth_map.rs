@@ -0,0 +1,333 @@
+//! Baked water depth.
+//!
+//! A `WaterDepthMap` is a top-down image of the water depth below the rest surface.  The
+//! water shader uses it for the shallow color, the shoreline edge and to shape the waves
+//! near the shore, so these work without a `DepthPrepass` (and on WebGL2).  `WaterParam`
+//! reads the same image on the CPU.
+//!
+//! The map is baked from a terrain heightfield, from meshes, or from the scene under a
+//! `WaterBody` with `BakeWaterDepth`.
+use bevy::{
+  asset::LoadState,
+  math::Vec3Swizzles,
+  prelude::*,
+  render::{
+    mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat},
+  },
+};
+
+use super::{body::*, material::StandardWaterMaterial};
+use crate::Buoyancy;
+
+/// A baked top-down map of the water depth.
+#[derive(Clone, Debug, Reflect)]
+pub struct WaterDepthMap {
+  /// Water depth below the rest surface divided by `max_depth` (`R8Unorm`).
+  pub image: Handle<Image>,
+  /// World space area (`x`, `z`) covered by the image, deeper water is assumed outside.
+  pub rect: Rect,
+  /// Depth (meters) stored as 1.0, deeper water is clamped.
+  pub max_depth: f32,
+  /// The waves shrink in water shallower than this (meters), 0.0 = no damping.
+  pub wave_damping_depth: f32,
+  /// Growth of the waves approaching the `wave_damping_depth` (shoaling), 0.0 = none.
+  ///
+  /// The waves grow from twice the damping depth, up to `1.0 + shoaling` times their
+  /// height at the damping depth.
+  pub shoaling: f32,
+}
+
+impl WaterDepthMap {
+  /// A depth map with the default wave shaping for `max_depth`.
+  pub fn new(image: Handle<Image>, rect: Rect, max_depth: f32) -> Self {
+    Self {
+      image,
+      rect,
+      max_depth,
+      wave_damping_depth: max_depth * 0.25,
+      shoaling: 0.3,
+    }
+  }
+
+  /// Bake the depth of water at `water_height` over a terrain heightfield.
+  ///
+  /// `ground` returns the ground height at a world position (`x`, `z`), it is sampled at
+  /// the texel centres of a `size` image covering `rect`.
+  pub fn from_heightfield(
+    rect: Rect,
+    size: UVec2,
+    water_height: f32,
+    max_depth: f32,
+    ground: impl Fn(Vec2) -> f32,
+    images: &mut Assets<Image>,
+  ) -> Self {
+    let image = depth_image(rect, size, max_depth, |p| water_height - ground(p));
+    Self::new(images.add(image), rect, max_depth)
+  }
+
+  /// Bake the depth of water at `water_height` over the top of `meshes`.
+  ///
+  /// The ground is the highest upward facing triangle below the water, so bridges and
+  /// overhangs don't hide the water under them.  Texels without ground are `max_depth`
+  /// deep, texels where the water is inside a mesh are dry.
+  pub fn from_meshes<'a>(
+    rect: Rect,
+    size: UVec2,
+    water_height: f32,
+    max_depth: f32,
+    meshes: impl IntoIterator<Item = (&'a Mesh, &'a GlobalTransform)>,
+    images: &mut Assets<Image>,
+  ) -> Self {
+    let ground = rasterize_ground(rect, size, water_height, meshes);
+    let size = size.max(UVec2::ONE);
+    let texel = rect.size() / size.as_vec2();
+    let image = depth_image(rect, size, max_depth, |p| {
+      let texel = ((p - rect.min) / texel).as_uvec2().min(size - 1);
+      water_height - ground[(texel.y * size.x + texel.x) as usize]
+    });
+    Self::new(images.add(image), rect, max_depth)
+  }
+
+  /// Water depth (meters) below the rest surface at the world position `p` (`x`, `z`).
+  ///
+  /// Bilinear like the water shader.  `None` if the image isn't loaded.
+  pub fn depth(&self, images: &Assets<Image>, p: Vec2) -> Option<f32> {
+    let image = images.get(&self.image)?;
+    if !self.rect.contains(p) {
+      return Some(self.max_depth);
+    }
+    let size = image.size();
+    if size.x == 0 || size.y == 0 || image.data.len() < (size.x * size.y) as usize {
+      return None;
+    }
+    let texel = |x: i32, y: i32| {
+      let x = x.clamp(0, size.x as i32 - 1) as u32;
+      let y = y.clamp(0, size.y as i32 - 1) as u32;
+      image.data[(y * size.x + x) as usize] as f32 / 255.0
+    };
+    let uv = (p - self.rect.min) / self.rect.size() * size.as_vec2() - 0.5;
+    let (i, f) = (uv.floor().as_ivec2(), uv - uv.floor());
+    let top = texel(i.x, i.y).lerp(texel(i.x + 1, i.y), f.x);
+    let bottom = texel(i.x, i.y + 1).lerp(texel(i.x + 1, i.y + 1), f.x);
+    Some(top.lerp(bottom, f.y) * self.max_depth)
+  }
+
+  /// Scale of the waves over water `depth` meters deep.
+  pub fn wave_scale(&self, depth: f32) -> f32 {
+    let damping = self.wave_damping_depth;
+    if damping <= 0.0 {
+      return 1.0;
+    }
+    let d = depth.max(0.0) / damping;
+    let grown = 1.0 + self.shoaling * (2.0 - d).clamp(0.0, 1.0);
+    grown.min((1.0 + self.shoaling) * d)
+  }
+}
+
+/// An `R8Unorm` image of the normalized water `depth` at the texel centres.
+pub fn depth_image(rect: Rect, size: UVec2, max_depth: f32, depth: impl Fn(Vec2) -> f32) -> Image {
+  let size = size.max(UVec2::ONE);
+  let texel = rect.size() / size.as_vec2();
+  let mut data = Vec::with_capacity((size.x * size.y) as usize);
+  for y in 0..size.y {
+    for x in 0..size.x {
+      let p = rect.min + (Vec2::new(x as f32, y as f32) + 0.5) * texel;
+      let normalized = (depth(p) / max_depth).clamp(0.0, 1.0);
+      data.push((normalized * 255.0).round() as u8);
+    }
+  }
+  Image::new(
+    Extent3d {
+      width: size.x,
+      height: size.y,
+      depth_or_array_layers: 1,
+    },
+    TextureDimension::D2,
+    data,
+    TextureFormat::R8Unorm,
+    RenderAssetUsages::default(),
+  )
+}
+
+/// Top-down orthographic rasterization of the ground under the water at each texel centre:
+/// the highest upward facing triangle below `water_height`.
+///
+/// Texels where the water is inside a solid (the closest triangle above the water faces up)
+/// are dry, at `water_height`.
+fn rasterize_ground<'a>(
+  rect: Rect,
+  size: UVec2,
+  water_height: f32,
+  meshes: impl IntoIterator<Item = (&'a Mesh, &'a GlobalTransform)>,
+) -> Vec<f32> {
+  let size = size.max(UVec2::ONE);
+  let texel = rect.size() / size.as_vec2();
+  let mut ground = vec![f32::NEG_INFINITY; (size.x * size.y) as usize];
+  // Closest triangle above the water, and whether it faces up.
+  let mut above = vec![(f32::INFINITY, false); ground.len()];
+  for (mesh, transform) in meshes {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+      continue;
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+      mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+      continue;
+    };
+    let positions: Vec<Vec3> = positions
+      .iter()
+      .map(|p| transform.transform_point(Vec3::from_array(*p)))
+      .collect();
+    let indices: Vec<usize> = match mesh.indices() {
+      Some(Indices::U16(indices)) => indices.iter().map(|i| *i as usize).collect(),
+      Some(Indices::U32(indices)) => indices.iter().map(|i| *i as usize).collect(),
+      None => (0..positions.len()).collect(),
+    };
+    for triangle in indices.chunks_exact(3) {
+      let (Some(a), Some(b), Some(c)) = (
+        positions.get(triangle[0]),
+        positions.get(triangle[1]),
+        positions.get(triangle[2]),
+      ) else {
+        continue;
+      };
+      let (a2, b2, c2) = (a.xz(), b.xz(), c.xz());
+      let area = (b2 - a2).perp_dot(c2 - a2);
+      if area.abs() < f32::EPSILON {
+        continue;
+      }
+      // Counter-clockwise seen from above.
+      let faces_up = area < 0.0;
+      // Texels with their centre inside the triangle's bounds.
+      let min = ((a2.min(b2).min(c2) - rect.min) / texel - 0.5).ceil();
+      let max = ((a2.max(b2).max(c2) - rect.min) / texel - 0.5).floor();
+      let min = min.max(Vec2::ZERO).as_uvec2();
+      let max = max.min((size - 1).as_vec2());
+      if max.x < 0.0 || max.y < 0.0 {
+        continue;
+      }
+      let max = max.as_uvec2();
+      for y in min.y..=max.y {
+        for x in min.x..=max.x {
+          let p = rect.min + (Vec2::new(x as f32, y as f32) + 0.5) * texel;
+          // Barycentric weights, all positive inside the triangle.
+          let wa = (b2 - p).perp_dot(c2 - p) / area;
+          let wb = (c2 - p).perp_dot(a2 - p) / area;
+          let wc = 1.0 - wa - wb;
+          if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+            continue;
+          }
+          let height = a.y * wa + b.y * wb + c.y * wc;
+          let i = (y * size.x + x) as usize;
+          if height > water_height {
+            if height < above[i].0 {
+              above[i] = (height, faces_up);
+            }
+          } else if faces_up {
+            ground[i] = ground[i].max(height);
+          }
+        }
+      }
+    }
+  }
+  for (ground, (_, inside)) in ground.iter_mut().zip(above) {
+    if inside {
+      *ground = water_height;
+    }
+  }
+  ground
+}
+
+/// Bake a `WaterDepthMap` for the `WaterBody` from the meshes of the scene.
+///
+/// All meshes except the water and `Buoyancy` bodies are baked, once they are loaded.
+/// Meshes that failed to load are skipped.
+/// The component is removed when done, insert it again to bake the map again.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct BakeWaterDepth {
+  /// Area to bake.  Defaults to the bounds of the `WaterBody`.
+  pub rect: Option<Rect>,
+  /// Size (meters) of the depth map texels.
+  pub texel_size: f32,
+  /// Depth (meters) stored as 1.0, deeper water is clamped.
+  pub max_depth: f32,
+}
+
+impl Default for BakeWaterDepth {
+  fn default() -> Self {
+    Self {
+      rect: None,
+      texel_size: 1.0,
+      max_depth: 8.0,
+    }
+  }
+}
+
+type SceneMesh<'a> = (Entity, &'a Handle<Mesh>, &'a GlobalTransform);
+
+type NotWater = (
+  Without<Handle<StandardWaterMaterial>>,
+  Without<WaterBody>,
+  Without<Buoyancy>,
+);
+
+type Floating = Or<(With<Buoyancy>, With<WaterBody>)>;
+
+/// Bake the depth maps of `BakeWaterDepth` bodies.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn bake_water_depth(
+  mut commands: Commands,
+  mut bodies: Query<(Entity, &mut WaterBody, &BakeWaterDepth)>,
+  scene: Query<SceneMesh, NotWater>,
+  floating: Query<(), Floating>,
+  parents: Query<&Parent>,
+  meshes: Res<Assets<Mesh>>,
+  asset_server: Res<AssetServer>,
+  mut images: ResMut<Assets<Image>>,
+) {
+  for (entity, mut body, bake) in bodies.iter_mut() {
+    let Some(rect) = bake.rect.or(body.bounds.rect()) else {
+      warn!("BakeWaterDepth needs a `rect` for unbounded water bodies.");
+      commands.entity(entity).remove::<BakeWaterDepth>();
+      continue;
+    };
+    // Skip the parts of floating objects and of water bodies.
+    let candidates: Vec<_> = scene
+      .iter()
+      .filter(|(entity, ..)| {
+        !parents
+          .iter_ancestors(*entity)
+          .any(|e| floating.contains(e))
+      })
+      .collect();
+    if candidates.iter().any(|(_, handle, _)| {
+      !meshes.contains(*handle)
+        && matches!(
+          asset_server.get_load_state(handle.id()),
+          Some(LoadState::Loading)
+        )
+    }) {
+      // Wait for the meshes to load.
+      continue;
+    }
+    let size = (rect.size() / bake.texel_size.max(1e-3))
+      .ceil()
+      .max(Vec2::ONE)
+      .as_uvec2();
+    let scene = candidates
+      .iter()
+      .filter_map(|(_, handle, transform)| Some((meshes.get(*handle)?, *transform)));
+    body.depth_map = Some(WaterDepthMap::from_meshes(
+      rect,
+      size,
+      body.height,
+      bake.max_depth,
+      scene,
+      &mut images,
+    ));
+    commands.entity(entity).remove::<BakeWaterDepth>();
+  }
+}
@@ -0,0 +1,871 @@
+//! FFT ocean (Tessendorf) wave model.
+//!
+//! The height and choppy displacement are synthesized from an ocean spectrum each frame
+//! by a compute shader (`assets/shaders/fft_ocean.wgsl`) and sampled by the water material
+//! when `WaveModel::Fft` is used.  `FftOceanState` runs the same simulation on the CPU
+//! so `WaterParam` can be queried.
+use std::f32::consts::{PI, TAU};
+
+use bevy::{
+  asset::load_internal_asset,
+  prelude::*,
+  render::{
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    texture::{ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor},
+  },
+};
+
+use super::material::StandardWaterMaterial;
+use crate::wave::WaveModel;
+
+pub const FFT_OCEAN_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x5b1f0c3e7a9d4e21);
+
+const GRAVITY: f32 = 9.81;
+/// The dispersion is quantized so the simulation loops after this many seconds.
+const REPEAT_TIME: f32 = 200.0;
+
+/// The ocean spectrum used to generate the FFT waves.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub enum OceanSpectrum {
+  /// Phillips spectrum, a fully developed sea.
+  Phillips,
+  /// JONSWAP spectrum, a fetch limited sea.
+  Jonswap {
+    /// Distance over which the wind has blown (meters).
+    fetch: f32,
+    /// Peak enhancement factor.
+    gamma: f32,
+  },
+}
+
+/// Settings of the FFT ocean simulation.
+#[derive(Resource, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct FftOcean {
+  /// Resolution of the simulation grid, a power of two (at least 16).
+  ///
+  /// Other sizes are rounded up, see `simulation_size`.
+  pub size: u32,
+  /// World size (meters) of the simulated ocean patch.  The waves repeat with this period.
+  pub patch_size: f32,
+  /// Ocean spectrum.
+  pub spectrum: OceanSpectrum,
+  /// Wind speed (m/s).
+  pub wind_speed: f32,
+  /// Wind direction on the XZ plane.
+  pub wind_direction: Vec2,
+  /// Scale of the horizontal (choppy) displacement.
+  pub choppiness: f32,
+  /// Seed of the random phases.
+  pub seed: u32,
+}
+
+impl Default for FftOcean {
+  fn default() -> Self {
+    Self {
+      size: 128,
+      patch_size: 300.0,
+      spectrum: OceanSpectrum::Phillips,
+      wind_speed: 10.0,
+      wind_direction: Vec2::new(1.0, 0.3),
+      choppiness: 1.0,
+      seed: 0x5eed,
+    }
+  }
+}
+
+impl FftOcean {
+  /// The `size` rounded up to a power of two, between 16 and 4096.
+  pub fn simulation_size(&self) -> u32 {
+    self.size.clamp(16, 4096).next_power_of_two()
+  }
+
+  fn wave_vector(&self, x: u32, y: u32) -> Vec2 {
+    let n = self.simulation_size() as i32;
+    let wrap = |m: u32| {
+      let m = m as i32;
+      if m >= n / 2 {
+        m - n
+      } else {
+        m
+      }
+    };
+    Vec2::new(wrap(x) as f32, wrap(y) as f32) * (TAU / self.patch_size)
+  }
+
+  /// Directional wave energy density for the wave vector `k`.
+  fn spectrum_density(&self, k_vec: Vec2) -> f32 {
+    let k = k_vec.length();
+    if k < 1e-6 {
+      return 0.0;
+    }
+    let wind_dir = self.wind_direction.normalize_or_zero();
+    let cos_theta = (k_vec / k).dot(wind_dir);
+    match self.spectrum {
+      OceanSpectrum::Phillips => {
+        let l = self.wind_speed * self.wind_speed / GRAVITY;
+        let small = l * 0.001;
+        let mut directional = cos_theta * cos_theta;
+        // Dampen the waves moving against the wind.
+        if cos_theta < 0.0 {
+          directional *= 0.07;
+        }
+        0.0081 / TAU * (-1.0 / (k * l).powi(2)).exp() / k.powi(4)
+          * directional
+          * (-(k * small).powi(2)).exp()
+      }
+      OceanSpectrum::Jonswap { fetch, gamma } => {
+        let u = self.wind_speed.max(0.1);
+        let fetch = fetch.max(1.0);
+        let omega = (GRAVITY * k).sqrt();
+        let omega_p = 22.0 * (GRAVITY * GRAVITY / (u * fetch)).powf(1.0 / 3.0);
+        let alpha = 0.076 * (u * u / (fetch * GRAVITY)).powf(0.22);
+        let sigma = if omega <= omega_p { 0.07 } else { 0.09 };
+        let r = (-(omega - omega_p).powi(2) / (2.0 * sigma * sigma * omega_p * omega_p)).exp();
+        let s_omega = alpha * GRAVITY * GRAVITY / omega.powi(5)
+          * (-1.25 * (omega_p / omega).powi(4)).exp()
+          * gamma.powf(r);
+        // Convert from frequency to wave number: dω/dk = g / 2ω.
+        let s_k = s_omega * GRAVITY / (2.0 * omega) / k;
+        let directional = if cos_theta > 0.0 {
+          2.0 / PI * cos_theta * cos_theta
+        } else {
+          0.0
+        };
+        s_k * directional
+      }
+    }
+  }
+
+  /// Initial spectrum: `[h0(k), conj(h0(-k))]` for every texel.
+  pub fn initial_spectrum(&self) -> Vec<[Vec2; 2]> {
+    let n = self.simulation_size();
+    let dk = TAU / self.patch_size;
+    let mut rng = Rng(self.seed.max(1));
+    let h0: Vec<Vec2> = (0..n * n)
+      .map(|idx| {
+        let k = self.wave_vector(idx % n, idx / n);
+        rng.gaussian() * ((self.spectrum_density(k) / 2.0).sqrt() * dk)
+      })
+      .collect();
+    (0..n * n)
+      .map(|idx| {
+        let (x, y) = (idx % n, idx / n);
+        let minus = h0[(((n - y) % n) * n + (n - x) % n) as usize];
+        [h0[idx as usize], Vec2::new(minus.x, -minus.y)]
+      })
+      .collect()
+  }
+}
+
+/// Small deterministic random number generator (xorshift).
+struct Rng(u32);
+
+impl Rng {
+  fn next(&mut self) -> f32 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 17;
+    self.0 ^= self.0 << 5;
+    (self.0 >> 8) as f32 / (1u32 << 24) as f32
+  }
+
+  /// Pair of normal distributed values (Box-Muller).
+  fn gaussian(&mut self) -> Vec2 {
+    let u1 = self.next().max(1e-7);
+    let u2 = self.next();
+    let r = (-2.0 * u1.ln()).sqrt();
+    Vec2::new(r * (TAU * u2).cos(), r * (TAU * u2).sin())
+  }
+}
+
+fn complex_mul(a: Vec2, b: Vec2) -> Vec2 {
+  Vec2::new(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x)
+}
+
+/// Inverse transform twiddles `e^(i * TAU * j / n)` for `j < n / 2`.
+fn twiddles(n: usize) -> Vec<Vec2> {
+  (0..n / 2)
+    .map(|j| {
+      let angle = TAU * j as f32 / n as f32;
+      Vec2::new(angle.cos(), angle.sin())
+    })
+    .collect()
+}
+
+/// In-place inverse FFT (without normalization) of `data`, which must have a power of two length.
+///
+/// `twiddles` are the `twiddles(data.len())`.
+fn inverse_fft(data: &mut [Vec2], twiddles: &[Vec2]) {
+  let n = data.len();
+  let bits = n.trailing_zeros();
+  for i in 0..n {
+    let j = i.reverse_bits() >> (usize::BITS - bits);
+    if i < j {
+      data.swap(i, j);
+    }
+  }
+  let mut len = 2;
+  while len <= n {
+    let half = len / 2;
+    let stride = n / len;
+    for start in (0..n).step_by(len) {
+      for j in 0..half {
+        let u = data[start + j];
+        let v = complex_mul(data[start + j + half], twiddles[j * stride]);
+        data[start + j] = u + v;
+        data[start + j + half] = u - v;
+      }
+    }
+    len *= 2;
+  }
+}
+
+fn inverse_fft_2d(data: &mut [Vec2], n: usize, twiddles: &[Vec2]) {
+  for row in data.chunks_mut(n) {
+    inverse_fft(row, twiddles);
+  }
+  let mut column = vec![Vec2::ZERO; n];
+  for x in 0..n {
+    for y in 0..n {
+      column[y] = data[y * n + x];
+    }
+    inverse_fft(&mut column, twiddles);
+    for y in 0..n {
+      data[y * n + x] = column[y];
+    }
+  }
+}
+
+/// CPU reference of the FFT ocean, used by `WaterParam`.
+#[derive(Resource, Default)]
+pub struct FftOceanState {
+  size: u32,
+  patch_size: f32,
+  choppiness: f32,
+  wave_vectors: Vec<Vec2>,
+  h0: Vec<[Vec2; 2]>,
+  twiddles: Vec<Vec2>,
+  /// Displacement of each grid point of the patch.
+  displacement: Vec<Vec3>,
+  /// Largest displacement on each axis since the ocean changed.
+  max_displacement: Vec3,
+}
+
+impl FftOceanState {
+  pub fn new(ocean: &FftOcean) -> Self {
+    let mut state = Self::default();
+    state.set_ocean(ocean);
+    state
+  }
+
+  fn set_ocean(&mut self, ocean: &FftOcean) {
+    let n = ocean.simulation_size();
+    if n != ocean.size {
+      warn!("FftOcean::size {} isn't a power of two between 16 and 4096, using {n}", ocean.size);
+    }
+    self.size = n;
+    self.patch_size = ocean.patch_size;
+    self.choppiness = ocean.choppiness;
+    self.wave_vectors = (0..n * n)
+      .map(|idx| ocean.wave_vector(idx % n, idx / n))
+      .collect();
+    self.h0 = ocean.initial_spectrum();
+    self.twiddles = twiddles(n as usize);
+    self.displacement = vec![Vec3::ZERO; (n * n) as usize];
+    self.max_displacement = Vec3::ZERO;
+  }
+
+  /// Evolve the spectrum to `time` and transform it back to the displacement grid.
+  ///
+  /// Matches the `spectrum`, `fft` and `resolve` passes of `fft_ocean.wgsl`.
+  pub fn update(&mut self, time: f32) {
+    let omega_0 = TAU / REPEAT_TIME;
+    let (mut a, mut b): (Vec<Vec2>, Vec<Vec2>) = self
+      .wave_vectors
+      .iter()
+      .zip(&self.h0)
+      .map(|(k_vec, [h0, h0_minus])| {
+        let k = k_vec.length().max(0.0001);
+        let m = ((GRAVITY * k).sqrt() / omega_0).floor();
+        let phase = TAU * (m * (time / REPEAT_TIME).fract()).fract();
+        let e = Vec2::new(phase.cos(), phase.sin());
+        let h = complex_mul(*h0, e) + complex_mul(*h0_minus, Vec2::new(e.x, -e.y));
+        let ih = Vec2::new(h.y, -h.x);
+        let dx = ih * (k_vec.x / k);
+        let dz = ih * (k_vec.y / k);
+        (Vec2::new(h.x - dx.y, h.y + dx.x), dz)
+      })
+      .unzip();
+    let n = self.size as usize;
+    inverse_fft_2d(&mut a, n, &self.twiddles);
+    inverse_fft_2d(&mut b, n, &self.twiddles);
+    for ((d, a), b) in self.displacement.iter_mut().zip(&a).zip(&b) {
+      *d = Vec3::new(self.choppiness * a.y, a.x, self.choppiness * b.x);
+      self.max_displacement = self.max_displacement.max(d.abs());
+    }
+  }
+
+  /// Largest displacement (with unit amplitude) on each axis since the ocean changed.
+  pub fn max_displacement(&self) -> Vec3 {
+    self.max_displacement
+  }
+
+  /// Displacement (with unit amplitude) of the point `p` on the undisturbed water plane.
+  pub fn displacement(&self, p: Vec2) -> Vec3 {
+    if self.size == 0 {
+      return Vec3::ZERO;
+    }
+    let n = self.size as i32;
+    let grid = p / self.patch_size * self.size as f32;
+    let base = grid.floor();
+    let f = grid - base;
+    let sample = |x: i32, y: i32| {
+      self.displacement[(y.rem_euclid(n) * n + x.rem_euclid(n)) as usize]
+    };
+    let (x, y) = (base.x as i32, base.y as i32);
+    let top = sample(x, y).lerp(sample(x + 1, y), f.x);
+    let bottom = sample(x, y + 1).lerp(sample(x + 1, y + 1), f.x);
+    top.lerp(bottom, f.y)
+  }
+
+  /// Wave height (with the given amplitude) at the world position `p`.
+  pub fn height(&self, amplitude: f32, p: Vec2) -> f32 {
+    // The choppy displacement moves the surface horizontally, find the
+    // point on the undisturbed plane that ends up at `p`.
+    let mut q = p;
+    for _ in 0..6 {
+      let d = self.displacement(q) * amplitude;
+      q = p - Vec2::new(d.x, d.z);
+    }
+    amplitude * self.displacement(q).y
+  }
+}
+
+/// Textures written by the FFT compute shader.
+#[derive(Resource, Clone, Debug)]
+pub struct FftOceanImages {
+  /// Initial spectrum, uploaded from the CPU.
+  pub h0: Handle<Image>,
+  /// xyz = displacement, w = d(Dx)/dz.
+  pub displacement: Handle<Image>,
+  /// x = dh/dx, y = dh/dz, z = d(Dx)/dx, w = d(Dz)/dz.
+  pub derivatives: Handle<Image>,
+  /// Wave folding (1 - Jacobian of the displacement) accumulated with decay rates of 0.5, 1, 2
+  /// and 4 per second, for the foam.
+  pub foam: Handle<Image>,
+}
+
+impl FftOceanImages {
+  fn new(ocean: &FftOcean, images: &mut Assets<Image>) -> Self {
+    Self {
+      h0: images.add(h0_image(ocean)),
+      displacement: images.add(storage_image(ocean.simulation_size())),
+      derivatives: images.add(storage_image(ocean.simulation_size())),
+      foam: images.add(storage_image(ocean.simulation_size())),
+    }
+  }
+}
+
+fn h0_image(ocean: &FftOcean) -> Image {
+  let data = ocean
+    .initial_spectrum()
+    .iter()
+    .flat_map(|[h0, h0_minus]| [h0.x, h0.y, h0_minus.x, h0_minus.y])
+    .flat_map(f32::to_le_bytes)
+    .collect();
+  Image::new(
+    Extent3d {
+      width: ocean.simulation_size(),
+      height: ocean.simulation_size(),
+      depth_or_array_layers: 1,
+    },
+    TextureDimension::D2,
+    data,
+    TextureFormat::Rgba32Float,
+    RenderAssetUsages::RENDER_WORLD,
+  )
+}
+
+fn storage_image(size: u32) -> Image {
+  let mut image = Image::new_fill(
+    Extent3d {
+      width: size,
+      height: size,
+      depth_or_array_layers: 1,
+    },
+    TextureDimension::D2,
+    &[0; 8],
+    TextureFormat::Rgba16Float,
+    RenderAssetUsages::RENDER_WORLD,
+  );
+  // WebGL2 doesn't support storage textures.
+  #[cfg(not(feature = "webgl2"))]
+  {
+    image.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING;
+  }
+  image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+    address_mode_u: ImageAddressMode::Repeat,
+    address_mode_v: ImageAddressMode::Repeat,
+    mag_filter: ImageFilterMode::Linear,
+    min_filter: ImageFilterMode::Linear,
+    ..default()
+  });
+  image
+}
+
+/// Rebuild the spectrum when the `FftOcean` settings change.
+fn update_fft_spectrum(
+  ocean: Res<FftOcean>,
+  mut state: ResMut<FftOceanState>,
+  fft_images: Res<FftOceanImages>,
+  mut images: ResMut<Assets<Image>>,
+) {
+  let resized = state.size != ocean.simulation_size();
+  state.set_ocean(&ocean);
+  images.insert(fft_images.h0.clone(), h0_image(&ocean));
+  if resized {
+    images.insert(fft_images.displacement.clone(), storage_image(ocean.simulation_size()));
+    images.insert(fft_images.derivatives.clone(), storage_image(ocean.simulation_size()));
+    images.insert(fft_images.foam.clone(), storage_image(ocean.simulation_size()));
+  }
+}
+
+fn update_fft_state(time: Res<Time>, mut state: ResMut<FftOceanState>) {
+  state.update(time.elapsed_seconds_wrapped());
+}
+
+/// Give every water material the FFT textures.
+fn update_fft_materials(
+  ocean: Res<FftOcean>,
+  fft_images: Res<FftOceanImages>,
+  mut events: EventReader<AssetEvent<StandardWaterMaterial>>,
+  mut materials: ResMut<Assets<StandardWaterMaterial>>,
+) {
+  let added: Vec<_> = events
+    .read()
+    .filter_map(|event| match event {
+      AssetEvent::Added { id } => Some(*id),
+      _ => None,
+    })
+    .collect();
+  let update = |mat: &mut StandardWaterMaterial| {
+    mat.extension.fft_patch_size = ocean.patch_size;
+    mat.extension.fft_displacement = Some(fft_images.displacement.clone());
+    mat.extension.fft_derivatives = Some(fft_images.derivatives.clone());
+    mat.extension.fft_foam = Some(fft_images.foam.clone());
+  };
+  if ocean.is_changed() {
+    for (_, mat) in materials.iter_mut() {
+      update(mat);
+    }
+  } else {
+    for id in added {
+      if let Some(mat) = materials.get_mut(id) {
+        update(mat);
+      }
+    }
+  }
+}
+
+/// Is the FFT ocean used by the global water or by any `WaterBody`?
+fn fft_in_use<'a>(
+  settings: &super::WaterSettings,
+  mut bodies: impl Iterator<Item = &'a super::WaterBody>,
+) -> bool {
+  let is_fft = |model: &WaveModel| matches!(model, WaveModel::Fft);
+  is_fft(&settings.wave_model) || bodies.any(|body| is_fft(&body.wave_model))
+}
+
+fn uses_fft(settings: Res<super::WaterSettings>, bodies: Query<&super::WaterBody>) -> bool {
+  fft_in_use(&settings, bodies.iter())
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct FftOceanPlugin;
+
+impl Plugin for FftOceanPlugin {
+  fn build(&self, app: &mut App) {
+    load_internal_asset!(
+      app,
+      FFT_OCEAN_SHADER_HANDLE,
+      concat!(env!("CARGO_MANIFEST_DIR"), "/assets/shaders/fft_ocean.wgsl"),
+      Shader::from_wgsl
+    );
+
+    app
+      .init_resource::<FftOcean>()
+      .register_type::<FftOcean>()
+      .add_systems(
+        Update,
+        (
+          update_fft_spectrum.run_if(resource_changed::<FftOcean>),
+          update_fft_state.run_if(uses_fft),
+          update_fft_materials,
+        )
+          .chain(),
+      );
+
+    #[cfg(not(feature = "webgl2"))]
+    compute::build(app);
+  }
+
+  fn finish(&self, app: &mut App) {
+    let ocean = app.world.resource::<FftOcean>().clone();
+    let images = FftOceanImages::new(&ocean, &mut app.world.resource_mut::<Assets<Image>>());
+    app
+      .insert_resource(images)
+      .insert_resource(FftOceanState::new(&ocean));
+
+    #[cfg(not(feature = "webgl2"))]
+    compute::finish(app);
+  }
+}
+
+/// GPU simulation (needs compute shaders).
+#[cfg(not(feature = "webgl2"))]
+mod compute {
+  use std::borrow::Cow;
+
+  use bevy::{
+    prelude::*,
+    render::{
+      extract_resource::{ExtractResource, ExtractResourcePlugin},
+      render_asset::RenderAssets,
+      render_graph::{self, RenderGraph, RenderLabel},
+      render_resource::{binding_types::*, *},
+      renderer::{RenderContext, RenderDevice, RenderQueue},
+      Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+  };
+
+  use super::{fft_in_use, FftOcean, FftOceanImages, FFT_OCEAN_SHADER_HANDLE};
+  use crate::water::{WaterBody, WaterSettings};
+
+  const WORKGROUP_SIZE: u32 = 8;
+
+  impl ExtractResource for FftOceanImages {
+    type Source = FftOceanImages;
+
+    fn extract_resource(source: &Self::Source) -> Self {
+      source.clone()
+    }
+  }
+
+  #[derive(Clone, Default, ShaderType)]
+  struct OceanUniform {
+    time: f32,
+    choppiness: f32,
+    patch_size: f32,
+    size: u32,
+    delta_time: f32,
+  }
+
+  #[derive(Clone, Default, ShaderType)]
+  struct StageUniform {
+    ns: u32,
+    direction: u32,
+  }
+
+  #[derive(Resource)]
+  struct ExtractedFftOcean(OceanUniform);
+
+  #[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+  struct FftOceanLabel;
+
+  pub(super) fn build(app: &mut App) {
+    app.add_plugins(ExtractResourcePlugin::<FftOceanImages>::default());
+    let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+      return;
+    };
+    render_app
+      .add_systems(ExtractSchedule, extract_fft_ocean)
+      .add_systems(
+        Render,
+        prepare_fft_ocean_bind_groups.in_set(RenderSet::PrepareBindGroups),
+      );
+
+    let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+    render_graph.add_node(FftOceanLabel, FftOceanNode::default());
+    render_graph.add_node_edge(FftOceanLabel, bevy::render::graph::CameraDriverLabel);
+  }
+
+  pub(super) fn finish(app: &mut App) {
+    let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+      return;
+    };
+    render_app
+      .init_resource::<FftOceanPipeline>()
+      .init_resource::<FftOceanBindGroups>();
+  }
+
+  /// Only extracted while the FFT ocean is used, the node doesn't run without it.
+  fn extract_fft_ocean(
+    mut commands: Commands,
+    ocean: Extract<Res<FftOcean>>,
+    time: Extract<Res<Time>>,
+    settings: Extract<Res<WaterSettings>>,
+    bodies: Extract<Query<&WaterBody>>,
+  ) {
+    if !fft_in_use(&settings, bodies.iter()) {
+      commands.remove_resource::<ExtractedFftOcean>();
+      return;
+    }
+    commands.insert_resource(ExtractedFftOcean(OceanUniform {
+      time: time.elapsed_seconds_wrapped(),
+      choppiness: ocean.choppiness,
+      patch_size: ocean.patch_size,
+      size: ocean.simulation_size(),
+      delta_time: time.delta_seconds(),
+    }));
+  }
+
+  #[derive(Resource)]
+  struct FftOceanPipeline {
+    layout: BindGroupLayout,
+    spectrum: CachedComputePipelineId,
+    fft: CachedComputePipelineId,
+    resolve: CachedComputePipelineId,
+  }
+
+  impl FromWorld for FftOceanPipeline {
+    fn from_world(world: &mut World) -> Self {
+      let render_device = world.resource::<RenderDevice>();
+      let layout = render_device.create_bind_group_layout(
+        "fft_ocean_layout",
+        &BindGroupLayoutEntries::sequential(
+          ShaderStages::COMPUTE,
+          (
+            uniform_buffer::<OceanUniform>(false),
+            uniform_buffer::<StageUniform>(false),
+            texture_2d(TextureSampleType::Float { filterable: false }),
+            storage_buffer_read_only::<Vec4>(false),
+            storage_buffer::<Vec4>(false),
+            texture_storage_2d(TextureFormat::Rgba16Float, StorageTextureAccess::WriteOnly),
+            texture_storage_2d(TextureFormat::Rgba16Float, StorageTextureAccess::WriteOnly),
+            storage_buffer::<Vec4>(false),
+            texture_storage_2d(TextureFormat::Rgba16Float, StorageTextureAccess::WriteOnly),
+          ),
+        ),
+      );
+      let pipeline_cache = world.resource::<PipelineCache>();
+      let queue = |entry_point: &'static str| {
+        pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+          label: Some(format!("fft_ocean_{entry_point}").into()),
+          layout: vec![layout.clone()],
+          push_constant_ranges: Vec::new(),
+          shader: FFT_OCEAN_SHADER_HANDLE,
+          shader_defs: vec![],
+          entry_point: Cow::from(entry_point),
+        })
+      };
+      let spectrum = queue("spectrum");
+      let fft = queue("fft");
+      let resolve = queue("resolve");
+      Self {
+        layout,
+        spectrum,
+        fft,
+        resolve,
+      }
+    }
+  }
+
+  /// Buffers that only need to be recreated when the simulation size changes.
+  struct FftOceanBuffers {
+    size: u32,
+    ping_pong: [Buffer; 2],
+    /// Accumulated foam, kept between frames.
+    foam: Buffer,
+    /// Spectrum, then one per fft stage, then resolve.
+    stages: Vec<UniformBuffer<StageUniform>>,
+  }
+
+  impl FftOceanBuffers {
+    fn new(size: u32, render_device: &RenderDevice, render_queue: &RenderQueue) -> Self {
+      let buffer = |label| {
+        render_device.create_buffer(&BufferDescriptor {
+          label: Some(label),
+          size: (size * size) as u64 * std::mem::size_of::<Vec4>() as u64,
+          usage: BufferUsages::STORAGE,
+          mapped_at_creation: false,
+        })
+      };
+      let log_size = size.trailing_zeros();
+      let stage = |ns, direction| {
+        let mut buffer = UniformBuffer::from(StageUniform { ns, direction });
+        buffer.write_buffer(render_device, render_queue);
+        buffer
+      };
+      let mut stages = vec![stage(0, 0)];
+      for direction in 0..2 {
+        stages.extend((0..log_size).map(|i| stage(1 << i, direction)));
+      }
+      stages.push(stage(0, 0));
+      Self {
+        size,
+        ping_pong: [buffer("fft_ocean_ping"), buffer("fft_ocean_pong")],
+        foam: buffer("fft_ocean_foam"),
+        stages,
+      }
+    }
+  }
+
+  #[derive(Resource, Default)]
+  struct FftOceanBindGroups {
+    size: u32,
+    ocean: UniformBuffer<OceanUniform>,
+    buffers: Option<FftOceanBuffers>,
+    /// Views (h0, displacement, derivatives, foam) the `passes` were created with.
+    views: Option<[TextureViewId; 4]>,
+    /// One bind group per pass.
+    passes: Vec<BindGroup>,
+  }
+
+  fn prepare_fft_ocean_bind_groups(
+    pipeline: Res<FftOceanPipeline>,
+    extracted: Option<Res<ExtractedFftOcean>>,
+    fft_images: Option<Res<FftOceanImages>>,
+    gpu_images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    bind_groups: ResMut<FftOceanBindGroups>,
+  ) {
+    let (Some(extracted), Some(fft_images)) = (extracted, fft_images) else {
+      return;
+    };
+    let size = extracted.0.size;
+    if !size.is_power_of_two() || size < WORKGROUP_SIZE * 2 {
+      return;
+    }
+    let (Some(h0), Some(displacement), Some(derivatives), Some(foam)) = (
+      gpu_images.get(&fft_images.h0),
+      gpu_images.get(&fft_images.displacement),
+      gpu_images.get(&fft_images.derivatives),
+      gpu_images.get(&fft_images.foam),
+    ) else {
+      return;
+    };
+    // Wait for the resized images.
+    if h0.size.x as u32 != size || displacement.size.x as u32 != size || foam.size.x as u32 != size
+    {
+      return;
+    }
+
+    let bind_groups = bind_groups.into_inner();
+    bind_groups.size = size;
+    bind_groups.ocean.set(extracted.0.clone());
+    bind_groups.ocean.write_buffer(&render_device, &render_queue);
+
+    if bind_groups.buffers.as_ref().map(|b| b.size) != Some(size) {
+      bind_groups.buffers = Some(FftOceanBuffers::new(size, &render_device, &render_queue));
+      bind_groups.views = None;
+    }
+    // The bind groups only change with the textures.
+    let views = [h0, displacement, derivatives, foam].map(|image| image.texture_view.id());
+    if bind_groups.views == Some(views) {
+      return;
+    }
+    let buffers = bind_groups.buffers.as_ref().unwrap();
+    let Some(ocean_binding) = bind_groups.ocean.binding() else {
+      return;
+    };
+    bind_groups.passes = buffers
+      .stages
+      .iter()
+      .enumerate()
+      .filter_map(|(idx, stage)| {
+        // The spectrum writes into the first buffer, then every fft stage swaps the buffers.
+        let (src, dst) = if idx == 0 {
+          (1, 0)
+        } else {
+          ((idx - 1) % 2, idx % 2)
+        };
+        Some(render_device.create_bind_group(
+          "fft_ocean_bind_group",
+          &pipeline.layout,
+          &BindGroupEntries::sequential((
+            ocean_binding.clone(),
+            stage.binding()?,
+            &h0.texture_view,
+            buffers.ping_pong[src].as_entire_binding(),
+            buffers.ping_pong[dst].as_entire_binding(),
+            &displacement.texture_view,
+            &derivatives.texture_view,
+            buffers.foam.as_entire_binding(),
+            &foam.texture_view,
+          )),
+        ))
+      })
+      .collect();
+    bind_groups.views = Some(views);
+  }
+
+  #[derive(Default)]
+  struct FftOceanNode {
+    ready: bool,
+  }
+
+  impl render_graph::Node for FftOceanNode {
+    fn update(&mut self, world: &mut World) {
+      if self.ready {
+        return;
+      }
+      let pipeline = world.resource::<FftOceanPipeline>();
+      let pipeline_cache = world.resource::<PipelineCache>();
+      self.ready = [pipeline.spectrum, pipeline.fft, pipeline.resolve]
+        .iter()
+        .all(|id| pipeline_cache.get_compute_pipeline(*id).is_some());
+    }
+
+    fn run(
+      &self,
+      _graph: &mut render_graph::RenderGraphContext,
+      render_context: &mut RenderContext,
+      world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+      if !self.ready || !world.contains_resource::<ExtractedFftOcean>() {
+        return Ok(());
+      }
+      let bind_groups = world.resource::<FftOceanBindGroups>();
+      if bind_groups.passes.is_empty() {
+        return Ok(());
+      }
+      let pipeline = world.resource::<FftOceanPipeline>();
+      let pipeline_cache = world.resource::<PipelineCache>();
+      let (Some(spectrum), Some(fft), Some(resolve)) = (
+        pipeline_cache.get_compute_pipeline(pipeline.spectrum),
+        pipeline_cache.get_compute_pipeline(pipeline.fft),
+        pipeline_cache.get_compute_pipeline(pipeline.resolve),
+      ) else {
+        return Ok(());
+      };
+
+      let size = bind_groups.size;
+      let groups = size / WORKGROUP_SIZE;
+      let last = bind_groups.passes.len() - 1;
+      let mut pass = render_context
+        .command_encoder()
+        .begin_compute_pass(&ComputePassDescriptor {
+          label: Some("fft_ocean"),
+          ..default()
+        });
+
+      pass.set_pipeline(spectrum);
+      pass.set_bind_group(0, &bind_groups.passes[0], &[]);
+      pass.dispatch_workgroups(groups, groups, 1);
+
+      pass.set_pipeline(fft);
+      for bind_group in &bind_groups.passes[1..last] {
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.dispatch_workgroups(groups / 2, groups, 1);
+      }
+
+      pass.set_pipeline(resolve);
+      pass.set_bind_group(0, &bind_groups.passes[last], &[]);
+      pass.dispatch_workgroups(groups, groups, 1);
+
+      Ok(())
+    }
+  }
+}
@@ -0,0 +1,121 @@
+//! Flow maps.
+//!
+//! A `WaterFlowMap` is a top-down image of the water velocity, for rivers and streams.
+//! The water shader scrolls the waves, normals and foam along the flow, blending two
+//! phases of the scrolling to hide the stretching.  `WaterParam::flow_velocity` reads the
+//! same image, and the drag of `Buoyancy` bodies pulls them along with the flow.
+use bevy::{
+  prelude::*,
+  render::{
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat},
+  },
+};
+
+/// A top-down map of the water velocity.
+#[derive(Clone, Debug, Reflect)]
+pub struct WaterFlowMap {
+  /// Velocity (`x`, `z`) divided by `max_speed` (`Rg8Snorm`).
+  pub image: Handle<Image>,
+  /// World space area (`x`, `z`) covered by the image, still water is assumed outside.
+  pub rect: Rect,
+  /// Speed (m/s) stored as 1.0, faster flow is clamped.
+  pub max_speed: f32,
+  /// Duration (seconds) of a scrolling phase.  Longer cycles stretch the waves more.
+  pub cycle: f32,
+}
+
+/// The two phases of the scrolling at a position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlowPhases {
+  /// Offsets of the wave coordinates of the two phases.
+  pub offsets: [Vec2; 2],
+  /// Weight of the second phase.
+  pub blend: f32,
+}
+
+impl WaterFlowMap {
+  pub fn new(image: Handle<Image>, rect: Rect, max_speed: f32) -> Self {
+    Self {
+      image,
+      rect,
+      max_speed,
+      cycle: 2.0,
+    }
+  }
+
+  /// Bake the `velocity` (m/s, `x`, `z`) at a world position (`x`, `z`).
+  ///
+  /// `velocity` is sampled at the texel centres of a `size` image covering `rect`.
+  pub fn from_fn(
+    rect: Rect,
+    size: UVec2,
+    max_speed: f32,
+    velocity: impl Fn(Vec2) -> Vec2,
+    images: &mut Assets<Image>,
+  ) -> Self {
+    let size = size.max(UVec2::ONE);
+    let texel = rect.size() / size.as_vec2();
+    let mut data = Vec::with_capacity((size.x * size.y * 2) as usize);
+    for y in 0..size.y {
+      for x in 0..size.x {
+        let p = rect.min + (Vec2::new(x as f32, y as f32) + 0.5) * texel;
+        let normalized = (velocity(p) / max_speed).clamp(Vec2::NEG_ONE, Vec2::ONE);
+        for v in normalized.to_array() {
+          data.push((v * 127.0).round() as i8 as u8);
+        }
+      }
+    }
+    let image = Image::new(
+      Extent3d {
+        width: size.x,
+        height: size.y,
+        depth_or_array_layers: 1,
+      },
+      TextureDimension::D2,
+      data,
+      TextureFormat::Rg8Snorm,
+      RenderAssetUsages::default(),
+    );
+    Self::new(images.add(image), rect, max_speed)
+  }
+
+  /// Flow velocity (m/s, `x`, `z`) at the world position `p` (`x`, `z`).
+  ///
+  /// Bilinear like the water shader.  `None` if the image isn't loaded.
+  pub fn velocity(&self, images: &Assets<Image>, p: Vec2) -> Option<Vec2> {
+    let image = images.get(&self.image)?;
+    if !self.rect.contains(p) {
+      return Some(Vec2::ZERO);
+    }
+    let size = image.size();
+    if size.x == 0 || size.y == 0 || image.data.len() < (size.x * size.y * 2) as usize {
+      return None;
+    }
+    let texel = |x: i32, y: i32| {
+      let x = x.clamp(0, size.x as i32 - 1) as u32;
+      let y = y.clamp(0, size.y as i32 - 1) as u32;
+      let idx = ((y * size.x + x) * 2) as usize;
+      let decode = |byte: u8| (byte as i8 as f32 / 127.0).max(-1.0);
+      Vec2::new(decode(image.data[idx]), decode(image.data[idx + 1]))
+    };
+    let uv = (p - self.rect.min) / self.rect.size() * size.as_vec2() - 0.5;
+    let (i, f) = (uv.floor().as_ivec2(), uv - uv.floor());
+    let top = texel(i.x, i.y).lerp(texel(i.x + 1, i.y), f.x);
+    let bottom = texel(i.x, i.y + 1).lerp(texel(i.x + 1, i.y + 1), f.x);
+    Some(top.lerp(bottom, f.y) * self.max_speed)
+  }
+
+  /// The scrolling of the waves by the flow `velocity` at the `time`, like the water shader.
+  ///
+  /// Each phase scrolls for a `cycle` and jumps back while the other one is shown.
+  pub fn phases(&self, velocity: Vec2, time: f32) -> FlowPhases {
+    let cycle = self.cycle.max(0.01);
+    let phase = (time / cycle).fract();
+    let phases = [phase, (phase + 0.5).fract()];
+    FlowPhases {
+      offsets: phases.map(|phase| -velocity * phase * cycle),
+      blend: (1.0 - 2.0 * phase).abs(),
+    }
+  }
+}
@@ -6,9 +6,131 @@ use bevy::{
   render::{mesh::MeshVertexBufferLayout, render_asset::*, render_resource::*},
 };
 
+use super::{rain::WaterRain, WaterBody, WaterSettings};
+use crate::wave::{WaveModel, MAX_GERSTNER_WAVES};
+
 pub type StandardWaterMaterial = ExtendedMaterial<StandardMaterial, WaterMaterial>;
 
+/// `StandardMaterial::specular_transmission` used to draw the water in the transmissive
+/// pass, where the scene color is available to the water shader.
+pub const WATER_TRANSMISSION: f32 = 1e-4;
+
+/// Screen space reflections of the water surface.
+///
+/// The reflection rays are marched against the depth prepass (add `DepthPrepass` to the
+/// camera), and the reflected color is read from the scene drawn before the water.  Rays
+/// that miss reflect the environment map of the camera, or `sky_color` without one.
+///
+/// The water is drawn like a transmissive `StandardMaterial` (after the opaque pass) and
+/// blends with the scene behind it itself, so the material `alpha_mode` becomes `Opaque`.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug)]
+pub struct ScreenSpaceReflections {
+  /// Ray march steps.
+  pub steps: u32,
+  /// Longest reflection ray (meters).
+  pub max_distance: f32,
+  /// Assumed thickness (meters) of the objects in the depth buffer.
+  pub thickness: f32,
+  /// Reflected color of the rays that miss, the alpha is the strength.
+  pub sky_color: Color,
+}
+
+impl Default for ScreenSpaceReflections {
+  fn default() -> Self {
+    Self {
+      steps: 32,
+      max_distance: 200.0,
+      thickness: 2.0,
+      sky_color: Color::rgba(0.6, 0.75, 0.9, 1.0),
+    }
+  }
+}
+
+/// Screen space refraction of the scene below the water.
+///
+/// The scene drawn before the water is read with an offset from the wave normals, which
+/// grows with the water depth (from the depth prepass, add `DepthPrepass` to the camera).
+///
+/// Like `ScreenSpaceReflections`, the water blends with the scene itself, so the material
+/// `alpha_mode` becomes `Opaque`.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug)]
+pub struct Refraction {
+  /// Largest offset (fraction of the screen).
+  pub strength: f32,
+  /// Water depth (meters) where the offset reaches `strength`.
+  pub depth: f32,
+}
+
+impl Default for Refraction {
+  fn default() -> Self {
+    Self {
+      strength: 0.05,
+      depth: 2.0,
+    }
+  }
+}
+
+/// Foam on the wave crests, where the surface is steep or folding over.
+///
+/// The foam covers the waves where they fold past `threshold`.  `WaveModel::Fft` foam is
+/// accumulated in a texture by the FFT compute pass and fades at the `decay` rate (0.5 to 4
+/// per second); the other wave models only foam where the waves fold now, and ignore `decay`
+/// (a warning is logged when it's changed for them).
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug)]
+pub struct Foam {
+  /// Foam color, the alpha is the opacity.
+  pub color: Color,
+  /// Wave folding (1 - Jacobian of the horizontal displacement, 0 = flat, 1 = folding over)
+  /// where the foam starts.  `WaveModel::Noise` waves are folded like gerstner waves.
+  pub threshold: f32,
+  /// How fast the `WaveModel::Fft` foam fades (per second).  Only used by `WaveModel::Fft`.
+  pub decay: f32,
+}
+
+impl Default for Foam {
+  fn default() -> Self {
+    Self {
+      color: Color::rgba(0.95, 0.97, 1.0, 1.0),
+      threshold: 0.3,
+      decay: 1.0,
+    }
+  }
+}
+
+impl Foam {
+  /// Is `decay` changed for a wave model without foam build-up, where it's ignored.
+  pub fn ignores_decay(&self, wave_model: &WaveModel) -> bool {
+    !matches!(wave_model, WaveModel::Fft) && self.decay != Self::default().decay
+  }
+}
+
+/// Warn (once) about a foam `decay` set for a wave model that ignores it.
+pub(crate) fn warn_ignored_foam_decay(
+  settings: Res<WaterSettings>,
+  bodies: Query<&WaterBody, Changed<WaterBody>>,
+  mut warned: Local<bool>,
+) {
+  if *warned {
+    return;
+  }
+  let global = settings
+    .is_changed()
+    .then_some((settings.foam, &settings.wave_model));
+  let ignored = global
+    .into_iter()
+    .chain(bodies.iter().map(|body| (body.foam, &body.wave_model)))
+    .any(|(foam, wave_model)| foam.is_some_and(|foam| foam.ignores_decay(wave_model)));
+  if ignored {
+    warn!("Foam::decay is only used by WaveModel::Fft, the other wave models don't build up foam.");
+    *warned = true;
+  }
+}
+
 #[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+#[bind_group_data(WaterMaterialKey)]
 #[uniform(100, WaterMaterialUniform)]
 #[reflect(Default, Debug)]
 pub struct WaterMaterial {
@@ -24,8 +146,78 @@ pub struct WaterMaterial {
   pub edge_scale: f32,
   /// Wave amplitude.
   pub amplitude: f32,
+  /// Wave model.
+  pub wave_model: WaveModel,
+  /// FFT ocean displacement texture, used by `WaveModel::Fft`.
+  #[texture(101)]
+  #[sampler(102)]
+  pub fft_displacement: Option<Handle<Image>>,
+  /// FFT ocean derivatives texture, used by `WaveModel::Fft`.
+  #[texture(103)]
+  #[sampler(104)]
+  pub fft_derivatives: Option<Handle<Image>>,
+  /// FFT ocean foam texture, used by `WaveModel::Fft` with `foam`.
+  #[texture(107)]
+  #[sampler(108)]
+  pub fft_foam: Option<Handle<Image>>,
+  /// World size of the FFT ocean patch.
+  pub fft_patch_size: f32,
   pub coord_offset: Vec2,
   pub coord_scale: Vec2,
+  /// The mesh is a screen space grid (see `projected_grid_mesh`) that the vertex shader
+  /// projects onto the water plane at the mesh's height.
+  pub projected_grid: bool,
+  /// Screen space reflections.
+  pub ssr: Option<ScreenSpaceReflections>,
+  /// Screen space refraction.
+  pub refraction: Option<Refraction>,
+  /// Wave crest foam.
+  pub foam: Option<Foam>,
+  /// Image of the `PlanarReflection` camera, reflected instead of the screen space
+  /// reflections.
+  #[texture(105)]
+  #[sampler(106)]
+  pub planar_reflection: Option<Handle<Image>>,
+  /// Offset of the planar reflection by the wave normals (fraction of the screen).
+  pub planar_reflection_distortion: f32,
+  /// Baked water depth (see `WaterDepthMap`), used instead of the depth prepass.
+  #[texture(109)]
+  #[sampler(110)]
+  pub depth_map: Option<Handle<Image>>,
+  /// World space area (`x`, `z`) covered by the `depth_map`.
+  pub depth_map_rect: Rect,
+  /// Depth (meters) of 1.0 in the `depth_map`.
+  pub depth_map_max_depth: f32,
+  /// The waves shrink in water shallower than this (meters), 0.0 = no damping.
+  pub wave_damping_depth: f32,
+  /// Growth of the waves approaching the `wave_damping_depth`.
+  pub wave_shoaling: f32,
+  /// Ripple simulation texture (see `WaterRipples`).
+  #[texture(111)]
+  #[sampler(112)]
+  pub ripples: Option<Handle<Image>>,
+  /// World space area covered by the `ripples`, written every frame (see
+  /// `WaterRipplesFrame`).
+  #[texture(117, filterable = false)]
+  pub ripples_area: Option<Handle<Image>>,
+  /// Trails of the `Wake` entities (see `WaterWakes`).
+  #[texture(113, filterable = false)]
+  pub wakes: Option<Handle<Image>>,
+  /// Rain rings.
+  pub rain: Option<WaterRain>,
+  /// Active splashes (see `WaterSplashes`).
+  #[texture(114, filterable = false)]
+  pub splashes: Option<Handle<Image>>,
+  /// Flow velocity (see `WaterFlowMap`), scrolls the waves along the flow.
+  #[texture(115)]
+  #[sampler(116)]
+  pub flow_map: Option<Handle<Image>>,
+  /// World space area (`x`, `z`) covered by the `flow_map`.
+  pub flow_map_rect: Rect,
+  /// Speed (m/s) of 1.0 in the `flow_map`.
+  pub flow_max_speed: f32,
+  /// Duration (seconds) of a scrolling phase.
+  pub flow_cycle: f32,
 }
 
 impl Default for WaterMaterial {
@@ -37,8 +229,67 @@ impl Default for WaterMaterial {
       edge_color: Color::rgba(1.0, 1.0, 1.0, 1.0),
       edge_scale: 0.1,
       amplitude: 1.0,
+      wave_model: WaveModel::default(),
+      fft_displacement: None,
+      fft_derivatives: None,
+      fft_foam: None,
+      fft_patch_size: 1.0,
       coord_offset: Vec2::new(0.0, 0.0),
       coord_scale: Vec2::new(1.0, 1.0),
+      projected_grid: false,
+      ssr: None,
+      refraction: None,
+      foam: None,
+      planar_reflection: None,
+      planar_reflection_distortion: 0.0,
+      depth_map: None,
+      depth_map_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+      depth_map_max_depth: 1.0,
+      wave_damping_depth: 0.0,
+      wave_shoaling: 0.0,
+      ripples: None,
+      ripples_area: None,
+      wakes: None,
+      rain: None,
+      splashes: None,
+      flow_map: None,
+      flow_map_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+      flow_max_speed: 0.0,
+      flow_cycle: 1.0,
+    }
+  }
+}
+
+/// The pipeline key for `WaterMaterial`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct WaterMaterialKey {
+  projected_grid: bool,
+  ssr: bool,
+  refraction: bool,
+  foam: bool,
+  planar_reflection: bool,
+  depth_map: bool,
+  ripples: bool,
+  wakes: bool,
+  rain: bool,
+  splashes: bool,
+  flow_map: bool,
+}
+
+impl From<&WaterMaterial> for WaterMaterialKey {
+  fn from(material: &WaterMaterial) -> Self {
+    Self {
+      projected_grid: material.projected_grid,
+      ssr: material.ssr.is_some(),
+      refraction: material.refraction.is_some(),
+      foam: material.foam.is_some(),
+      planar_reflection: material.planar_reflection.is_some(),
+      depth_map: material.depth_map.is_some(),
+      ripples: material.ripples.is_some(),
+      wakes: material.wakes.is_some(),
+      rain: material.rain.is_some(),
+      splashes: material.splashes.is_some(),
+      flow_map: material.flow_map.is_some(),
     }
   }
 }
@@ -53,6 +304,43 @@ pub struct WaterMaterialUniform {
   pub amplitude: f32,
   pub clarity: f32,
   pub edge_scale: f32,
+  pub wave_mode: u32,
+  pub wave_count: u32,
+  pub waves: [Vec4; MAX_GERSTNER_WAVES],
+  pub fft_patch_size: f32,
+  pub noise_rotation: Vec2,
+  pub noise_scale: f32,
+  pub noise_fade_rotation: Vec2,
+  pub noise_fade_scale: f32,
+  pub noise_fade: f32,
+  pub ssr_steps: u32,
+  pub ssr_max_distance: f32,
+  pub ssr_thickness: f32,
+  pub sky_color: Color,
+  pub refraction_strength: f32,
+  pub refraction_depth: f32,
+  pub planar_reflection_distortion: f32,
+  pub foam_color: Color,
+  pub foam_threshold: f32,
+  pub foam_decay: f32,
+  /// xy = `depth_map_rect.min`, zw = 1 / `depth_map_rect.size()`.
+  pub depth_map_rect: Vec4,
+  pub depth_map_max_depth: f32,
+  pub wave_damping_depth: f32,
+  pub wave_shoaling: f32,
+  pub rain_intensity: f32,
+  pub rain_drop_size: f32,
+  pub rain_strength: f32,
+  /// Like `depth_map_rect`.
+  pub flow_map_rect: Vec4,
+  pub flow_max_speed: f32,
+  pub flow_cycle: f32,
+}
+
+/// Pack `rect` as the offset and scale from world to texture coordinates.
+pub(crate) fn texture_rect(rect: Rect) -> Vec4 {
+  let scale = 1.0 / rect.size().max(Vec2::splat(1e-6));
+  Vec4::new(rect.min.x, rect.min.y, scale.x, scale.y)
 }
 
 impl From<WaterMaterial> for WaterMaterialUniform {
@@ -66,6 +354,35 @@ impl From<WaterMaterial> for WaterMaterialUniform {
       edge_color: material.edge_color,
       coord_offset: material.coord_offset,
       coord_scale: material.coord_scale,
+      wave_mode: material.wave_model.mode(),
+      wave_count: material.wave_model.gerstner_waves().len() as u32,
+      waves: material.wave_model.packed_waves(),
+      fft_patch_size: material.fft_patch_size,
+      noise_rotation: material.wave_model.noise_waves().rotation(),
+      noise_scale: material.wave_model.noise_waves().scale(),
+      noise_fade_rotation: material.wave_model.noise_fade().rotation(),
+      noise_fade_scale: material.wave_model.noise_fade().scale(),
+      noise_fade: material.wave_model.noise_waves().fade_weight(),
+      ssr_steps: material.ssr.map_or(0, |ssr| ssr.steps),
+      ssr_max_distance: material.ssr.map_or(0.0, |ssr| ssr.max_distance),
+      ssr_thickness: material.ssr.map_or(0.0, |ssr| ssr.thickness),
+      sky_color: material.ssr.map_or(Color::NONE, |ssr| ssr.sky_color),
+      refraction_strength: material.refraction.map_or(0.0, |r| r.strength),
+      refraction_depth: material.refraction.map_or(0.0, |r| r.depth),
+      planar_reflection_distortion: material.planar_reflection_distortion,
+      foam_color: material.foam.map_or(Color::NONE, |foam| foam.color),
+      foam_threshold: material.foam.map_or(0.0, |foam| foam.threshold),
+      foam_decay: material.foam.map_or(0.0, |foam| foam.decay),
+      depth_map_rect: texture_rect(material.depth_map_rect),
+      depth_map_max_depth: material.depth_map_max_depth,
+      wave_damping_depth: material.wave_damping_depth,
+      wave_shoaling: material.wave_shoaling,
+      rain_intensity: material.rain.map_or(0.0, |rain| rain.intensity),
+      rain_drop_size: material.rain.map_or(0.0, |rain| rain.drop_size),
+      rain_strength: material.rain.map_or(0.0, |rain| rain.strength),
+      flow_map_rect: texture_rect(material.flow_map_rect),
+      flow_max_speed: material.flow_max_speed,
+      flow_cycle: material.flow_cycle,
     }
   }
 }
@@ -81,6 +398,35 @@ impl AsBindGroupShaderType<WaterMaterialUniform> for WaterMaterial {
       edge_color: self.edge_color,
       coord_offset: self.coord_offset,
       coord_scale: self.coord_scale,
+      wave_mode: self.wave_model.mode(),
+      wave_count: self.wave_model.gerstner_waves().len() as u32,
+      waves: self.wave_model.packed_waves(),
+      fft_patch_size: self.fft_patch_size,
+      noise_rotation: self.wave_model.noise_waves().rotation(),
+      noise_scale: self.wave_model.noise_waves().scale(),
+      noise_fade_rotation: self.wave_model.noise_fade().rotation(),
+      noise_fade_scale: self.wave_model.noise_fade().scale(),
+      noise_fade: self.wave_model.noise_waves().fade_weight(),
+      ssr_steps: self.ssr.map_or(0, |ssr| ssr.steps),
+      ssr_max_distance: self.ssr.map_or(0.0, |ssr| ssr.max_distance),
+      ssr_thickness: self.ssr.map_or(0.0, |ssr| ssr.thickness),
+      sky_color: self.ssr.map_or(Color::NONE, |ssr| ssr.sky_color),
+      refraction_strength: self.refraction.map_or(0.0, |r| r.strength),
+      refraction_depth: self.refraction.map_or(0.0, |r| r.depth),
+      planar_reflection_distortion: self.planar_reflection_distortion,
+      foam_color: self.foam.map_or(Color::NONE, |foam| foam.color),
+      foam_threshold: self.foam.map_or(0.0, |foam| foam.threshold),
+      foam_decay: self.foam.map_or(0.0, |foam| foam.decay),
+      depth_map_rect: texture_rect(self.depth_map_rect),
+      depth_map_max_depth: self.depth_map_max_depth,
+      wave_damping_depth: self.wave_damping_depth,
+      wave_shoaling: self.wave_shoaling,
+      rain_intensity: self.rain.map_or(0.0, |rain| rain.intensity),
+      rain_drop_size: self.rain.map_or(0.0, |rain| rain.drop_size),
+      rain_strength: self.rain.map_or(0.0, |rain| rain.strength),
+      flow_map_rect: texture_rect(self.flow_map_rect),
+      flow_max_speed: self.flow_max_speed,
+      flow_cycle: self.flow_cycle,
     }
   }
 }
@@ -95,6 +441,19 @@ pub const WATER_BINDINGS_HANDLE: Handle<Shader> = Handle::weak_from_u128(0xa9010
 
 pub const WATER_FUNCTIONS_HANDLE: Handle<Shader> = Handle::weak_from_u128(0xb73bf2f50994c394);
 
+pub const WATER_FOAM_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x3fd2e6a10b97c584);
+
+pub const WATER_WAKE_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x71e9b3c58d046a2f);
+
+pub const WATER_RAIN_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x4c0a7e2f95d31b86);
+
+pub const WATER_TRANSMISSION_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x8a61f2d93c4e0b57);
+
+pub const WATER_SSR_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x6e0d4c2b9a7f1358);
+
+pub const WATER_PLANAR_REFLECTION_HANDLE: Handle<Shader> =
+  Handle::weak_from_u128(0x15c3a8e07d2b94f6);
+
 pub const WATER_VERTEX_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0xcea5177230c961ac);
 
 pub const WATER_FRAGMENT_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0xbe72b1f6760558cb);
@@ -140,9 +499,65 @@ impl MaterialExtension for WaterMaterial {
     _pipeline: &MaterialExtensionPipeline,
     descriptor: &mut RenderPipelineDescriptor,
     _layout: &MeshVertexBufferLayout,
-    _key: MaterialExtensionKey<Self>,
+    key: MaterialExtensionKey<Self>,
   ) -> Result<(), SpecializedMeshPipelineError> {
     descriptor.primitive.cull_mode = None;
+    // All of the water textures are bound.
+    descriptor.vertex.shader_defs.push("WATER_MATERIAL".into());
+    if let Some(fragment) = descriptor.fragment.as_mut() {
+      fragment.shader_defs.push("WATER_MATERIAL".into());
+    }
+    if key.bind_group_data.projected_grid {
+      descriptor
+        .vertex
+        .shader_defs
+        .push("WATER_PROJECTED_GRID".into());
+    }
+    if key.bind_group_data.depth_map {
+      descriptor.vertex.shader_defs.push("WATER_DEPTH_MAP".into());
+      if let Some(fragment) = descriptor.fragment.as_mut() {
+        fragment.shader_defs.push("WATER_DEPTH_MAP".into());
+      }
+    }
+    if key.bind_group_data.flow_map {
+      descriptor.vertex.shader_defs.push("WATER_FLOW_MAP".into());
+      if let Some(fragment) = descriptor.fragment.as_mut() {
+        fragment.shader_defs.push("WATER_FLOW_MAP".into());
+      }
+    }
+    if key.bind_group_data.ripples {
+      descriptor.vertex.shader_defs.push("WATER_RIPPLES".into());
+      if let Some(fragment) = descriptor.fragment.as_mut() {
+        fragment.shader_defs.push("WATER_RIPPLES".into());
+      }
+    }
+    if let Some(fragment) = descriptor.fragment.as_mut() {
+      if key.bind_group_data.ssr || key.bind_group_data.refraction {
+        // The water blends with the scene drawn before it.
+        fragment.shader_defs.push("WATER_TRANSMISSION".into());
+      }
+      if key.bind_group_data.ssr {
+        fragment.shader_defs.push("WATER_SSR".into());
+      }
+      if key.bind_group_data.refraction {
+        fragment.shader_defs.push("WATER_REFRACTION".into());
+      }
+      if key.bind_group_data.foam {
+        fragment.shader_defs.push("WATER_FOAM".into());
+      }
+      if key.bind_group_data.planar_reflection {
+        fragment.shader_defs.push("WATER_PLANAR_REFLECTION".into());
+      }
+      if key.bind_group_data.wakes {
+        fragment.shader_defs.push("WATER_WAKES".into());
+      }
+      if key.bind_group_data.rain {
+        fragment.shader_defs.push("WATER_RAIN".into());
+      }
+      if key.bind_group_data.splashes {
+        fragment.shader_defs.push("WATER_SPLASHES".into());
+      }
+    }
     Ok(())
   }
 }
@@ -199,6 +614,63 @@ impl Plugin for WaterMaterialPlugin {
       Shader::from_wgsl
     );
 
+    load_internal_asset!(
+      app,
+      WATER_FOAM_HANDLE,
+      concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/shaders/water_foam.wgsl"
+      ),
+      Shader::from_wgsl
+    );
+
+    load_internal_asset!(
+      app,
+      WATER_WAKE_HANDLE,
+      concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/shaders/water_wake.wgsl"
+      ),
+      Shader::from_wgsl
+    );
+
+    load_internal_asset!(
+      app,
+      WATER_RAIN_HANDLE,
+      concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/shaders/water_rain.wgsl"
+      ),
+      Shader::from_wgsl
+    );
+
+    load_internal_asset!(
+      app,
+      WATER_TRANSMISSION_HANDLE,
+      concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/shaders/water_transmission.wgsl"
+      ),
+      Shader::from_wgsl
+    );
+
+    load_internal_asset!(
+      app,
+      WATER_SSR_HANDLE,
+      concat!(env!("CARGO_MANIFEST_DIR"), "/assets/shaders/water_ssr.wgsl"),
+      Shader::from_wgsl
+    );
+
+    load_internal_asset!(
+      app,
+      WATER_PLANAR_REFLECTION_HANDLE,
+      concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/shaders/water_planar_reflection.wgsl"
+      ),
+      Shader::from_wgsl
+    );
+
     load_internal_asset!(
       app,
       WATER_VERTEX_SHADER_HANDLE,
@@ -0,0 +1,345 @@
+//! Planar reflections of calm water bodies.
+//!
+//! A `PlanarReflection` on a `WaterBody` spawns a camera that mirrors the view camera
+//! below the water plane and renders to a texture, which the water material samples
+//! with the wave normals offsetting it.  The mirrored camera clips everything below the
+//! wave crests with an oblique near plane, and never draws the water body itself (its
+//! material samples the reflection image).
+use bevy::{
+  core_pipeline::{core_3d::graph::Core3d, tonemapping::Tonemapping},
+  math::Vec3A,
+  prelude::*,
+  render::{
+    camera::{CameraProjection, CameraRenderGraph, RenderTarget},
+    primitives::Frustum,
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    view::VisibleEntities,
+  },
+  utils::HashSet,
+};
+
+use super::{body::*, fft::FftOceanState, material::StandardWaterMaterial};
+use crate::wave::WaveModel;
+
+/// Planar reflections of a `WaterBody`.
+///
+/// Best suited to small, nearly flat lakes and pools: the reflection ignores the wave
+/// shape, and is clipped at the wave crests.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct PlanarReflection {
+  /// Camera to reflect.  Defaults to the first active 3d camera rendering to a window.
+  pub camera: Option<Entity>,
+  /// Size of the reflection texture relative to the camera's viewport.
+  pub resolution_scale: f32,
+  /// Offset of the reflection by the wave normals (fraction of the screen).
+  pub distortion: f32,
+  /// Raises the clip plane above the wave crests (meters).
+  pub clip_offset: f32,
+}
+
+impl Default for PlanarReflection {
+  fn default() -> Self {
+    Self {
+      camera: None,
+      resolution_scale: 0.5,
+      distortion: 0.02,
+      clip_offset: 0.0,
+    }
+  }
+}
+
+/// Marks the mirrored camera of a `PlanarReflection`.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct PlanarReflectionCamera {
+  /// The water body entity.
+  pub body: Entity,
+}
+
+/// The reflection camera and its image, on the water body entity.
+#[derive(Component, Clone, Debug)]
+pub(crate) struct PlanarReflectionTarget {
+  camera: Entity,
+  image: Handle<Image>,
+}
+
+/// Perspective projection with the near plane replaced by a clip plane.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component, Default)]
+pub struct PlanarReflectionProjection {
+  pub perspective: PerspectiveProjection,
+  /// View space clip plane (normal, distance), the positive side is kept.
+  ///
+  /// Ignored when zero or when the camera is on the kept side.
+  pub clip_plane: Vec4,
+}
+
+impl CameraProjection for PlanarReflectionProjection {
+  fn get_projection_matrix(&self) -> Mat4 {
+    let mut matrix = self.perspective.get_projection_matrix();
+    let normal_length = self.clip_plane.truncate().length();
+    if normal_length == 0.0 || self.clip_plane.w >= 0.0 {
+      return matrix;
+    }
+    let plane = self.clip_plane / normal_length;
+    // With reverse z the depth is 1 on the clip plane.  Scale the plane so that the depth
+    // stays above 0 inside the frustum (the depth goes down with the distance from the
+    // plane relative to the view depth, which is at most the length of a corner ray).
+    let tan = (self.perspective.fov * 0.5).tan();
+    let aspect = self.perspective.aspect_ratio;
+    let corner = (1.0 + tan * tan * (1.0 + aspect * aspect)).sqrt();
+    let row = matrix.row(3) - plane / corner;
+    matrix.x_axis.z = row.x;
+    matrix.y_axis.z = row.y;
+    matrix.z_axis.z = row.z;
+    matrix.w_axis.z = row.w;
+    matrix
+  }
+
+  fn update(&mut self, width: f32, height: f32) {
+    self.perspective.update(width, height);
+  }
+
+  fn far(&self) -> f32 {
+    self.perspective.far()
+  }
+
+  fn get_frustum_corners(&self, z_near: f32, z_far: f32) -> [Vec3A; 8] {
+    self.perspective.get_frustum_corners(z_near, z_far)
+  }
+}
+
+/// Mirror a camera `transform` below the water plane at `height`.
+///
+/// The mirrored camera sees the reflected scene flipped horizontally.
+pub fn mirror_transform(transform: &Transform, height: f32) -> Transform {
+  let mirror = Vec3::new(1.0, -1.0, 1.0);
+  let right = *transform.right() * mirror;
+  let up = *transform.up() * mirror;
+  let back = *transform.back() * mirror;
+  let mut translation = transform.translation;
+  translation.y = height * 2.0 - translation.y;
+  Transform {
+    translation,
+    // The reflection flips the handedness, flip `right` back.
+    rotation: Quat::from_mat3(&Mat3::from_cols(-right, up, back)),
+    scale: transform.scale,
+  }
+}
+
+/// Height of the reflection clip plane of a water body.
+fn clip_height(
+  body: &WaterBody,
+  reflection: &PlanarReflection,
+  fft: Option<&FftOceanState>,
+) -> f32 {
+  let crest = match (&body.wave_model, fft) {
+    (WaveModel::Fft, Some(fft)) => fft.max_displacement().y * body.amplitude.abs(),
+    (model, _) => model.max_displacement(body.amplitude).y,
+  };
+  body.height + crest + reflection.clip_offset
+}
+
+type NewReflection = (
+  With<PlanarReflection>,
+  With<WaterBody>,
+  Without<PlanarReflectionTarget>,
+);
+
+/// Spawn the reflection cameras of new planar reflections.
+pub(crate) fn setup_planar_reflections(
+  mut commands: Commands,
+  reflections: Query<Entity, NewReflection>,
+  cameras: Query<&Camera, With<PlanarReflectionCamera>>,
+  mut images: ResMut<Assets<Image>>,
+) {
+  // Render before the reflected cameras, each reflection camera with its own order.
+  let mut order = cameras.iter().map(|camera| camera.order).min().unwrap_or(0).min(0);
+  for body in reflections.iter() {
+    order -= 1;
+    // Resized to the viewport by `update_planar_reflections`.
+    let mut image = Image::new_fill(
+      Extent3d {
+        width: 1,
+        height: 1,
+        depth_or_array_layers: 1,
+      },
+      TextureDimension::D2,
+      &[0, 0, 0, 255],
+      TextureFormat::Bgra8UnormSrgb,
+      RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage =
+      TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+    let image = images.add(image);
+
+    let camera = commands
+      .spawn((
+        Name::new("Planar Reflection Camera"),
+        PlanarReflectionCamera { body },
+        Camera {
+          order,
+          is_active: false,
+          target: RenderTarget::Image(image.clone()),
+          ..default()
+        },
+        CameraRenderGraph::new(Core3d),
+        Camera3d::default(),
+        PlanarReflectionProjection::default(),
+        Tonemapping::default(),
+        VisibleEntities::default(),
+        Frustum::default(),
+        TransformBundle::default(),
+      ))
+      .id();
+    commands
+      .entity(body)
+      .insert(PlanarReflectionTarget { camera, image });
+  }
+}
+
+type ChangedReflection = Or<(Changed<PlanarReflection>, Added<PlanarReflectionTarget>)>;
+
+/// Give the water materials of the bodies the reflection image.
+pub(crate) fn update_planar_reflection_materials(
+  reflections: Query<(Entity, &PlanarReflection, &PlanarReflectionTarget), ChangedReflection>,
+  children: Query<&Children>,
+  handles: Query<&Handle<StandardWaterMaterial>>,
+  mut materials: ResMut<Assets<StandardWaterMaterial>>,
+) {
+  for (body, reflection, target) in reflections.iter() {
+    for id in body_materials(body, &children, &handles) {
+      if let Some(mat) = materials.get_mut(id) {
+        mat.extension.planar_reflection = Some(target.image.clone());
+        mat.extension.planar_reflection_distortion = reflection.distortion;
+      }
+    }
+  }
+}
+
+/// Despawn the reflection cameras of removed planar reflections.
+pub(crate) fn cleanup_planar_reflections(
+  mut commands: Commands,
+  cameras: Query<(Entity, &PlanarReflectionCamera)>,
+  reflections: Query<(), With<PlanarReflection>>,
+  targets: Query<&PlanarReflectionTarget>,
+  children: Query<&Children>,
+  handles: Query<&Handle<StandardWaterMaterial>>,
+  mut materials: ResMut<Assets<StandardWaterMaterial>>,
+) {
+  for (entity, camera) in cameras.iter() {
+    if reflections.contains(camera.body) {
+      continue;
+    }
+    commands.entity(entity).despawn_recursive();
+    if targets.contains(camera.body) {
+      commands
+        .entity(camera.body)
+        .remove::<PlanarReflectionTarget>();
+      for id in body_materials(camera.body, &children, &handles) {
+        if let Some(mat) = materials.get_mut(id) {
+          mat.extension.planar_reflection = None;
+        }
+      }
+    }
+  }
+}
+
+type ReflectedCamera<'a> = (
+  &'a Camera,
+  &'a GlobalTransform,
+  &'a Projection,
+  Option<&'a Tonemapping>,
+);
+
+type ReflectionCamera<'a> = (
+  &'a mut Camera,
+  &'a mut Transform,
+  &'a mut GlobalTransform,
+  &'a mut PlanarReflectionProjection,
+  &'a mut Tonemapping,
+);
+
+/// Mirror the reflected cameras and size the reflection images to their viewports.
+pub(crate) fn update_planar_reflections(
+  fft: Option<Res<FftOceanState>>,
+  reflections: Query<(&WaterBody, &PlanarReflection, &PlanarReflectionTarget)>,
+  views: Query<ReflectedCamera, Without<PlanarReflectionCamera>>,
+  mut cameras: Query<ReflectionCamera, With<PlanarReflectionCamera>>,
+  mut images: ResMut<Assets<Image>>,
+) {
+  for (body, reflection, target) in reflections.iter() {
+    let Ok((mut camera, mut transform, mut global, mut projection, mut tonemapping)) =
+      cameras.get_mut(target.camera)
+    else {
+      continue;
+    };
+    let view = match reflection.camera {
+      Some(entity) => views.get(entity).ok(),
+      None => views
+        .iter()
+        .find(|(camera, ..)| camera.is_active && matches!(camera.target, RenderTarget::Window(_))),
+    };
+    let clip_height = clip_height(body, reflection, fft.as_deref());
+    let Some((view_camera, view_global, Projection::Perspective(perspective), view_tonemapping)) =
+      view.filter(|(_, global, ..)| global.translation().y > clip_height)
+    else {
+      // No camera, or the camera is below the reflecting side of the water.
+      if camera.is_active {
+        camera.is_active = false;
+      }
+      continue;
+    };
+    let Some(viewport) = view_camera.physical_viewport_size() else {
+      continue;
+    };
+
+    let size = (viewport.as_vec2() * reflection.resolution_scale)
+      .max(Vec2::ONE)
+      .as_uvec2();
+    if images
+      .get(&target.image)
+      .is_some_and(|image| image.size() != size)
+    {
+      if let Some(image) = images.get_mut(&target.image) {
+        image.resize(Extent3d {
+          width: size.x,
+          height: size.y,
+          depth_or_array_layers: 1,
+        });
+      }
+    }
+
+    let mirrored = mirror_transform(&view_global.compute_transform(), body.height);
+    *transform = mirrored;
+    *global = GlobalTransform::from(mirrored);
+    // The world space plane (y up at the clip height) in view space.
+    let world_plane = Vec4::new(0.0, 1.0, 0.0, -clip_height);
+    projection.perspective = perspective.clone();
+    projection.clip_plane = global.compute_matrix().transpose() * world_plane;
+
+    camera.is_active = true;
+    camera.hdr = view_camera.hdr;
+    let view_tonemapping = view_tonemapping.copied().unwrap_or(Tonemapping::None);
+    if *tonemapping != view_tonemapping {
+      *tonemapping = view_tonemapping;
+    }
+  }
+}
+
+/// Keep the water bodies out of their own reflections.
+///
+/// Their materials sample the reflection image, which the camera renders to.
+pub(crate) fn hide_reflecting_bodies(
+  mut cameras: Query<(&PlanarReflectionCamera, &mut VisibleEntities)>,
+  children: Query<&Children>,
+) {
+  for (camera, mut visible) in cameras.iter_mut() {
+    let body: HashSet<Entity> = std::iter::once(camera.body)
+      .chain(children.iter_descendants(camera.body))
+      .collect();
+    visible.entities.retain(|entity| !body.contains(entity));
+  }
+}
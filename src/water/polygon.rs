@@ -0,0 +1,232 @@
+//! Water meshes for polygon shaped lakes and pools.
+//!
+//! The polygon is triangulated (ear clipping) and the triangles are split until no edge is
+//! longer than the grid spacing.  An edge is split by its own length, so neighbouring
+//! triangles share the new vertices and the waves don't open cracks.
+use bevy::{
+  math::Vec3Swizzles,
+  prelude::*,
+  render::{
+    mesh::{Indices, PrimitiveTopology, VertexAttributeValues},
+    render_asset::RenderAssetUsages,
+  },
+  utils::{HashMap, HashSet},
+};
+
+use super::body::{WaterBounds, GRID_SPACING};
+
+/// Most rounds of edge splitting.
+const MAX_SPLIT_ROUNDS: u32 = 16;
+
+/// Signed area of the polygon, positive for counter-clockwise `x`, `y` points.
+pub fn polygon_area(points: &[Vec2]) -> f32 {
+  let mut area = 0.0;
+  let mut j = points.len().wrapping_sub(1);
+  for (i, a) in points.iter().enumerate() {
+    area += points[j].perp_dot(*a);
+    j = i;
+  }
+  area * 0.5
+}
+
+/// Triangulate a simple polygon by ear clipping.
+///
+/// The triangles are counter-clockwise, whatever the winding of the polygon.
+pub fn triangulate_polygon(points: &[Vec2]) -> Vec<[usize; 3]> {
+  let mut remaining: Vec<usize> = (0..points.len()).collect();
+  if polygon_area(points) < 0.0 {
+    remaining.reverse();
+  }
+  let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+  while remaining.len() > 3 {
+    let n = remaining.len();
+    let corner = |i: usize| {
+      (
+        remaining[(i + n - 1) % n],
+        remaining[i],
+        remaining[(i + 1) % n],
+      )
+    };
+    let is_ear = |i: usize| {
+      let (a, b, c) = corner(i);
+      let (pa, pb, pc) = (points[a], points[b], points[c]);
+      if (pb - pa).perp_dot(pc - pa) <= f32::EPSILON {
+        // Reflex or collinear.
+        return false;
+      }
+      !remaining.iter().any(|&other| {
+        other != a && other != b && other != c && triangle_contains(pa, pb, pc, points[other])
+      })
+    };
+    // Clip any convex corner when the polygon isn't simple.
+    let ear = (0..n)
+      .find(|&i| is_ear(i))
+      .or_else(|| {
+        (0..n).find(|&i| {
+          let (a, b, c) = corner(i);
+          (points[b] - points[a]).perp_dot(points[c] - points[a]) > 0.0
+        })
+      })
+      .unwrap_or(0);
+    let (a, b, c) = corner(ear);
+    triangles.push([a, b, c]);
+    remaining.remove(ear);
+  }
+  if let [a, b, c] = remaining[..] {
+    triangles.push([a, b, c]);
+  }
+  triangles
+}
+
+fn triangle_contains(a: Vec2, b: Vec2, c: Vec2, p: Vec2) -> bool {
+  (b - a).perp_dot(p - a) >= 0.0 && (c - b).perp_dot(p - b) >= 0.0 && (a - c).perp_dot(p - c) >= 0.0
+}
+
+/// Split the triangles until no edge is longer than `spacing`.
+fn subdivide(
+  vertices: &mut Vec<Vec2>,
+  mut triangles: Vec<[usize; 3]>,
+  spacing: f32,
+) -> Vec<[usize; 3]> {
+  for _ in 0..MAX_SPLIT_ROUNDS {
+    let mut midpoints: HashMap<(usize, usize), usize> = HashMap::default();
+    let mut split = |vertices: &mut Vec<Vec2>, a: usize, b: usize| -> Option<usize> {
+      if vertices[a].distance(vertices[b]) <= spacing {
+        return None;
+      }
+      let key = (a.min(b), a.max(b));
+      Some(*midpoints.entry(key).or_insert_with(|| {
+        vertices.push((vertices[a] + vertices[b]) * 0.5);
+        vertices.len() - 1
+      }))
+    };
+    let mut next = Vec::with_capacity(triangles.len() * 4);
+    for [a, b, c] in triangles.iter().copied() {
+      let ab = split(vertices, a, b);
+      let bc = split(vertices, b, c);
+      let ca = split(vertices, c, a);
+      match (ab, bc, ca) {
+        (None, None, None) => next.push([a, b, c]),
+        (Some(ab), None, None) => next.extend([[a, ab, c], [ab, b, c]]),
+        (None, Some(bc), None) => next.extend([[a, b, bc], [a, bc, c]]),
+        (None, None, Some(ca)) => next.extend([[a, b, ca], [ca, b, c]]),
+        (Some(ab), Some(bc), None) => next.extend([[a, ab, c], [ab, bc, c], [ab, b, bc]]),
+        (None, Some(bc), Some(ca)) => next.extend([[a, b, ca], [ca, b, bc], [ca, bc, c]]),
+        (Some(ab), None, Some(ca)) => next.extend([[a, ab, ca], [ab, b, ca], [ca, b, c]]),
+        (Some(ab), Some(bc), Some(ca)) => {
+          next.extend([[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]);
+        }
+      }
+    }
+    let done = next.len() == triangles.len();
+    triangles = next;
+    if done {
+      break;
+    }
+  }
+  triangles
+}
+
+/// A flat water mesh filling the polygon (world space `x`, `z`), centred on the centre of
+/// its bounds.
+///
+/// The UVs span the bounds, like `water_grid_mesh`, so a material from
+/// `WaterBody::material` with the bounds keeps the waves continuous in world space.
+pub fn water_polygon_mesh(points: &[Vec2]) -> Mesh {
+  let rect = WaterBounds::Polygon(points.to_vec())
+    .rect()
+    .unwrap_or_default();
+  let mut vertices = points.to_vec();
+  let triangles = subdivide(&mut vertices, triangulate_polygon(points), GRID_SPACING);
+
+  let center = rect.center();
+  let size = rect.size().max(Vec2::splat(f32::EPSILON));
+  let positions: Vec<[f32; 3]> = vertices
+    .iter()
+    .map(|p| [p.x - center.x, 0.0, p.y - center.y])
+    .collect();
+  let uvs: Vec<[f32; 2]> = vertices
+    .iter()
+    .map(|p| ((*p - rect.min) / size).to_array())
+    .collect();
+  let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+  // Counter-clockwise on the XZ plane is clockwise seen from above.
+  let indices = triangles
+    .iter()
+    .flat_map(|[a, b, c]| [*a as u32, *c as u32, *b as u32])
+    .collect();
+
+  Mesh::new(
+    PrimitiveTopology::TriangleList,
+    RenderAssetUsages::default(),
+  )
+  .with_inserted_indices(Indices::U32(indices))
+  .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+  .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+  .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+}
+
+/// The outline (world space `x`, `z`) of an open mesh, like a flat pool surface.
+///
+/// This is the largest loop of the edges used by a single triangle.  `None` for closed
+/// meshes and meshes without triangles.
+pub fn mesh_outline(mesh: &Mesh, transform: &GlobalTransform) -> Option<Vec<Vec2>> {
+  if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+    return None;
+  }
+  let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+  else {
+    return None;
+  };
+  let indices: Vec<usize> = match mesh.indices() {
+    Some(Indices::U16(indices)) => indices.iter().map(|i| *i as usize).collect(),
+    Some(Indices::U32(indices)) => indices.iter().map(|i| *i as usize).collect(),
+    None => (0..positions.len()).collect(),
+  };
+  // Merge the vertices at the same position (split for normals or UVs).
+  let mut merged: HashMap<[u32; 3], usize> = HashMap::default();
+  let vertex: Vec<usize> = positions
+    .iter()
+    .map(|p| {
+      let key = p.map(f32::to_bits);
+      let next = merged.len();
+      *merged.entry(key).or_insert(next)
+    })
+    .collect();
+
+  let mut edges: HashSet<(usize, usize)> = HashSet::default();
+  for triangle in indices.chunks_exact(3) {
+    let [a, b, c] = [0, 1, 2].map(|i| vertex.get(triangle[i]).copied());
+    let (Some(a), Some(b), Some(c)) = (a, b, c) else {
+      continue;
+    };
+    for (from, to) in [(a, b), (b, c), (c, a)] {
+      if from == to {
+        continue;
+      }
+      // An edge shared with a neighbour is walked both ways.
+      if !edges.remove(&(to, from)) {
+        edges.insert((from, to));
+      }
+    }
+  }
+  let mut next: HashMap<usize, usize> = edges.into_iter().collect();
+
+  let mut points = vec![Vec2::ZERO; merged.len()];
+  for (p, v) in positions.iter().zip(&vertex) {
+    points[*v] = transform.transform_point(Vec3::from_array(*p)).xz();
+  }
+  let mut best: Option<Vec<Vec2>> = None;
+  while let Some(&start) = next.keys().next() {
+    let mut outline = Vec::new();
+    let mut current = start;
+    while let Some(to) = next.remove(&current) {
+      outline.push(points[current]);
+      current = to;
+    }
+    if polygon_area(&outline).abs() > best.as_deref().map_or(0.0, |b| polygon_area(b).abs()) {
+      best = Some(outline);
+    }
+  }
+  best
+}
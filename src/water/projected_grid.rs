@@ -0,0 +1,116 @@
+//! Projected grid water surface.
+//!
+//! A single screen space grid that the vertex shader projects onto the water plane for
+//! each camera, which gives the same on-screen vertex density at any altitude.
+use bevy::{
+  pbr::NotShadowCaster,
+  prelude::*,
+  render::{
+    mesh::{Indices, PrimitiveTopology},
+    render_asset::RenderAssetUsages,
+    view::NoFrustumCulling,
+  },
+};
+
+use super::{material::*, WaterBody, WaterSettings};
+
+/// Settings of the projected grid water surface.
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct WaterProjectedGrid {
+  /// Cells across and down the screen.
+  pub resolution: UVec2,
+  /// Extra screen area covered around the screen (fraction of the screen size), so waves
+  /// displaced in from outside the screen aren't cut.
+  pub margin: f32,
+}
+
+impl Default for WaterProjectedGrid {
+  fn default() -> Self {
+    Self {
+      resolution: UVec2::new(256, 256),
+      margin: 0.1,
+    }
+  }
+}
+
+/// Marks the projected grid water surface.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct WaterProjectedGridSurface;
+
+/// A screen space grid for a `WaterMaterial` with `projected_grid` set.
+///
+/// The UVs are the screen position, from (0, 0) at the top left to (1, 1) at the bottom
+/// right, extended by `margin` on each side.
+pub fn projected_grid_mesh(resolution: UVec2, margin: f32) -> Mesh {
+  let cells = resolution.max(UVec2::ONE);
+  let (x_count, y_count) = (cells.x + 1, cells.y + 1);
+
+  let mut positions = Vec::with_capacity((x_count * y_count) as usize);
+  let mut uvs = Vec::with_capacity((x_count * y_count) as usize);
+  for y in 0..y_count {
+    for x in 0..x_count {
+      let uv = Vec2::new(x as f32, y as f32) / cells.as_vec2();
+      let uv = uv * (1.0 + margin * 2.0) - margin;
+      positions.push([uv.x, 0.0, uv.y]);
+      uvs.push(uv.to_array());
+    }
+  }
+  let mut indices = Vec::with_capacity((cells.x * cells.y * 6) as usize);
+  for y in 0..cells.y {
+    for x in 0..cells.x {
+      let quad = y * x_count + x;
+      indices.extend([quad + x_count + 1, quad + 1, quad + x_count]);
+      indices.extend([quad, quad + x_count, quad + 1]);
+    }
+  }
+  let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+
+  Mesh::new(
+    PrimitiveTopology::TriangleList,
+    RenderAssetUsages::default(),
+  )
+  .with_inserted_indices(Indices::U32(indices))
+  .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+  .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+  .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+}
+
+/// Spawn the projected grid surface.
+pub(crate) fn setup_projected_grid(
+  commands: &mut Commands,
+  settings: &WaterSettings,
+  grid: &WaterProjectedGrid,
+  meshes: &mut Assets<Mesh>,
+  materials: &mut Assets<StandardWaterMaterial>,
+) {
+  // The vertex shader places the vertices, the rect isn't used.
+  let mut material = WaterBody::from(settings).material(Rect::from_corners(Vec2::ZERO, Vec2::ONE));
+  material.extension.projected_grid = true;
+  let material = materials.add(material);
+  commands.spawn((
+    Name::new("Water Projected Grid"),
+    WaterProjectedGridSurface,
+    MaterialMeshBundle {
+      mesh: meshes.add(projected_grid_mesh(grid.resolution, grid.margin)),
+      material,
+      transform: Transform::from_xyz(0.0, settings.height, 0.0),
+      ..default()
+    },
+    NotShadowCaster,
+    // The vertices are placed by the vertex shader.
+    NoFrustumCulling,
+  ));
+}
+
+/// Keep the projected grid at the water height.
+pub(crate) fn update_projected_grid(
+  settings: Res<WaterSettings>,
+  mut surfaces: Query<&mut Transform, With<WaterProjectedGridSurface>>,
+) {
+  for mut transform in surfaces.iter_mut() {
+    if transform.translation.y != settings.height {
+      transform.translation.y = settings.height;
+    }
+  }
+}
@@ -0,0 +1,165 @@
+//! Rain and splashes.
+//!
+//! The `WaterRain` resource covers the water with the rings of rain drops, drawn
+//! procedurally by the water shader (`assets/shaders/water_rain.wgsl`).  A `SplashEvent`
+//! spreads rings from a world position, for falling objects.
+//!
+//! Both only change the water normals, they don't change `WaterParam`.
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use super::{data_texture::*, material::StandardWaterMaterial};
+
+/// Most splashes at a time, the oldest are dropped first.
+pub const MAX_WATER_SPLASHES: usize = 32;
+
+/// How long (seconds) the rings of a splash last.
+pub const SPLASH_DURATION: f32 = 3.0;
+
+/// Rain on the water.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Resource, Default, Debug)]
+pub struct WaterRain {
+  /// Share of the surface hit by drops (0.0 = dry, 1.0 = downpour).
+  pub intensity: f32,
+  /// Largest radius (meters) of the rings.
+  pub drop_size: f32,
+  /// Strength of the rings.
+  pub strength: f32,
+}
+
+impl Default for WaterRain {
+  fn default() -> Self {
+    Self {
+      intensity: 0.0,
+      drop_size: 0.3,
+      strength: 1.0,
+    }
+  }
+}
+
+/// Spreads rings on the water from `position`.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct SplashEvent {
+  /// World position, only `x` and `z` are used.
+  pub position: Vec3,
+  /// Radius (meters) of the splash.
+  pub radius: f32,
+  /// Strength of the rings.
+  pub strength: f32,
+}
+
+impl SplashEvent {
+  pub fn new(position: Vec3, radius: f32) -> Self {
+    Self {
+      position,
+      radius,
+      strength: 1.0,
+    }
+  }
+}
+
+/// A splash spreading rings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WaterSplash {
+  /// World position (`x`, `z`).
+  pub position: Vec2,
+  pub radius: f32,
+  pub strength: f32,
+  /// Time (seconds) since the splash.
+  pub age: f32,
+}
+
+/// The active splashes, drawn by the water shader.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct WaterSplashes {
+  pub splashes: Vec<WaterSplash>,
+}
+
+impl WaterDataTexture for WaterSplashes {
+  const SIZE: UVec2 = UVec2::new(MAX_WATER_SPLASHES as u32, 2);
+
+  /// Row 0: xy = position, z = age, w = radius.  Row 1: x = strength, y = duration.
+  fn texels(&self) -> Vec<Vec4> {
+    let mut texels = vec![Vec4::ZERO; MAX_WATER_SPLASHES * 2];
+    for (idx, splash) in self.splashes.iter().take(MAX_WATER_SPLASHES).enumerate() {
+      texels[idx] = splash
+        .position
+        .extend(splash.age)
+        .extend(splash.radius.max(0.01));
+      texels[MAX_WATER_SPLASHES + idx] = Vec4::new(splash.strength, SPLASH_DURATION, 0.0, 0.0);
+    }
+    texels
+  }
+}
+
+/// Age the splashes and add the new ones.
+fn update_splashes(
+  time: Res<Time>,
+  mut events: EventReader<SplashEvent>,
+  mut water_splashes: ResMut<WaterSplashes>,
+) {
+  let delta = time.delta_seconds();
+  let splashes = &mut water_splashes.splashes;
+  for splash in splashes.iter_mut() {
+    splash.age += delta;
+  }
+  splashes.retain(|splash| splash.age < SPLASH_DURATION);
+  for event in events.read() {
+    if splashes.len() >= MAX_WATER_SPLASHES {
+      splashes.remove(0);
+    }
+    splashes.push(WaterSplash {
+      position: event.position.xz(),
+      radius: event.radius,
+      strength: event.strength,
+      age: 0.0,
+    });
+  }
+}
+
+/// Give the water materials the rain and the splashes.
+///
+/// The materials only draw the splashes while there are any.
+fn update_rain_materials(
+  rain: Res<WaterRain>,
+  water_splashes: Res<WaterSplashes>,
+  splashes_image: Res<WaterDataImage<WaterSplashes>>,
+  mut materials: ResMut<Assets<StandardWaterMaterial>>,
+) {
+  let rain = (rain.intensity > 0.0).then_some(*rain);
+  let splashes = (!water_splashes.splashes.is_empty()).then_some(&splashes_image.image);
+  // Only touch the materials that changed, changed materials are prepared again.
+  let outdated: Vec<_> = materials
+    .iter()
+    .filter(|(_, mat)| mat.extension.rain != rain || mat.extension.splashes.as_ref() != splashes)
+    .map(|(id, _)| id)
+    .collect();
+  for id in outdated {
+    if let Some(mat) = materials.get_mut(id) {
+      mat.extension.rain = rain;
+      mat.extension.splashes = splashes.cloned();
+    }
+  }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct WaterRainPlugin;
+
+impl Plugin for WaterRainPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .register_type::<WaterRain>()
+      .init_resource::<WaterRain>()
+      .init_resource::<WaterSplashes>()
+      .add_event::<SplashEvent>()
+      .add_systems(
+        Update,
+        (
+          update_splashes,
+          update_rain_materials.run_if(resource_exists::<WaterDataImage<WaterSplashes>>),
+        )
+          .chain(),
+      )
+      .add_plugins(WaterDataTexturePlugin::<WaterSplashes>::default());
+  }
+}
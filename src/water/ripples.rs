@@ -0,0 +1,601 @@
+//! Interactive ripples.
+//!
+//! A wave equation heightfield is simulated by a compute shader
+//! (`assets/shaders/water_ripples.wgsl`) in a square area that follows the camera, or
+//! the `WaterRipplesFocus` entity.  Moving `WaterDisturber` entities touching the water
+//! push it down, and the water material adds the ripples to the waves.
+//!
+//! The ripples are only drawn, they don't change `WaterParam`.  Not supported on WebGL2.
+use bevy::{
+  asset::load_internal_asset,
+  math::Vec3Swizzles,
+  prelude::*,
+  render::{
+    camera::RenderTarget,
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    texture::{ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor},
+  },
+  utils::HashMap,
+};
+
+use super::{
+  data_texture::*,
+  material::{texture_rect, StandardWaterMaterial},
+  WaterSettings,
+};
+use crate::WaterParam;
+
+pub const WATER_RIPPLES_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x2d7e94a1c6b05f38);
+
+/// Most disturbances added per frame.
+pub const MAX_RIPPLE_DISTURBANCES: usize = 32;
+
+/// Settings of the ripple simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug)]
+pub struct WaterRipples {
+  /// Resolution of the simulation grid, a multiple of 8.
+  pub size: u32,
+  /// World size (meters) of the simulated area.
+  pub extent: f32,
+  /// Speed of the ripples (m/s), limited by the grid resolution and the frame rate.
+  pub speed: f32,
+  /// How fast the ripples fade (per second).
+  pub damping: f32,
+  /// Largest ripple height (meters) up or down, the simulation is clamped to it.
+  pub max_height: f32,
+}
+
+impl Default for WaterRipples {
+  fn default() -> Self {
+    Self {
+      size: 256,
+      extent: 64.0,
+      speed: 3.0,
+      damping: 1.0,
+      max_height: 0.5,
+    }
+  }
+}
+
+impl WaterRipples {
+  /// Size (meters) of a simulation texel.
+  pub fn texel_size(&self) -> f32 {
+    self.extent / self.size.max(1) as f32
+  }
+}
+
+/// Marks the entity the ripple simulation follows.
+///
+/// Without it the simulation follows the first active 3d camera rendering to a window.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct WaterRipplesFocus;
+
+/// Makes ripples where the entity moves through the water surface.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct WaterDisturber {
+  /// Radius (meters) of the disturbed water.
+  pub radius: f32,
+  /// Water pushed down (meters) per meter moved.
+  pub strength: f32,
+}
+
+impl Default for WaterDisturber {
+  fn default() -> Self {
+    Self {
+      radius: 0.5,
+      strength: 0.05,
+    }
+  }
+}
+
+/// The simulation textures: x = height, y = previous height, zw = slope (dh/dx, dh/dz).
+///
+/// Each frame steps `current` into `scratch` and back.
+#[derive(Resource, Clone, Debug)]
+pub struct WaterRipplesImages {
+  pub current: Handle<Image>,
+  pub scratch: Handle<Image>,
+  /// Resolution of the images.
+  ///
+  /// The images only live in the render world, they aren't in the main world `Assets`.
+  pub size: u32,
+}
+
+/// The simulated area and the disturbances of the frame.
+///
+/// Empty (`rect` of zero size) until there is a focus.  The `rect` is written to a data
+/// texture for the water materials.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct WaterRipplesFrame {
+  /// Grid position of the first texel.
+  pub origin: IVec2,
+  /// Movement of the grid since the last frame (texels).
+  pub shift: IVec2,
+  /// World space area (`x`, `z`) covered by the simulation.
+  pub rect: Rect,
+  /// Time step of the frame (seconds).
+  pub delta_time: f32,
+  /// Disturbances: xy = texel position, z = radius (texels), w = height (meters).
+  pub disturbances: Vec<Vec4>,
+}
+
+impl WaterDataTexture for WaterRipplesFrame {
+  const SIZE: UVec2 = UVec2::ONE;
+
+  /// xy = world offset, zw = world to texture scale of the `rect`.
+  fn texels(&self) -> Vec<Vec4> {
+    if self.rect.is_empty() {
+      return vec![Vec4::ZERO];
+    }
+    vec![texture_rect(self.rect)]
+  }
+}
+
+fn ripple_image(size: u32) -> Image {
+  let mut image = Image::new_fill(
+    Extent3d {
+      width: size,
+      height: size,
+      depth_or_array_layers: 1,
+    },
+    TextureDimension::D2,
+    &[0; 8],
+    TextureFormat::Rgba16Float,
+    RenderAssetUsages::RENDER_WORLD,
+  );
+  #[cfg(not(feature = "webgl2"))]
+  {
+    image.texture_descriptor.usage |= TextureUsages::STORAGE_BINDING;
+  }
+  image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+    address_mode_u: ImageAddressMode::ClampToEdge,
+    address_mode_v: ImageAddressMode::ClampToEdge,
+    mag_filter: ImageFilterMode::Linear,
+    min_filter: ImageFilterMode::Linear,
+    ..default()
+  });
+  image
+}
+
+/// Resize the simulation textures when the settings change.
+fn update_ripple_images(
+  settings: Res<WaterSettings>,
+  mut ripple_images: ResMut<WaterRipplesImages>,
+  mut images: ResMut<Assets<Image>>,
+) {
+  let Some(ripples) = &settings.ripples else {
+    return;
+  };
+  let size = ripples.size.max(8);
+  if ripple_images.size == size {
+    return;
+  }
+  ripple_images.size = size;
+  images.insert(ripple_images.current.clone(), ripple_image(size));
+  images.insert(ripple_images.scratch.clone(), ripple_image(size));
+}
+
+type Disturber<'a> = (Entity, &'a WaterDisturber, &'a GlobalTransform);
+
+/// Move the simulated area with the focus, and collect the disturbances.
+fn update_ripples(
+  time: Res<Time>,
+  water: WaterParam,
+  mut frame: ResMut<WaterRipplesFrame>,
+  focus: Query<&GlobalTransform, With<WaterRipplesFocus>>,
+  cameras: Query<(&GlobalTransform, &Camera), With<Camera3d>>,
+  disturbers: Query<Disturber>,
+  mut last_positions: Local<HashMap<Entity, Vec3>>,
+) {
+  let Some(ripples) = water.settings.ripples else {
+    return;
+  };
+  let focus = focus.iter().next().or_else(|| {
+    cameras
+      .iter()
+      .find(|(_, camera)| camera.is_active && matches!(camera.target, RenderTarget::Window(_)))
+      .map(|(transform, _)| transform)
+  });
+  let Some(focus) = focus.map(|transform| transform.translation().xz()) else {
+    return;
+  };
+
+  let size = ripples.size.max(8);
+  let texel = ripples.texel_size();
+  let origin = (focus / texel).round().as_ivec2() - IVec2::splat(size as i32 / 2);
+  let min = origin.as_vec2() * texel;
+  let rect = Rect::from_corners(min, min + Vec2::splat(ripples.extent));
+  let delta_time = time.delta_seconds();
+
+  let mut disturbances = Vec::new();
+  let mut positions = HashMap::with_capacity(last_positions.len());
+  for (entity, disturber, transform) in disturbers.iter() {
+    let position = transform.translation();
+    positions.insert(entity, position);
+    let Some(last) = last_positions.get(&entity) else {
+      continue;
+    };
+    let moved = position.distance(*last);
+    let touching = water
+      .try_wave_height(position)
+      .is_some_and(|height| (position.y - height).abs() <= disturber.radius);
+    if moved <= 0.0 || !touching || !rect.contains(position.xz()) {
+      continue;
+    }
+    if disturbances.len() < MAX_RIPPLE_DISTURBANCES {
+      disturbances.push(
+        ((position.xz() - min) / texel)
+          .extend(disturber.radius / texel)
+          .extend(-disturber.strength * moved),
+      );
+    }
+  }
+  *last_positions = positions;
+
+  let shift = origin - frame.origin;
+  *frame = WaterRipplesFrame {
+    origin,
+    shift,
+    rect,
+    delta_time,
+    disturbances,
+  };
+}
+
+/// Give the water materials the ripples.
+///
+/// The materials only change when the ripples start or stop, the moving area is written
+/// to the `WaterDataImage<WaterRipplesFrame>`.
+fn update_ripple_materials(
+  settings: Res<WaterSettings>,
+  frame: Res<WaterRipplesFrame>,
+  ripple_images: Res<WaterRipplesImages>,
+  area_image: Res<WaterDataImage<WaterRipplesFrame>>,
+  mut materials: ResMut<Assets<StandardWaterMaterial>>,
+) {
+  let image = match settings.ripples {
+    Some(_) if !frame.rect.is_empty() => Some(&ripple_images.current),
+    _ => None,
+  };
+  // Only touch the materials that changed, changed materials are prepared again.
+  let outdated: Vec<_> = materials
+    .iter()
+    .filter(|(_, mat)| mat.extension.ripples.as_ref() != image)
+    .map(|(id, _)| id)
+    .collect();
+  for id in outdated {
+    if let Some(mat) = materials.get_mut(id) {
+      mat.extension.ripples = image.cloned();
+      mat.extension.ripples_area = image.map(|_| area_image.image.clone());
+    }
+  }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct WaterRipplesPlugin;
+
+impl Plugin for WaterRipplesPlugin {
+  fn build(&self, app: &mut App) {
+    load_internal_asset!(
+      app,
+      WATER_RIPPLES_SHADER_HANDLE,
+      concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/shaders/water_ripples.wgsl"
+      ),
+      Shader::from_wgsl
+    );
+
+    app
+      .register_type::<WaterRipples>()
+      .register_type::<WaterRipplesFocus>()
+      .register_type::<WaterDisturber>()
+      .init_resource::<WaterRipplesFrame>();
+
+    // The simulation needs compute shaders.
+    #[cfg(not(feature = "webgl2"))]
+    {
+      app.add_plugins(WaterDataTexturePlugin::<WaterRipplesFrame>::default());
+      app.add_systems(
+        Update,
+        (
+          update_ripple_images,
+          update_ripples,
+          update_ripple_materials,
+        )
+          .chain(),
+      );
+      compute::build(app);
+    }
+  }
+
+  fn finish(&self, app: &mut App) {
+    let size = app
+      .world
+      .resource::<WaterSettings>()
+      .ripples
+      .map_or(8, |ripples| ripples.size.max(8));
+    let mut images = app.world.resource_mut::<Assets<Image>>();
+    let ripple_images = WaterRipplesImages {
+      current: images.add(ripple_image(size)),
+      scratch: images.add(ripple_image(size)),
+      size,
+    };
+    app.insert_resource(ripple_images);
+
+    #[cfg(not(feature = "webgl2"))]
+    compute::finish(app);
+  }
+}
+
+/// GPU simulation (needs compute shaders).
+#[cfg(not(feature = "webgl2"))]
+mod compute {
+  use std::borrow::Cow;
+
+  use bevy::{
+    prelude::*,
+    render::{
+      extract_resource::{ExtractResource, ExtractResourcePlugin},
+      render_asset::RenderAssets,
+      render_graph::{self, RenderGraph, RenderLabel},
+      render_resource::{binding_types::*, *},
+      renderer::{RenderContext, RenderDevice, RenderQueue},
+      Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+  };
+
+  use super::{
+    WaterRipplesFrame, WaterRipplesImages, MAX_RIPPLE_DISTURBANCES, WATER_RIPPLES_SHADER_HANDLE,
+  };
+  use crate::water::WaterSettings;
+
+  const WORKGROUP_SIZE: u32 = 8;
+  /// Simulation steps per frame, alternating between the two textures.
+  const STEPS: usize = 2;
+  /// Largest `speed * step time / texel size` that stays stable.
+  const MAX_COURANT: f32 = 0.5;
+
+  impl ExtractResource for WaterRipplesImages {
+    type Source = WaterRipplesImages;
+
+    fn extract_resource(source: &Self::Source) -> Self {
+      source.clone()
+    }
+  }
+
+  #[derive(Clone, Default, ShaderType)]
+  struct RipplesUniform {
+    size: u32,
+    count: u32,
+    shift: IVec2,
+    /// Squared courant number.
+    courant2: f32,
+    /// Height kept per step.
+    damping: f32,
+    texel_size: f32,
+    max_height: f32,
+    disturbances: [Vec4; MAX_RIPPLE_DISTURBANCES],
+  }
+
+  #[derive(Resource)]
+  struct ExtractedRipples {
+    size: u32,
+    /// One per step, the first step shifts the grid and adds the disturbances.
+    steps: [RipplesUniform; STEPS],
+  }
+
+  #[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+  struct WaterRipplesLabel;
+
+  pub(super) fn build(app: &mut App) {
+    app.add_plugins(ExtractResourcePlugin::<WaterRipplesImages>::default());
+    let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+      return;
+    };
+    render_app
+      .add_systems(ExtractSchedule, extract_ripples)
+      .add_systems(
+        Render,
+        prepare_ripples_bind_groups.in_set(RenderSet::PrepareBindGroups),
+      );
+
+    let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+    render_graph.add_node(WaterRipplesLabel, WaterRipplesNode::default());
+    render_graph.add_node_edge(WaterRipplesLabel, bevy::render::graph::CameraDriverLabel);
+  }
+
+  pub(super) fn finish(app: &mut App) {
+    let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+      return;
+    };
+    render_app
+      .init_resource::<WaterRipplesPipeline>()
+      .init_resource::<WaterRipplesBindGroups>();
+  }
+
+  fn extract_ripples(
+    mut commands: Commands,
+    settings: Extract<Res<WaterSettings>>,
+    frame: Extract<Res<WaterRipplesFrame>>,
+  ) {
+    let (Some(ripples), false) = (settings.ripples, frame.rect.is_empty()) else {
+      commands.remove_resource::<ExtractedRipples>();
+      return;
+    };
+    let size = ripples.size.max(8);
+    let step_time = frame.delta_time / STEPS as f32;
+    let courant = (ripples.speed * step_time / ripples.texel_size()).min(MAX_COURANT);
+    let step = RipplesUniform {
+      size,
+      count: 0,
+      shift: IVec2::ZERO,
+      courant2: courant * courant,
+      damping: (-ripples.damping * step_time).exp(),
+      texel_size: ripples.texel_size(),
+      max_height: ripples.max_height.abs(),
+      disturbances: [Vec4::ZERO; MAX_RIPPLE_DISTURBANCES],
+    };
+    let mut first = step.clone();
+    first.shift = frame.shift;
+    first.count = frame.disturbances.len().min(MAX_RIPPLE_DISTURBANCES) as u32;
+    for (dst, src) in first.disturbances.iter_mut().zip(&frame.disturbances) {
+      *dst = *src;
+    }
+    commands.insert_resource(ExtractedRipples {
+      size,
+      steps: [first, step],
+    });
+  }
+
+  #[derive(Resource)]
+  struct WaterRipplesPipeline {
+    layout: BindGroupLayout,
+    step: CachedComputePipelineId,
+  }
+
+  impl FromWorld for WaterRipplesPipeline {
+    fn from_world(world: &mut World) -> Self {
+      let render_device = world.resource::<RenderDevice>();
+      let layout = render_device.create_bind_group_layout(
+        "water_ripples_layout",
+        &BindGroupLayoutEntries::sequential(
+          ShaderStages::COMPUTE,
+          (
+            uniform_buffer::<RipplesUniform>(false),
+            texture_2d(TextureSampleType::Float { filterable: false }),
+            texture_storage_2d(TextureFormat::Rgba16Float, StorageTextureAccess::WriteOnly),
+          ),
+        ),
+      );
+      let step =
+        world
+          .resource::<PipelineCache>()
+          .queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("water_ripples_step".into()),
+            layout: vec![layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader: WATER_RIPPLES_SHADER_HANDLE,
+            shader_defs: vec![],
+            entry_point: Cow::from("step"),
+          });
+      Self { layout, step }
+    }
+  }
+
+  #[derive(Resource, Default)]
+  struct WaterRipplesBindGroups {
+    size: u32,
+    uniforms: Vec<UniformBuffer<RipplesUniform>>,
+    /// One bind group per step.
+    steps: Vec<BindGroup>,
+  }
+
+  fn prepare_ripples_bind_groups(
+    pipeline: Res<WaterRipplesPipeline>,
+    extracted: Option<Res<ExtractedRipples>>,
+    ripple_images: Option<Res<WaterRipplesImages>>,
+    gpu_images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    bind_groups: ResMut<WaterRipplesBindGroups>,
+  ) {
+    let bind_groups = bind_groups.into_inner();
+    bind_groups.steps.clear();
+    let (Some(extracted), Some(ripple_images)) = (extracted, ripple_images) else {
+      return;
+    };
+    let (Some(current), Some(scratch)) = (
+      gpu_images.get(&ripple_images.current),
+      gpu_images.get(&ripple_images.scratch),
+    ) else {
+      return;
+    };
+    // Wait for the resized images.
+    let size = extracted.size;
+    if current.size.x as u32 != size || scratch.size.x as u32 != size {
+      return;
+    }
+
+    bind_groups.size = size;
+    bind_groups
+      .uniforms
+      .resize_with(STEPS, UniformBuffer::default);
+    let textures = [current, scratch];
+    for (idx, (uniform, step)) in bind_groups
+      .uniforms
+      .iter_mut()
+      .zip(&extracted.steps)
+      .enumerate()
+    {
+      uniform.set(step.clone());
+      uniform.write_buffer(&render_device, &render_queue);
+      let Some(binding) = uniform.binding() else {
+        bind_groups.steps.clear();
+        return;
+      };
+      // Step from one texture into the other.
+      let (src, dst) = (textures[idx % 2], textures[(idx + 1) % 2]);
+      bind_groups.steps.push(render_device.create_bind_group(
+        "water_ripples_bind_group",
+        &pipeline.layout,
+        &BindGroupEntries::sequential((binding, &src.texture_view, &dst.texture_view)),
+      ));
+    }
+  }
+
+  #[derive(Default)]
+  struct WaterRipplesNode {
+    ready: bool,
+  }
+
+  impl render_graph::Node for WaterRipplesNode {
+    fn update(&mut self, world: &mut World) {
+      if self.ready {
+        return;
+      }
+      let pipeline = world.resource::<WaterRipplesPipeline>();
+      let pipeline_cache = world.resource::<PipelineCache>();
+      self.ready = pipeline_cache.get_compute_pipeline(pipeline.step).is_some();
+    }
+
+    fn run(
+      &self,
+      _graph: &mut render_graph::RenderGraphContext,
+      render_context: &mut RenderContext,
+      world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+      if !self.ready {
+        return Ok(());
+      }
+      let bind_groups = world.resource::<WaterRipplesBindGroups>();
+      if bind_groups.steps.len() != STEPS {
+        return Ok(());
+      }
+      let pipeline = world.resource::<WaterRipplesPipeline>();
+      let pipeline_cache = world.resource::<PipelineCache>();
+      let Some(step) = pipeline_cache.get_compute_pipeline(pipeline.step) else {
+        return Ok(());
+      };
+
+      let groups = bind_groups.size.div_ceil(WORKGROUP_SIZE);
+      let mut pass = render_context
+        .command_encoder()
+        .begin_compute_pass(&ComputePassDescriptor {
+          label: Some("water_ripples"),
+          ..default()
+        });
+      pass.set_pipeline(step);
+      for bind_group in &bind_groups.steps {
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.dispatch_workgroups(groups, groups, 1);
+      }
+
+      Ok(())
+    }
+  }
+}
@@ -0,0 +1,439 @@
+//! Rivers along a spline.
+//!
+//! A `RiverSpline` on a `WaterBody` entity generates a water mesh following a smooth
+//! curve through its control points.  The surface follows the height of the points and
+//! banks in the bends, and a `WaterFlowMap` and a `WaterDepthMap` are baked along the
+//! channel.  Everything is generated again when the spline changes.
+use bevy::{
+  pbr::NotShadowCaster,
+  prelude::*,
+  render::{
+    mesh::{Indices, PrimitiveTopology},
+    render_asset::RenderAssetUsages,
+  },
+};
+
+use super::{
+  body::{WaterBody, WaterBounds},
+  depth_map::{depth_image, WaterDepthMap},
+  flow_map::WaterFlowMap,
+  material::StandardWaterMaterial,
+};
+
+const GRAVITY: f32 = 9.81;
+
+/// Steepest banking of the surface across the river.
+const MAX_BANK: f32 = 0.2;
+
+/// A control point of a `RiverSpline`.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct RiverPoint {
+  /// World space position of the centre line, `y` is the height of the surface.
+  pub position: Vec3,
+  /// Width (meters) of the river.
+  pub width: f32,
+  /// Depth (meters) of the river at the centre line.
+  pub depth: f32,
+}
+
+impl RiverPoint {
+  pub fn new(position: Vec3, width: f32, depth: f32) -> Self {
+    Self {
+      position,
+      width,
+      depth,
+    }
+  }
+}
+
+/// A river flowing through the control `points`, in order.
+///
+/// Spawn it with a `WaterBody` (see `RiverBundle`), its mesh, `StandardWaterMaterial`,
+/// `bounds`, `flow_map` and `depth_map` are generated.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct RiverSpline {
+  pub points: Vec<RiverPoint>,
+  /// Flow speed (m/s) at the centre line, slowing down towards the banks.
+  pub speed: f32,
+  /// Length (meters) of the mesh segments along the river.
+  pub segment_length: f32,
+  /// Number of mesh segments across the river.
+  pub cross_segments: u32,
+  /// Scale of the surface banking in the bends, 0.0 = flat.
+  ///
+  /// 1.0 rises the outer bank like water flowing at `speed` around the bend.
+  pub banking: f32,
+  /// Size (meters) of the flow and depth map texels.
+  pub texel_size: f32,
+}
+
+impl Default for RiverSpline {
+  fn default() -> Self {
+    Self {
+      points: Vec::new(),
+      speed: 1.5,
+      segment_length: 2.0,
+      cross_segments: 4,
+      banking: 1.0,
+      texel_size: 1.0,
+    }
+  }
+}
+
+/// A cross-section of a river.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct RiverSection {
+  /// Centre of the surface.
+  pub centre: Vec3,
+  /// Direction (`x`, `z`) of the flow.
+  pub direction: Vec2,
+  pub width: f32,
+  pub depth: f32,
+  /// Rise (meters per meter) of the surface towards the right bank.
+  pub bank: f32,
+  /// Distance (meters) along the centre line from the first point.
+  pub distance: f32,
+}
+
+impl RiverSection {
+  /// Direction (`x`, `z`) towards the right bank.
+  pub fn right(&self) -> Vec2 {
+    self.direction.perp()
+  }
+
+  fn lerp(&self, other: &Self, t: f32) -> Self {
+    Self {
+      centre: self.centre.lerp(other.centre, t),
+      direction: self
+        .direction
+        .lerp(other.direction, t)
+        .try_normalize()
+        .unwrap_or(self.direction),
+      width: self.width.lerp(other.width, t),
+      depth: self.depth.lerp(other.depth, t),
+      bank: self.bank.lerp(other.bank, t),
+      distance: self.distance.lerp(other.distance, t),
+    }
+  }
+}
+
+/// A river at a world position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RiverSample {
+  /// The cross-section through the position.
+  pub section: RiverSection,
+  /// Distance (meters) from the centre line, positive towards the right bank.
+  pub across: f32,
+}
+
+impl RiverSample {
+  /// Height of the undisturbed surface.
+  pub fn height(&self) -> f32 {
+    self.section.centre.y + self.section.bank * self.across
+  }
+
+  /// Position across the river, -1.0 at the left bank to 1.0 at the right bank.
+  pub fn across_fraction(&self) -> f32 {
+    self.across / (self.section.width * 0.5).max(f32::EPSILON)
+  }
+
+  /// Depth (meters) below the surface, rounded towards the banks.
+  pub fn depth(&self) -> f32 {
+    self.section.depth * (1.0 - self.across_fraction().powi(2)).max(0.0)
+  }
+
+  /// Flow velocity (m/s, `x`, `z`) of a river flowing at `speed`.
+  pub fn velocity(&self, speed: f32) -> Vec2 {
+    self.section.direction * speed * (1.0 - self.across_fraction().powi(2)).max(0.0)
+  }
+}
+
+/// The generated surface of a `RiverSpline`, used by `WaterParam`.
+#[derive(Component, Clone, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct RiverSurface {
+  /// Cross-sections every `segment_length` along the river.
+  pub sections: Vec<RiverSection>,
+}
+
+impl RiverSpline {
+  /// The cross-sections of the river, along a Catmull-Rom spline through the points.
+  pub fn surface(&self) -> RiverSurface {
+    let points = &self.points;
+    if points.len() < 2 {
+      return RiverSurface::default();
+    }
+    // Extrapolate the ends, so the spline passes through all the points.
+    let n = points.len();
+    let control = |i: isize| -> Vec3 {
+      match i {
+        -1 => points[0].position * 2.0 - points[1].position,
+        i if i as usize >= n => points[n - 1].position * 2.0 - points[n - 2].position,
+        i => points[i as usize].position,
+      }
+    };
+
+    let mut sections: Vec<RiverSection> = Vec::new();
+    let mut distance = 0.0;
+    for i in 0..n - 1 {
+      let (a, b) = (&points[i], &points[i + 1]);
+      let p = [
+        control(i as isize - 1),
+        control(i as isize),
+        control(i as isize + 1),
+        control(i as isize + 2),
+      ];
+      let steps = (a.position.distance(b.position) / self.segment_length.max(0.01))
+        .ceil()
+        .max(1.0) as u32;
+      let last = i == n - 2;
+      for step in 0..steps + u32::from(last) {
+        let t = step as f32 / steps as f32;
+        let (centre, velocity) = catmull_rom(p, t);
+        if let Some(previous) = sections.last() {
+          distance += previous.centre.distance(centre);
+        }
+        let direction = Vec2::new(velocity.x, velocity.z)
+          .try_normalize()
+          .or_else(|| sections.last().map(|s| s.direction))
+          .unwrap_or(Vec2::X);
+        sections.push(RiverSection {
+          centre,
+          direction,
+          width: a.width.lerp(b.width, t).max(0.0),
+          depth: a.depth.lerp(b.depth, t).max(0.0),
+          bank: 0.0,
+          distance,
+        });
+      }
+    }
+
+    // Bank the surface in the bends, the outer bank rises.
+    let curvatures: Vec<f32> = (0..sections.len())
+      .map(|i| {
+        let prev = &sections[i.saturating_sub(1)];
+        let next = &sections[(i + 1).min(sections.len() - 1)];
+        let length = next.distance - prev.distance;
+        if length <= f32::EPSILON {
+          return 0.0;
+        }
+        // Positive when turning towards the right bank.
+        (next.direction - prev.direction).dot(sections[i].right()) / length
+      })
+      .collect();
+    let lift = self.banking * self.speed * self.speed / GRAVITY;
+    for (section, curvature) in sections.iter_mut().zip(curvatures) {
+      section.bank = (-lift * curvature).clamp(-MAX_BANK, MAX_BANK);
+    }
+    RiverSurface { sections }
+  }
+}
+
+/// Position and derivative of a uniform Catmull-Rom segment from `p[1]` to `p[2]`.
+fn catmull_rom(p: [Vec3; 4], t: f32) -> (Vec3, Vec3) {
+  let c1 = p[2] - p[0];
+  let c2 = p[0] * 2.0 - p[1] * 5.0 + p[2] * 4.0 - p[3];
+  let c3 = p[1] * 3.0 - p[0] - p[2] * 3.0 + p[3];
+  let position = 0.5 * (p[1] * 2.0 + c1 * t + c2 * t * t + c3 * t * t * t);
+  let velocity = 0.5 * (c1 + c2 * 2.0 * t + c3 * 3.0 * t * t);
+  (position, velocity)
+}
+
+impl RiverSurface {
+  /// The river at the world position `p` (`x`, `z`), `None` beyond the banks.
+  pub fn sample(&self, p: Vec2) -> Option<RiverSample> {
+    let mut nearest: Option<(f32, RiverSection)> = None;
+    for pair in self.sections.windows(2) {
+      let (a, b) = (&pair[0], &pair[1]);
+      let (a2, b2) = (
+        Vec2::new(a.centre.x, a.centre.z),
+        Vec2::new(b.centre.x, b.centre.z),
+      );
+      let segment = b2 - a2;
+      let t = if segment.length_squared() > f32::EPSILON {
+        ((p - a2).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+      } else {
+        0.0
+      };
+      let distance = p.distance_squared(a2 + segment * t);
+      if !nearest.is_some_and(|(d, _)| d <= distance) {
+        nearest = Some((distance, a.lerp(b, t)));
+      }
+    }
+    let (_, section) = nearest?;
+    let across = (p - Vec2::new(section.centre.x, section.centre.z)).dot(section.right());
+    (across.abs() <= section.width * 0.5).then_some(RiverSample { section, across })
+  }
+
+  /// The banks of the river, the left bank downstream then the right bank upstream.
+  pub fn outline(&self) -> Vec<Vec2> {
+    let bank = |section: &RiverSection, side: f32| {
+      Vec2::new(section.centre.x, section.centre.z) + section.right() * (side * section.width * 0.5)
+    };
+    let left = self.sections.iter().map(|section| bank(section, -1.0));
+    let right = self.sections.iter().rev().map(|section| bank(section, 1.0));
+    left.chain(right).collect()
+  }
+
+  /// World space area (`x`, `z`) covered by the river.
+  pub fn rect(&self) -> Rect {
+    WaterBounds::Polygon(self.outline())
+      .rect()
+      .unwrap_or_default()
+  }
+
+  /// A water mesh with `cross_segments` quads across the river, in world space.
+  ///
+  /// The UVs span the `rect` of the river, like the meshes of `WaterBodyBundle::rect`.
+  pub fn mesh(&self, cross_segments: u32) -> Mesh {
+    let cross_segments = cross_segments.max(1);
+    let columns = cross_segments + 1;
+    let rect = self.rect();
+    let size = rect.size().max(Vec2::splat(f32::EPSILON));
+
+    let mut positions = Vec::with_capacity(self.sections.len() * columns as usize);
+    let mut normals = Vec::with_capacity(positions.capacity());
+    let mut uvs = Vec::with_capacity(positions.capacity());
+    for (i, section) in self.sections.iter().enumerate() {
+      // Slope along the river.
+      let prev = &self.sections[i.saturating_sub(1)];
+      let next = &self.sections[(i + 1).min(self.sections.len() - 1)];
+      let length = next.distance - prev.distance;
+      let slope = if length > f32::EPSILON {
+        (next.centre.y - prev.centre.y) / length
+      } else {
+        0.0
+      };
+      let (right, direction) = (section.right(), section.direction);
+      let normal = Vec3::new(
+        -right.x * section.bank - direction.x * slope,
+        1.0,
+        -right.y * section.bank - direction.y * slope,
+      )
+      .normalize();
+      for column in 0..columns {
+        let across = (column as f32 / cross_segments as f32 - 0.5) * section.width;
+        let p = Vec2::new(section.centre.x, section.centre.z) + right * across;
+        let height = section.centre.y + section.bank * across;
+        positions.push([p.x, height, p.y]);
+        normals.push(normal.to_array());
+        uvs.push(((p - rect.min) / size).to_array());
+      }
+    }
+    let rows = self.sections.len().saturating_sub(1) as u32;
+    let mut indices = Vec::with_capacity((rows * cross_segments * 6) as usize);
+    for row in 0..rows {
+      for column in 0..cross_segments {
+        let quad = row * columns + column;
+        indices.extend([quad, quad + 1, quad + columns]);
+        indices.extend([quad + 1, quad + columns + 1, quad + columns]);
+      }
+    }
+
+    Mesh::new(
+      PrimitiveTopology::TriangleList,
+      RenderAssetUsages::default(),
+    )
+    .with_inserted_indices(Indices::U32(indices))
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+  }
+}
+
+#[derive(Bundle)]
+pub struct RiverBundle {
+  pub name: Name,
+  pub river: RiverSpline,
+  pub body: WaterBody,
+  pub spatial: SpatialBundle,
+  pub not_shadow_caster: NotShadowCaster,
+}
+
+impl RiverBundle {
+  pub fn new(body: WaterBody, river: RiverSpline) -> Self {
+    Self {
+      name: Name::new("River"),
+      river,
+      body,
+      spatial: SpatialBundle::default(),
+      not_shadow_caster: NotShadowCaster,
+    }
+  }
+}
+
+type River<'a> = (
+  Entity,
+  &'a RiverSpline,
+  &'a mut WaterBody,
+  Option<&'a Handle<StandardWaterMaterial>>,
+);
+
+/// Generate the meshes, materials and maps of the rivers that changed.
+pub(crate) fn update_rivers(
+  mut commands: Commands,
+  mut rivers: Query<River, Changed<RiverSpline>>,
+  mut meshes: ResMut<Assets<Mesh>>,
+  mut materials: ResMut<Assets<StandardWaterMaterial>>,
+  mut images: ResMut<Assets<Image>>,
+) {
+  for (entity, river, mut body, material) in rivers.iter_mut() {
+    let surface = river.surface();
+    if surface.sections.len() < 2 {
+      warn!("A RiverSpline needs at least 2 points.");
+      continue;
+    }
+    let rect = surface.rect();
+    let size = (rect.size() / river.texel_size.max(1e-3))
+      .ceil()
+      .max(Vec2::ONE)
+      .as_uvec2();
+
+    body.height = surface
+      .sections
+      .iter()
+      .map(|section| section.centre.y)
+      .fold(f32::INFINITY, f32::min);
+    body.bounds = WaterBounds::Polygon(surface.outline());
+    body.flow_map = Some(WaterFlowMap::from_fn(
+      rect,
+      size,
+      river.speed.max(f32::EPSILON),
+      |p| {
+        surface
+          .sample(p)
+          .map_or(Vec2::ZERO, |sample| sample.velocity(river.speed))
+      },
+      &mut images,
+    ));
+    let max_depth = river
+      .points
+      .iter()
+      .map(|point| point.depth)
+      .fold(0.0, f32::max)
+      .max(0.1);
+    let depth = depth_image(rect, size, max_depth, |p| {
+      surface.sample(p).map_or(0.0, |sample| sample.depth())
+    });
+    body.depth_map = Some(WaterDepthMap::new(images.add(depth), rect, max_depth));
+
+    // A new mesh, so its bounds are computed again.
+    commands
+      .entity(entity)
+      .insert(meshes.add(surface.mesh(river.cross_segments)));
+    // Keep the waves continuous in world space.
+    match material.and_then(|material| materials.get_mut(material)) {
+      Some(material) => {
+        material.extension.coord_offset = rect.min;
+        material.extension.coord_scale = rect.size();
+      }
+      None => {
+        commands
+          .entity(entity)
+          .insert(materials.add(body.material(rect)));
+      }
+    }
+    commands.entity(entity).insert(surface);
+  }
+}
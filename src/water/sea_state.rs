@@ -0,0 +1,261 @@
+//! Wind driven sea state.
+//!
+//! Insert a `SeaState` resource to drive the wave amplitude, wavelength and direction of
+//! `WaterSettings` (and the wind of the `FftOcean`).  Changes are cross-faded in over
+//! `SeaState::transition_time`, so `WaterParam::wave_height` follows the water material.
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use super::{
+  fft::{FftOcean, OceanSpectrum},
+  WaterSettings,
+};
+use crate::wave::{GerstnerWave, NoiseFade, WaveModel};
+
+const GRAVITY: f32 = 9.81;
+
+/// Wavelength (meters) of the noise waves at `NoiseWaves::scale = 1.0`.
+const NOISE_WAVELENGTH: f32 = 60.0;
+
+/// Keep the sum of the Gerstner steepness below this to avoid loops at the crests.
+const MAX_STEEPNESS: f32 = 0.8;
+
+/// Mean wind speed (m/s) of each Beaufort force.
+const BEAUFORT_WIND_SPEED: [f32; 13] = [
+  0.2, 1.5, 3.3, 5.4, 7.9, 10.7, 13.8, 17.1, 20.7, 24.4, 28.4, 32.6, 36.0,
+];
+
+/// Gerstner layers generated for a sea state: (angle from the wind, wavelength, height) relative
+/// to the dominant waves.
+const GERSTNER_LAYERS: [(f32, f32, f32); 4] = [
+  (0.0, 1.0, 0.6),
+  (0.45, 0.62, 0.3),
+  (-0.55, 0.4, 0.2),
+  (0.9, 0.25, 0.1),
+];
+
+/// Wind and sea conditions.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct SeaState {
+  /// Direction the wind blows towards on the XZ plane.
+  pub wind_direction: Vec2,
+  /// Wind speed (m/s) at 10 meters above the sea.
+  pub wind_speed: f32,
+  /// Distance (meters) over which the wind has blown over open water.
+  pub fetch: f32,
+  /// Time (seconds) it takes to cross-fade to a new sea state.
+  ///
+  /// A change during a transition starts once the running transition is done.
+  pub transition_time: f32,
+}
+
+impl Default for SeaState {
+  fn default() -> Self {
+    Self::beaufort(5)
+  }
+}
+
+impl SeaState {
+  /// Open ocean sea state for a Beaufort force (0 - 12) with the wind blowing along +X.
+  pub fn beaufort(force: u8) -> Self {
+    Self {
+      wind_direction: Vec2::X,
+      wind_speed: BEAUFORT_WIND_SPEED[force.min(12) as usize],
+      fetch: 100_000.0,
+      transition_time: 5.0,
+    }
+  }
+
+  /// Beaufort force of the wind speed.
+  pub fn beaufort_force(&self) -> u8 {
+    BEAUFORT_WIND_SPEED
+      .windows(2)
+      .take_while(|speeds| self.wind_speed >= (speeds[0] + speeds[1]) / 2.0)
+      .count() as u8
+  }
+
+  /// Significant wave height (meters).
+  ///
+  /// Fetch limited (JONSWAP) up to a fully developed sea (Pierson-Moskowitz).
+  pub fn significant_wave_height(&self) -> f32 {
+    let u = self.wind_speed.max(0.0);
+    let fetch_limited = 0.0016 * u * (self.fetch.max(0.0) / GRAVITY).sqrt();
+    let fully_developed = 0.21 * u * u / GRAVITY;
+    fetch_limited.min(fully_developed)
+  }
+
+  /// Wavelength (meters) of the dominant waves.
+  pub fn peak_wavelength(&self) -> f32 {
+    let u = self.wind_speed.max(0.1);
+    let fetch = self.fetch.max(1.0);
+    // Peak frequencies (Hz).
+    let fetch_limited = 3.5 * GRAVITY / u * (GRAVITY * fetch / (u * u)).powf(-0.33);
+    let fully_developed = 0.877 * GRAVITY / u / TAU;
+    let frequency = fetch_limited.max(fully_developed);
+    GRAVITY / (TAU * frequency * frequency)
+  }
+
+  fn wind_direction(&self) -> Vec2 {
+    self.wind_direction.try_normalize().unwrap_or(Vec2::X)
+  }
+
+  /// Gerstner layers for this sea state: the dominant waves along the wind and shorter
+  /// waves spread around it.
+  pub fn gerstner_waves(&self) -> Vec<GerstnerWave> {
+    let wavelength = self.peak_wavelength();
+    let height = self.significant_wave_height() / 2.0;
+    let wind = self.wind_direction();
+    let mut waves: Vec<_> = GERSTNER_LAYERS
+      .iter()
+      .map(|&(angle, length, amplitude)| {
+        let wavelength = wavelength * length;
+        let steepness = TAU / wavelength * height * amplitude;
+        GerstnerWave::new(Vec2::from_angle(angle).rotate(wind), wavelength, steepness)
+      })
+      .collect();
+    let total: f32 = waves.iter().map(|wave| wave.steepness).sum();
+    if total > MAX_STEEPNESS {
+      for wave in &mut waves {
+        wave.steepness *= MAX_STEEPNESS / total;
+      }
+    }
+    waves
+  }
+
+  fn noise_scale(&self) -> f32 {
+    (self.peak_wavelength() / NOISE_WAVELENGTH).clamp(0.1, 10.0)
+  }
+
+  /// Update the amplitude and wave model of `settings` for this sea state.
+  pub fn update_settings(&self, settings: &mut WaterSettings) {
+    self.update_settings_from(self, 1.0, settings);
+  }
+
+  /// Update `settings` for the transition from the sea state `from` to this one,
+  /// `t` = 0.0 (`from`) ..= 1.0 (this one).
+  ///
+  /// The waves of both sea states are cross-faded.  Blending their wavelengths and
+  /// directions instead would slide the waves.
+  pub fn update_settings_from(&self, from: &SeaState, t: f32, settings: &mut WaterSettings) {
+    let t = if from == self { 1.0 } else { t.clamp(0.0, 1.0) };
+    match &mut settings.wave_model {
+      WaveModel::Noise(noise) => {
+        let height = self.significant_wave_height();
+        let from_height = from.significant_wave_height();
+        settings.amplitude = (from_height + (height - from_height) * t) / 2.0;
+        noise.direction = self.wind_direction();
+        noise.scale = self.noise_scale();
+        noise.fade = (t < 1.0).then(|| NoiseFade {
+          direction: from.wind_direction(),
+          scale: from.noise_scale(),
+          weight: 1.0 - t,
+        });
+      }
+      WaveModel::Gerstner(waves) => {
+        // The layers have physical heights.
+        settings.amplitude = 1.0;
+        let faded = |sea: &SeaState, weight: f32| {
+          sea.gerstner_waves().into_iter().map(move |mut wave| {
+            wave.steepness *= weight;
+            wave
+          })
+        };
+        *waves = faded(self, t).collect();
+        if t < 1.0 {
+          waves.extend(faded(from, 1.0 - t));
+        }
+      }
+      WaveModel::Fft => {
+        // The spectrum has physical heights.
+        settings.amplitude = 1.0;
+      }
+    }
+  }
+
+  /// Update the wind of the FFT ocean for this sea state.
+  pub fn update_fft_ocean(&self, ocean: &mut FftOcean) {
+    ocean.wind_speed = self.wind_speed;
+    ocean.wind_direction = self.wind_direction();
+    if let OceanSpectrum::Jonswap { fetch, .. } = &mut ocean.spectrum {
+      *fetch = self.fetch;
+    }
+  }
+
+  /// The sea state the fraction `t` of the way from this one to `target`.
+  fn lerp(&self, target: &Self, t: f32) -> Self {
+    let from = self.wind_direction();
+    let angle = from.angle_between(target.wind_direction());
+    Self {
+      wind_direction: Vec2::from_angle(angle * t).rotate(from),
+      wind_speed: self.wind_speed + (target.wind_speed - self.wind_speed) * t,
+      fetch: self.fetch + (target.fetch - self.fetch) * t,
+      transition_time: target.transition_time,
+    }
+  }
+}
+
+/// The transition of the applied sea state.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SeaStateTransition {
+  from: SeaState,
+  to: SeaState,
+  /// 0.0 = `from` ..= 1.0 = `to`.
+  progress: f32,
+}
+
+/// Cross-fade the applied sea state to the `SeaState` resource.
+pub(crate) fn update_sea_state(
+  time: Res<Time>,
+  target: Res<SeaState>,
+  mut transition: Local<Option<SeaStateTransition>>,
+  mut settings: ResMut<WaterSettings>,
+  ocean: Option<ResMut<FftOcean>>,
+) {
+  let first = transition.is_none();
+  let transition = transition.get_or_insert(SeaStateTransition {
+    from: *target,
+    to: *target,
+    progress: 1.0,
+  });
+  // Only two sea states are cross-faded, the next transition waits for the running one.
+  if transition.progress >= 1.0 && transition.to != *target {
+    *transition = SeaStateTransition {
+      from: transition.to,
+      to: *target,
+      progress: 0.0,
+    };
+  }
+  if transition.progress < 1.0 {
+    let duration = transition.to.transition_time;
+    transition.progress = if duration > 0.0 {
+      (transition.progress + time.delta_seconds() / duration).min(1.0)
+    } else {
+      1.0
+    };
+  } else if !first && !settings.is_changed() {
+    return;
+  }
+
+  let SeaStateTransition { from, to, progress } = *transition;
+  to.update_settings_from(&from, progress, &mut settings);
+  if let (WaveModel::Fft, Some(mut ocean)) = (&settings.wave_model, ocean) {
+    // The FFT waves don't slide when the wind changes, the spectrum is blended.
+    let mut wind = ocean.clone();
+    from.lerp(&to, progress).update_fft_ocean(&mut wind);
+    ocean.set_if_neq(wind);
+  }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct SeaStatePlugin;
+
+impl Plugin for SeaStatePlugin {
+  fn build(&self, app: &mut App) {
+    app.register_type::<SeaState>().add_systems(
+      Update,
+      update_sea_state.run_if(resource_exists::<SeaState>),
+    );
+  }
+}
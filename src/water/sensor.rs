@@ -0,0 +1,82 @@
+//! Events when entities enter and leave the water.
+//!
+//! A `WaterSensor` point is tested with `WaterParam::is_underwater` every frame, so audio,
+//! swimming and post effects agree on when something is under water.
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::WaterParam;
+
+/// Send `EnteredWater` and `ExitedWater` events for the entity.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component, Default)]
+pub struct WaterSensor {
+  /// Local position of the tested point, like the eyes of a swimmer.
+  pub offset: Vec3,
+  /// Is the point under the water, updated by `WaterSensorPlugin`.
+  ///
+  /// Sensors spawned under the water send an `EnteredWater` event in their first frame.
+  pub submerged: bool,
+}
+
+impl WaterSensor {
+  pub fn new(offset: Vec3) -> Self {
+    Self {
+      offset,
+      submerged: false,
+    }
+  }
+}
+
+/// A `WaterSensor` went below the wave surface.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct EnteredWater {
+  pub entity: Entity,
+  /// World position of the sensor point.
+  pub position: Vec3,
+}
+
+/// A `WaterSensor` came out of the water.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct ExitedWater {
+  pub entity: Entity,
+  /// World position of the sensor point.
+  pub position: Vec3,
+}
+
+/// Test the sensors against the waves.
+fn update_water_sensors(
+  water: WaterParam,
+  mut sensors: Query<(Entity, &GlobalTransform, &mut WaterSensor)>,
+  mut entered: EventWriter<EnteredWater>,
+  mut exited: EventWriter<ExitedWater>,
+) {
+  for (entity, transform, mut sensor) in sensors.iter_mut() {
+    let position = transform.transform_point(sensor.offset);
+    let submerged = water.is_underwater(position);
+    if submerged == sensor.submerged {
+      continue;
+    }
+    sensor.submerged = submerged;
+    if submerged {
+      entered.send(EnteredWater { entity, position });
+    } else {
+      exited.send(ExitedWater { entity, position });
+    }
+  }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct WaterSensorPlugin;
+
+impl Plugin for WaterSensorPlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .register_type::<WaterSensor>()
+      .add_event::<EnteredWater>()
+      .add_event::<ExitedWater>()
+      .add_systems(
+        PostUpdate,
+        update_water_sensors.after(TransformSystem::TransformPropagate),
+      );
+  }
+}
@@ -0,0 +1,684 @@
+//! Underwater camera post-processing.
+//!
+//! Cameras with `UnderwaterPostProcess` get an `UnderwaterView` each frame, with the wave
+//! surface at the camera from `WaterParam` (the CPU port of the water shader's waves).
+//! While the camera's near plane is under that surface, a fullscreen pass on the linear
+//! scene colors (before the bloom and the tonemapping) adds depth fog with per channel
+//! (wavelength) absorption, a color tint and a refraction wobble.  When the near plane
+//! straddles the surface, only the part below the waterline is affected.
+//!
+//! `UnderwaterGodRays` adds light shafts from the caustics of the waves.
+//!
+//! The fog needs a `DepthPrepass` on the camera for the distance to the scene, otherwise
+//! `fallback_distance` is used everywhere.
+use bevy::{
+  asset::load_internal_asset, prelude::*, render::extract_component::ExtractComponent,
+  transform::TransformSystem,
+};
+
+use crate::WaterParam;
+
+pub const UNDERWATER_POST_SHADER_HANDLE: Handle<Shader> =
+  Handle::weak_from_u128(0x93b5e07c2a4d16f8);
+
+/// Add the underwater post-processing to a camera.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component, Default)]
+pub struct UnderwaterPostProcess {
+  /// Absorption (1/m) of the red, green and blue light, red fades out first.
+  pub absorption: Vec3,
+  /// Color of the light scattered by the water near the surface.
+  pub fog_color: Color,
+  /// Color multiplied with the scene.
+  pub tint: Color,
+  /// Size (fraction of the screen) of the refraction wobble.
+  pub wobble: f32,
+  /// Distance (meters) to the scene for the fog without a `DepthPrepass`.
+  pub fallback_distance: f32,
+}
+
+impl Default for UnderwaterPostProcess {
+  fn default() -> Self {
+    Self {
+      absorption: Vec3::new(0.45, 0.07, 0.04),
+      fog_color: Color::rgb(0.02, 0.2, 0.3),
+      tint: Color::rgb(0.8, 0.95, 1.0),
+      wobble: 0.003,
+      fallback_distance: 10.0,
+    }
+  }
+}
+
+/// Light shafts for an `UnderwaterPostProcess` camera.
+///
+/// The view rays are marched through the water, and each step looks up how much light
+/// the waves above focus on it in a caustics texture.  That texture is rendered by a
+/// `CausticsWaterMaterial` from the wave normals (see the `plane_caustics` example), so
+/// the `CausticsPlugin` is needed.
+#[derive(Component, Clone, Debug, Reflect, ExtractComponent)]
+#[reflect(Component, Default)]
+pub struct UnderwaterGodRays {
+  /// Render target of the caustics camera, in a filterable format (like `Rgba16Float`).
+  pub caustics_texture: Handle<Image>,
+  /// From world space to the caustics texture (`x`, `z`), like
+  /// `UnderwaterExtension::water_world_to_uv`.
+  pub water_world_to_uv: Mat4,
+  /// Direction towards the light.
+  pub light_dir: Vec3,
+  pub color: Color,
+  pub intensity: f32,
+  /// Ray-marching steps per pixel.
+  pub steps: u32,
+  /// Longest distance (meters) marched under the water.
+  pub max_distance: f32,
+}
+
+impl Default for UnderwaterGodRays {
+  fn default() -> Self {
+    Self {
+      caustics_texture: Handle::default(),
+      water_world_to_uv: Mat4::IDENTITY,
+      light_dir: Vec3::new(0.65, 0.69, 0.3),
+      color: Color::rgb(1.0, 0.95, 0.8),
+      intensity: 0.02,
+      steps: 24,
+      max_distance: 30.0,
+    }
+  }
+}
+
+/// The water surface at an `UnderwaterPostProcess` camera, updated every frame.
+///
+/// Removed while there is no water at the camera.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct UnderwaterView {
+  /// Plane of the wave surface at the camera: normal (`xyz`) and `w` = -normal·point.
+  pub surface: Vec4,
+  /// Depth (meters) of the camera below the surface, see `WaterParam::depth_below_surface`.
+  pub depth: f32,
+  /// Depth of the deepest corner of the camera's near plane below the surface.
+  pub near_plane_depth: f32,
+}
+
+impl UnderwaterView {
+  /// Is the camera under the water, like `WaterParam::is_underwater`.
+  pub fn is_underwater(&self) -> bool {
+    self.depth > 0.0
+  }
+
+  /// Does the near plane reach under the water, so the post-processing shows.
+  pub fn is_near_plane_underwater(&self) -> bool {
+    self.near_plane_depth > 0.0
+  }
+}
+
+/// Update the `UnderwaterView` of the cameras.
+pub(crate) fn update_underwater_views(
+  mut commands: Commands,
+  water: WaterParam,
+  mut cameras: Query<
+    (
+      Entity,
+      &Camera,
+      &GlobalTransform,
+      Option<&mut UnderwaterView>,
+    ),
+    With<UnderwaterPostProcess>,
+  >,
+) {
+  for (entity, camera, transform, view) in cameras.iter_mut() {
+    let position = transform.translation();
+    let Some(depth) = water.depth_below_surface(position) else {
+      // No water here.
+      if view.is_some() {
+        commands.entity(entity).remove::<UnderwaterView>();
+      }
+      continue;
+    };
+    let normal = water.wave_normal(position);
+    let surface = normal.extend(-normal.dot(position + Vec3::Y * depth));
+    // The near plane is at 1.0 (reversed z).
+    let near_plane_depth = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+      .into_iter()
+      .filter_map(|(x, y)| camera.ndc_to_world(transform, Vec3::new(x, y, 1.0)))
+      .filter_map(|corner| water.depth_below_surface(corner))
+      .fold(depth, f32::max);
+    let new_view = UnderwaterView {
+      surface,
+      depth,
+      near_plane_depth,
+    };
+    match view {
+      Some(mut view) => {
+        if *view != new_view {
+          *view = new_view;
+        }
+      }
+      None => {
+        commands.entity(entity).insert(new_view);
+      }
+    }
+  }
+}
+
+pub struct UnderwaterPostProcessPlugin;
+
+impl Plugin for UnderwaterPostProcessPlugin {
+  fn build(&self, app: &mut App) {
+    load_internal_asset!(
+      app,
+      UNDERWATER_POST_SHADER_HANDLE,
+      concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/shaders/underwater_post.wgsl"
+      ),
+      Shader::from_wgsl
+    );
+
+    app
+      .register_type::<UnderwaterPostProcess>()
+      .register_type::<UnderwaterView>()
+      .register_type::<UnderwaterGodRays>()
+      .add_systems(
+        PostUpdate,
+        update_underwater_views.after(TransformSystem::TransformPropagate),
+      );
+
+    render::build(app);
+  }
+
+  fn finish(&self, app: &mut App) {
+    render::finish(app);
+  }
+}
+
+/// The fullscreen pass.
+mod render {
+  use bevy::{
+    core_pipeline::{
+      core_3d::graph::{Core3d, Node3d},
+      fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+      prepass::ViewPrepassTextures,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+      extract_component::{
+        ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin,
+        UniformComponentPlugin,
+      },
+      globals::{GlobalsBuffer, GlobalsUniform},
+      render_asset::RenderAssets,
+      render_graph::{
+        NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+      },
+      render_resource::{binding_types::*, *},
+      renderer::{RenderContext, RenderDevice},
+      view::ViewTarget,
+      Render, RenderApp, RenderSet,
+    },
+  };
+
+  use super::{
+    UnderwaterGodRays, UnderwaterPostProcess, UnderwaterView, UNDERWATER_POST_SHADER_HANDLE,
+  };
+
+  /// Settings of the pass for a submerged camera.
+  #[derive(Component, Clone, Default, ShaderType)]
+  pub struct UnderwaterUniform {
+    inverse_view_proj: Mat4,
+    camera_position: Vec3,
+    camera_depth: f32,
+    surface: Vec4,
+    absorption: Vec3,
+    wobble: f32,
+    fog_color: Vec4,
+    tint: Vec4,
+    fallback_distance: f32,
+    // God rays.
+    god_ray_steps: u32,
+    god_ray_distance: f32,
+    god_ray_intensity: f32,
+    god_ray_color: Vec4,
+    light_dir: Vec3,
+    water_world_to_uv: Mat4,
+  }
+
+  impl ExtractComponent for UnderwaterPostProcess {
+    type QueryData = (
+      &'static UnderwaterPostProcess,
+      &'static UnderwaterView,
+      Option<&'static UnderwaterGodRays>,
+      &'static Camera,
+      &'static GlobalTransform,
+    );
+    type QueryFilter = ();
+    type Out = UnderwaterUniform;
+
+    fn extract_component(
+      (settings, view, god_rays, camera, transform): QueryItem<'_, Self::QueryData>,
+    ) -> Option<Self::Out> {
+      if !view.is_near_plane_underwater() || !camera.is_active {
+        return None;
+      }
+      let mut uniform = UnderwaterUniform {
+        inverse_view_proj: transform.compute_matrix() * camera.projection_matrix().inverse(),
+        camera_position: transform.translation(),
+        camera_depth: view.depth,
+        surface: view.surface,
+        absorption: settings.absorption,
+        wobble: settings.wobble,
+        fog_color: settings.fog_color.rgba_linear_to_vec4(),
+        tint: settings.tint.rgba_linear_to_vec4(),
+        fallback_distance: settings.fallback_distance,
+        ..default()
+      };
+      if let Some(god_rays) = god_rays {
+        uniform.god_ray_steps = god_rays.steps.max(1);
+        uniform.god_ray_distance = god_rays.max_distance;
+        uniform.god_ray_intensity = god_rays.intensity;
+        uniform.god_ray_color = god_rays.color.rgba_linear_to_vec4();
+        uniform.light_dir = god_rays.light_dir.normalize_or_zero();
+        uniform.water_world_to_uv = god_rays.water_world_to_uv;
+      }
+      Some(uniform)
+    }
+  }
+
+  #[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+  struct UnderwaterPostProcessLabel;
+
+  pub(super) fn build(app: &mut App) {
+    app.add_plugins((
+      ExtractComponentPlugin::<UnderwaterPostProcess>::default(),
+      ExtractComponentPlugin::<UnderwaterGodRays>::default(),
+      UniformComponentPlugin::<UnderwaterUniform>::default(),
+    ));
+    let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+      return;
+    };
+    render_app
+      .init_resource::<SpecializedRenderPipelines<UnderwaterPipeline>>()
+      .add_systems(
+        Render,
+        prepare_underwater_pipelines.in_set(RenderSet::Prepare),
+      )
+      .add_render_graph_node::<ViewNodeRunner<UnderwaterNode>>(Core3d, UnderwaterPostProcessLabel)
+      // On the linear (HDR) scene, before the bloom and the tonemapping.
+      .add_render_graph_edges(
+        Core3d,
+        (
+          Node3d::EndMainPass,
+          UnderwaterPostProcessLabel,
+          Node3d::Bloom,
+          Node3d::Tonemapping,
+        ),
+      );
+  }
+
+  pub(super) fn finish(app: &mut App) {
+    let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+      return;
+    };
+    render_app.init_resource::<UnderwaterPipeline>();
+  }
+
+  /// Binding of the prepass depth.
+  const DEPTH_BINDING: u32 = 4;
+  /// Bindings of the caustics texture and sampler.
+  const CAUSTICS_BINDING: u32 = 5;
+
+  /// Where the distance to the scene comes from.
+  #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+  enum SceneDepth {
+    Fallback,
+    Prepass,
+    PrepassMultisampled,
+  }
+
+  impl SceneDepth {
+    const ALL: [Self; 3] = [Self::Fallback, Self::Prepass, Self::PrepassMultisampled];
+
+    fn of(prepass: Option<&ViewPrepassTextures>) -> Self {
+      match prepass.and_then(|prepass| prepass.depth.as_ref()) {
+        None => Self::Fallback,
+        Some(depth) if depth.texture.texture.sample_count() > 1 => Self::PrepassMultisampled,
+        Some(_) => Self::Prepass,
+      }
+    }
+
+    fn binding(self) -> Option<BindGroupLayoutEntryBuilder> {
+      match self {
+        Self::Fallback => None,
+        Self::Prepass => Some(texture_depth_2d()),
+        Self::PrepassMultisampled => Some(texture_2d_multisampled(TextureSampleType::Depth)),
+      }
+    }
+  }
+
+  /// Bindings of the pass, see `underwater_post.wgsl`.
+  fn layout_entries(depth: SceneDepth, god_rays: bool) -> Vec<BindGroupLayoutEntry> {
+    let mut entries = BindGroupLayoutEntries::sequential(
+      ShaderStages::FRAGMENT,
+      (
+        texture_2d(TextureSampleType::Float { filterable: true }),
+        sampler(SamplerBindingType::Filtering),
+        uniform_buffer::<UnderwaterUniform>(true),
+        uniform_buffer::<GlobalsUniform>(false),
+      ),
+    )
+    .to_vec();
+    if let Some(depth) = depth.binding() {
+      entries.push(depth.build(DEPTH_BINDING, ShaderStages::FRAGMENT));
+    }
+    if god_rays {
+      entries.extend([
+        texture_2d(TextureSampleType::Float { filterable: true })
+          .build(CAUSTICS_BINDING, ShaderStages::FRAGMENT),
+        sampler(SamplerBindingType::Filtering).build(CAUSTICS_BINDING + 1, ShaderStages::FRAGMENT),
+      ]);
+    }
+    entries
+  }
+
+  #[derive(Resource)]
+  struct UnderwaterPipeline {
+    /// One layout per `SceneDepth`, without and with the god rays.
+    layouts: [[BindGroupLayout; 2]; 3],
+    /// Linear sampler of the scene and the caustics.
+    sampler: Sampler,
+  }
+
+  impl FromWorld for UnderwaterPipeline {
+    fn from_world(world: &mut World) -> Self {
+      let render_device = world.resource::<RenderDevice>();
+      let layout = |depth, god_rays| {
+        render_device
+          .create_bind_group_layout("underwater_post_layout", &layout_entries(depth, god_rays))
+      };
+      let layouts = SceneDepth::ALL.map(|depth| [layout(depth, false), layout(depth, true)]);
+      let sampler = render_device.create_sampler(&SamplerDescriptor {
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        ..default()
+      });
+      Self { layouts, sampler }
+    }
+  }
+
+  impl UnderwaterPipeline {
+    fn layout(&self, key: UnderwaterPipelineKey) -> &BindGroupLayout {
+      &self.layouts[key.depth as usize][key.god_rays as usize]
+    }
+  }
+
+  #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+  struct UnderwaterPipelineKey {
+    texture_format: TextureFormat,
+    depth: SceneDepth,
+    god_rays: bool,
+  }
+
+  impl UnderwaterPipelineKey {
+    /// Without god rays until the caustics texture `is_ready`.
+    fn new(
+      texture_format: TextureFormat,
+      depth: SceneDepth,
+      god_rays: Option<&UnderwaterGodRays>,
+      is_ready: impl Fn(&Handle<Image>) -> bool,
+    ) -> Self {
+      Self {
+        texture_format,
+        depth,
+        god_rays: god_rays.is_some_and(|god_rays| is_ready(&god_rays.caustics_texture)),
+      }
+    }
+  }
+
+  impl SpecializedRenderPipeline for UnderwaterPipeline {
+    type Key = UnderwaterPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+      let mut shader_defs = match key.depth {
+        SceneDepth::Fallback => vec![],
+        SceneDepth::Prepass => vec!["DEPTH_PREPASS".into()],
+        SceneDepth::PrepassMultisampled => vec!["DEPTH_PREPASS".into(), "MULTISAMPLED".into()],
+      };
+      if key.god_rays {
+        shader_defs.push("GOD_RAYS".into());
+      }
+      RenderPipelineDescriptor {
+        label: Some("underwater_post".into()),
+        layout: vec![self.layout(key).clone()],
+        vertex: fullscreen_shader_vertex_state(),
+        fragment: Some(FragmentState {
+          shader: UNDERWATER_POST_SHADER_HANDLE,
+          shader_defs,
+          entry_point: "fragment".into(),
+          targets: vec![Some(ColorTargetState {
+            format: key.texture_format,
+            blend: None,
+            write_mask: ColorWrites::ALL,
+          })],
+        }),
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        push_constant_ranges: Vec::new(),
+      }
+    }
+  }
+
+  #[derive(Component)]
+  struct UnderwaterPipelineId {
+    id: CachedRenderPipelineId,
+    key: UnderwaterPipelineKey,
+  }
+
+  #[allow(clippy::type_complexity)]
+  fn prepare_underwater_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<UnderwaterPipeline>>,
+    pipeline: Res<UnderwaterPipeline>,
+    images: Res<RenderAssets<Image>>,
+    views: Query<
+      (
+        Entity,
+        &ViewTarget,
+        Option<&ViewPrepassTextures>,
+        Option<&UnderwaterGodRays>,
+      ),
+      With<UnderwaterUniform>,
+    >,
+  ) {
+    for (entity, target, prepass, god_rays) in views.iter() {
+      let key = UnderwaterPipelineKey::new(
+        target.main_texture_format(),
+        SceneDepth::of(prepass),
+        god_rays,
+        |image| images.get(image).is_some(),
+      );
+      let id = pipelines.specialize(&pipeline_cache, &pipeline, key);
+      commands
+        .entity(entity)
+        .insert(UnderwaterPipelineId { id, key });
+    }
+  }
+
+  #[derive(Default)]
+  struct UnderwaterNode;
+
+  impl ViewNode for UnderwaterNode {
+    type ViewQuery = (
+      &'static ViewTarget,
+      &'static UnderwaterPipelineId,
+      &'static DynamicUniformIndex<UnderwaterUniform>,
+      Option<&'static ViewPrepassTextures>,
+      Option<&'static UnderwaterGodRays>,
+    );
+
+    fn run(
+      &self,
+      _graph: &mut RenderGraphContext,
+      render_context: &mut RenderContext,
+      (target, pipeline_id, uniform_index, prepass, god_rays): QueryItem<Self::ViewQuery>,
+      world: &World,
+    ) -> Result<(), NodeRunError> {
+      let pipeline_cache = world.resource::<PipelineCache>();
+      let underwater_pipeline = world.resource::<UnderwaterPipeline>();
+      let Some(pipeline) = pipeline_cache.get_render_pipeline(pipeline_id.id) else {
+        return Ok(());
+      };
+      let (Some(uniforms), Some(globals)) = (
+        world
+          .resource::<ComponentUniforms<UnderwaterUniform>>()
+          .binding(),
+        world.resource::<GlobalsBuffer>().buffer.binding(),
+      ) else {
+        return Ok(());
+      };
+      let caustics = match god_rays {
+        Some(god_rays) if pipeline_id.key.god_rays => {
+          let images = world.resource::<RenderAssets<Image>>();
+          let Some(image) = images.get(&god_rays.caustics_texture) else {
+            return Ok(());
+          };
+          Some(image)
+        }
+        _ => None,
+      };
+      let depth_view = match pipeline_id.key.depth {
+        SceneDepth::Fallback => None,
+        _ => {
+          let Some(depth_view) = prepass.and_then(|prepass| prepass.depth_view()) else {
+            return Ok(());
+          };
+          Some(depth_view)
+        }
+      };
+
+      let post_process = target.post_process_write();
+      let mut entries = vec![
+        BindGroupEntry {
+          binding: 0,
+          resource: post_process.source.into_binding(),
+        },
+        BindGroupEntry {
+          binding: 1,
+          resource: underwater_pipeline.sampler.into_binding(),
+        },
+        BindGroupEntry {
+          binding: 2,
+          resource: uniforms,
+        },
+        BindGroupEntry {
+          binding: 3,
+          resource: globals,
+        },
+      ];
+      if let Some(depth_view) = depth_view {
+        entries.push(BindGroupEntry {
+          binding: DEPTH_BINDING,
+          resource: depth_view.into_binding(),
+        });
+      }
+      if let Some(image) = caustics {
+        entries.extend([
+          BindGroupEntry {
+            binding: CAUSTICS_BINDING,
+            resource: image.texture_view.into_binding(),
+          },
+          BindGroupEntry {
+            binding: CAUSTICS_BINDING + 1,
+            resource: underwater_pipeline.sampler.into_binding(),
+          },
+        ]);
+      }
+      let bind_group = render_context.render_device().create_bind_group(
+        "underwater_post_bind_group",
+        underwater_pipeline.layout(pipeline_id.key),
+        &entries,
+      );
+
+      let mut render_pass =
+        render_context
+          .command_encoder()
+          .begin_render_pass(&RenderPassDescriptor {
+            label: Some("underwater_post_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+              view: post_process.destination,
+              resolve_target: None,
+              ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+          });
+      render_pass.set_pipeline(pipeline);
+      render_pass.set_bind_group(0, &bind_group, &[uniform_index.index()]);
+      render_pass.draw(0..3, 0..1);
+
+      Ok(())
+    }
+  }
+
+  #[cfg(test)]
+  mod tests {
+    use super::*;
+
+    fn binding(entries: &[BindGroupLayoutEntry], binding: u32) -> Option<BindingType> {
+      entries
+        .iter()
+        .find(|entry| entry.binding == binding)
+        .map(|entry| entry.ty)
+    }
+
+    #[test]
+    fn god_rays_wait_for_the_caustics_texture() {
+      let god_rays = UnderwaterGodRays::default();
+      let key = |god_rays, ready: bool| {
+        UnderwaterPipelineKey::new(
+          TextureFormat::Rgba16Float,
+          SceneDepth::Prepass,
+          god_rays,
+          |_| ready,
+        )
+        .god_rays
+      };
+      assert!(!key(None, true));
+      assert!(!key(Some(&god_rays), false));
+      assert!(key(Some(&god_rays), true));
+    }
+
+    #[test]
+    fn layouts_match_the_key() {
+      for depth in SceneDepth::ALL {
+        let entries = layout_entries(depth, false);
+        assert_eq!(binding(&entries, CAUSTICS_BINDING), None);
+        match binding(&entries, DEPTH_BINDING) {
+          None => assert_eq!(depth, SceneDepth::Fallback),
+          Some(BindingType::Texture { multisampled, .. }) => {
+            assert_eq!(multisampled, depth == SceneDepth::PrepassMultisampled)
+          }
+          Some(ty) => panic!("depth bound as {ty:?}"),
+        }
+
+        // The caustics are filtered between their texels.
+        let entries = layout_entries(depth, true);
+        assert!(matches!(
+          binding(&entries, CAUSTICS_BINDING),
+          Some(BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: true },
+            ..
+          })
+        ));
+        assert_eq!(
+          binding(&entries, CAUSTICS_BINDING + 1),
+          Some(BindingType::Sampler(SamplerBindingType::Filtering))
+        );
+      }
+    }
+  }
+}
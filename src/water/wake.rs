@@ -0,0 +1,186 @@
+//! Ship wakes.
+//!
+//! Entities with a `Wake` leave a trail of points behind them as they move.  The water
+//! shader (`assets/shaders/water_wake.wgsl`) draws a V-shaped Kelvin wake and a foam
+//! trail along them, fading with the age of the points.
+//!
+//! The trails are written to a small data texture every frame, so the water materials
+//! don't change while the entities move.  Works on WebGL2.
+use std::collections::VecDeque;
+
+use bevy::{math::Vec3Swizzles, prelude::*, utils::HashMap};
+
+use super::{data_texture::*, material::StandardWaterMaterial};
+
+/// Most trail points of all of the wakes.
+pub const MAX_WAKE_POINTS: usize = 256;
+
+/// Most points of a trail.
+pub const MAX_WAKE_TRAIL_POINTS: usize = 32;
+
+/// Leaves a Kelvin wake and a foam trail on the water behind the entity.
+///
+/// A trail has up to `MAX_WAKE_TRAIL_POINTS` points, and all of the trails share
+/// `MAX_WAKE_POINTS`: with more wakes the last trails are cut short, or left out (with a
+/// warning) once there is no room for them.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct Wake {
+  /// Half the width (meters) of the hull at the water line.
+  pub half_width: f32,
+  /// Opacity of the foam trail.
+  pub foam: f32,
+  /// How long (seconds) the trail lasts.
+  pub lifetime: f32,
+  /// Distance (meters) between the trail points.
+  pub spacing: f32,
+}
+
+impl Default for Wake {
+  fn default() -> Self {
+    Self {
+      half_width: 1.0,
+      foam: 0.8,
+      lifetime: 12.0,
+      spacing: 2.0,
+    }
+  }
+}
+
+/// The trail of a `Wake`.
+#[derive(Clone, Debug, Default)]
+pub struct WakeTrail {
+  pub wake: Wake,
+  /// From the entity back: xy = position (`x`, `z`), z = distance behind the entity
+  /// (meters), w = age (seconds).
+  pub points: Vec<Vec4>,
+}
+
+/// The wake trails of the frame, drawn by the water shader.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct WaterWakes {
+  pub trails: Vec<WakeTrail>,
+}
+
+impl WaterDataTexture for WaterWakes {
+  const SIZE: UVec2 = UVec2::new(MAX_WAKE_POINTS as u32, 2);
+
+  /// Row 0 has the points, row 1 has the trail at its first point:
+  /// x = half width, y = foam, z = lifetime, w = points.
+  fn texels(&self) -> Vec<Vec4> {
+    let mut texels = vec![Vec4::ZERO; MAX_WAKE_POINTS * 2];
+    let mut start = 0;
+    for trail in &self.trails {
+      // Cut the last trails down to the space left.
+      let count = trail.points.len().min(MAX_WAKE_POINTS - start);
+      if count < 2 {
+        continue;
+      }
+      texels[start..start + count].copy_from_slice(&trail.points[..count]);
+      let wake = trail.wake;
+      texels[MAX_WAKE_POINTS + start] =
+        Vec4::new(wake.half_width, wake.foam, wake.lifetime, count as f32);
+      start += count;
+    }
+    texels
+  }
+}
+
+/// Recorded trail positions (`x`, `z`) and times.
+type TrailHistory = VecDeque<(Vec2, f32)>;
+
+/// Record the trails of the wakes.
+fn update_wakes(
+  time: Res<Time>,
+  mut water_wakes: ResMut<WaterWakes>,
+  wakes: Query<(Entity, &Wake, &GlobalTransform)>,
+  mut history: Local<HashMap<Entity, TrailHistory>>,
+  mut warned: Local<bool>,
+) {
+  let now = time.elapsed_seconds();
+  let mut trails = Vec::new();
+  let mut recorded = HashMap::with_capacity(history.len());
+  for (entity, wake, transform) in wakes.iter() {
+    let position = transform.translation().xz();
+    let mut points = history.remove(&entity).unwrap_or_default();
+    // A new point every `spacing` meters.
+    if !points
+      .front()
+      .is_some_and(|(last, _)| last.distance(position) < wake.spacing)
+    {
+      points.push_front((position, now));
+    }
+    while points.back().is_some_and(|(_, t)| now - *t > wake.lifetime) {
+      points.pop_back();
+    }
+    points.truncate(MAX_WAKE_TRAIL_POINTS - 1);
+
+    // From the entity back along the recorded positions.
+    let mut trail = vec![position.extend(0.0).extend(0.0)];
+    let mut last = position;
+    let mut distance = 0.0;
+    for (point, t) in points.iter() {
+      let step = last.distance(*point);
+      if step < 1e-3 {
+        continue;
+      }
+      distance += step;
+      trail.push(point.extend(distance).extend(now - t));
+      last = *point;
+    }
+    if trail.len() >= 2 {
+      trails.push(WakeTrail {
+        wake: *wake,
+        points: trail,
+      });
+    }
+    recorded.insert(entity, points);
+  }
+  *history = recorded;
+  let points: usize = trails.iter().map(|trail| trail.points.len()).sum();
+  if points > MAX_WAKE_POINTS && !*warned {
+    warn!("The wake trails don't fit in {MAX_WAKE_POINTS} points, the last ones are cut short.");
+    *warned = true;
+  }
+  water_wakes.trails = trails;
+}
+
+/// Give the water materials the wakes while there are any.
+fn update_wake_materials(
+  wakes: Query<(), With<Wake>>,
+  wakes_image: Res<WaterDataImage<WaterWakes>>,
+  mut materials: ResMut<Assets<StandardWaterMaterial>>,
+) {
+  let image = (!wakes.is_empty()).then_some(&wakes_image.image);
+  // Only touch the materials that changed, changed materials are prepared again.
+  let outdated: Vec<_> = materials
+    .iter()
+    .filter(|(_, mat)| mat.extension.wakes.as_ref() != image)
+    .map(|(id, _)| id)
+    .collect();
+  for id in outdated {
+    if let Some(mat) = materials.get_mut(id) {
+      mat.extension.wakes = image.cloned();
+    }
+  }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct WaterWakePlugin;
+
+impl Plugin for WaterWakePlugin {
+  fn build(&self, app: &mut App) {
+    app
+      .register_type::<Wake>()
+      .init_resource::<WaterWakes>()
+      .add_systems(
+        Update,
+        (
+          update_wakes,
+          update_wake_materials.run_if(resource_exists::<WaterDataImage<WaterWakes>>),
+        )
+          .chain(),
+      )
+      .add_plugins(WaterDataTexturePlugin::<WaterWakes>::default());
+  }
+}
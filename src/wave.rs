@@ -85,6 +85,8 @@ fn wave(g_time: f32, p: Vec2) -> f32 {
   wave_y + n
 }
 
+// CPU port of `get_wave_height` in `assets/shaders/water_functions.wgsl`.
+// `tests/wave_parity.rs` evaluates the shader and fails if the two diverge.
 pub(crate) fn get_wave_height_2d(g_time: f32, p: Vec2) -> f32 {
   let time = g_time / 2.0;
   let mut d = wave(g_time, (p + time) * 0.4) * 0.3;
@@ -115,3 +117,309 @@ pub fn get_wave_point(time: f32, base_height: f32, amplitude: f32, mut pos: Vec3
   pos.y = get_wave_height(time, base_height, amplitude, pos);
   pos
 }
+
+/// Maximum number of Gerstner wave layers supported by the water shader.
+pub const MAX_GERSTNER_WAVES: usize = 8;
+
+/// Gravity used for the deep water dispersion relation.
+const GRAVITY: f32 = 9.81;
+
+/// A single Gerstner (trochoidal) wave layer.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug)]
+pub struct GerstnerWave {
+  /// Direction the wave travels in on the XZ plane.  Doesn't need to be normalized.
+  pub direction: Vec2,
+  /// Distance between two crests.
+  pub wavelength: f32,
+  /// Sharpness of the crests, 0.0 = flat, 1.0 = sharp crests.
+  ///
+  /// The sum of the steepness of all layers should stay below 1.0 to avoid loops at the crests.
+  pub steepness: f32,
+  /// Phase speed of the wave.
+  pub speed: f32,
+}
+
+impl Default for GerstnerWave {
+  fn default() -> Self {
+    Self::new(Vec2::X, 60.0, 0.25)
+  }
+}
+
+impl GerstnerWave {
+  /// Create a wave layer that moves with the deep water phase speed for its wavelength.
+  pub fn new(direction: Vec2, wavelength: f32, steepness: f32) -> Self {
+    Self {
+      direction,
+      wavelength,
+      steepness,
+      speed: (GRAVITY * wavelength / std::f32::consts::TAU).sqrt(),
+    }
+  }
+
+  /// Wave vector: the normalized direction scaled by the wave number.
+  pub fn wave_vector(&self) -> Vec2 {
+    self.direction.normalize_or_zero() * (std::f32::consts::TAU / self.wavelength.max(0.001))
+  }
+
+  /// Displacement of the point `p` on the undisturbed water plane at `time`.
+  pub fn displacement(&self, time: f32, p: Vec2) -> Vec3 {
+    let k_vec = self.wave_vector();
+    let k = k_vec.length();
+    if k == 0.0 {
+      return Vec3::ZERO;
+    }
+    let dir = k_vec / k;
+    let a = self.steepness / k;
+    let f = k * (dir.dot(p) - self.speed * time);
+    let (sin, cos) = (f.sin(), f.cos());
+    Vec3::new(dir.x * a * cos, a * sin, dir.y * a * cos)
+  }
+}
+
+/// Direction and scale of the noise waves.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug)]
+pub struct NoiseWaves {
+  /// Axis the waves travel along (both ways) on the XZ plane.  Doesn't need to be normalized.
+  pub direction: Vec2,
+  /// Wavelength multiplier.  Longer waves also travel faster.
+  pub scale: f32,
+  /// Earlier waves that are faded out while these fade in.
+  pub fade: Option<NoiseFade>,
+}
+
+impl Default for NoiseWaves {
+  fn default() -> Self {
+    Self {
+      direction: Vec2::ONE,
+      scale: 1.0,
+      fade: None,
+    }
+  }
+}
+
+/// Noise waves faded out by a `SeaState` transition.
+///
+/// Cross-fading keeps the waves of both in place, changing the direction or scale of
+/// the waves would slide them.
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Debug)]
+pub struct NoiseFade {
+  /// Direction of the faded out waves.
+  pub direction: Vec2,
+  /// Wavelength multiplier of the faded out waves.
+  pub scale: f32,
+  /// Weight of the faded out waves, 0.0 = gone, 1.0 = only these.
+  pub weight: f32,
+}
+
+impl NoiseFade {
+  /// The faded out waves.
+  pub(crate) fn waves(&self) -> NoiseWaves {
+    NoiseWaves {
+      direction: self.direction,
+      scale: self.scale,
+      fade: None,
+    }
+  }
+}
+
+impl NoiseWaves {
+  /// Rotation (`cos`, `sin`) from world space to the noise space, where the waves travel
+  /// along the diagonal.
+  pub(crate) fn rotation(&self) -> Vec2 {
+    let diagonal = Vec2::ONE.normalize();
+    let dir = self.direction.try_normalize().unwrap_or(diagonal);
+    // diagonal * conjugate(dir)
+    Vec2::new(
+      diagonal.x * dir.x + diagonal.y * dir.y,
+      diagonal.y * dir.x - diagonal.x * dir.y,
+    )
+  }
+
+  pub(crate) fn scale(&self) -> f32 {
+    self.scale.max(0.001)
+  }
+
+  /// Weight of the faded out waves.
+  pub(crate) fn fade_weight(&self) -> f32 {
+    self.fade.map_or(0.0, |fade| fade.weight.clamp(0.0, 1.0))
+  }
+
+  /// Wave height (with unit amplitude) at `p`.
+  ///
+  /// Matches `get_noise_wave_height` in `water_functions.wgsl`.
+  pub fn height(&self, time: f32, p: Vec2) -> f32 {
+    let scale = self.scale();
+    let height = get_wave_height_2d(time / scale.sqrt(), self.rotation().rotate(p) / scale);
+    match self.fade {
+      Some(fade) if self.fade_weight() > 0.0 => {
+        mix(height, fade.waves().height(time, p), self.fade_weight())
+      }
+      _ => height,
+    }
+  }
+}
+
+/// The wave model used by the water material and `WaterParam`.
+#[derive(Clone, Debug, PartialEq, Reflect)]
+#[reflect(Default, Debug)]
+pub enum WaveModel {
+  /// Isotropic noise (fbm) based waves.
+  Noise(NoiseWaves),
+  /// Sum of directional Gerstner wave layers (up to `MAX_GERSTNER_WAVES`).
+  Gerstner(Vec<GerstnerWave>),
+  /// FFT ocean spectrum, configured by the `FftOcean` resource.
+  ///
+  /// The CPU side of the simulation lives in the `FftOceanState` resource,
+  /// use `WaterParam` to query the waves.
+  Fft,
+}
+
+impl Default for WaveModel {
+  fn default() -> Self {
+    Self::Noise(NoiseWaves::default())
+  }
+}
+
+impl WaveModel {
+  /// A few directional swells, mostly traveling along +X.
+  pub fn gerstner_swell() -> Self {
+    Self::Gerstner(vec![
+      GerstnerWave::new(Vec2::new(1.0, 0.0), 60.0, 0.25),
+      GerstnerWave::new(Vec2::new(1.0, 0.6), 31.0, 0.25),
+      GerstnerWave::new(Vec2::new(1.0, 1.3), 18.0, 0.2),
+      GerstnerWave::new(Vec2::new(0.7, -1.0), 9.0, 0.15),
+    ])
+  }
+
+  /// The shader mode id.
+  pub(crate) fn mode(&self) -> u32 {
+    match self {
+      Self::Noise(_) => 0,
+      Self::Gerstner(_) => 1,
+      Self::Fft => 2,
+    }
+  }
+
+  /// The active Gerstner layers.
+  pub fn gerstner_waves(&self) -> &[GerstnerWave] {
+    match self {
+      Self::Gerstner(waves) => &waves[..waves.len().min(MAX_GERSTNER_WAVES)],
+      _ => &[],
+    }
+  }
+
+  /// The noise wave settings, defaults for the other models.
+  pub(crate) fn noise_waves(&self) -> NoiseWaves {
+    match self {
+      Self::Noise(noise) => *noise,
+      _ => NoiseWaves::default(),
+    }
+  }
+
+  /// The faded out noise waves, defaults without them.
+  pub(crate) fn noise_fade(&self) -> NoiseWaves {
+    self
+      .noise_waves()
+      .fade
+      .map_or_else(NoiseWaves::default, |fade| fade.waves())
+  }
+
+  /// Gerstner layers packed for the shader: `xy` = wave vector, `z` = steepness, `w` = speed.
+  pub(crate) fn packed_waves(&self) -> [Vec4; MAX_GERSTNER_WAVES] {
+    let mut packed = [Vec4::ZERO; MAX_GERSTNER_WAVES];
+    for (packed, wave) in packed.iter_mut().zip(self.gerstner_waves()) {
+      *packed = wave.wave_vector().extend(wave.steepness).extend(wave.speed);
+    }
+    packed
+  }
+
+  /// Displacement (with unit amplitude) of the point `p` on the undisturbed water plane.
+  ///
+  /// Matches `get_wave_displacement` in `water_functions.wgsl`.
+  /// Returns zero for `WaveModel::Fft`.
+  pub fn displacement(&self, time: f32, p: Vec2) -> Vec3 {
+    match self {
+      Self::Fft => Vec3::ZERO,
+      Self::Noise(noise) => Vec3::new(0.0, noise.height(time, p), 0.0),
+      Self::Gerstner(_) => self
+        .gerstner_waves()
+        .iter()
+        .fold(Vec3::ZERO, |d, wave| d + wave.displacement(time, p)),
+    }
+  }
+
+  /// Largest displacement (with the given amplitude) on each axis.
+  ///
+  /// Returns zero for `WaveModel::Fft`, see `FftOceanState::max_displacement`.
+  pub fn max_displacement(&self, amplitude: f32) -> Vec3 {
+    let amplitude = amplitude.abs();
+    match self {
+      Self::Fft => Vec3::ZERO,
+      // The noise waves are in -1.0..0.5.
+      Self::Noise(_) => Vec3::new(0.0, amplitude, 0.0),
+      Self::Gerstner(_) => {
+        let a: f32 = self
+          .gerstner_waves()
+          .iter()
+          .map(|wave| {
+            let k = wave.wave_vector().length();
+            if k == 0.0 {
+              0.0
+            } else {
+              wave.steepness.abs() / k
+            }
+          })
+          .sum();
+        Vec3::splat(a * amplitude)
+      }
+    }
+  }
+
+  /// Wave height (with the given amplitude) at the world position `p`.
+  ///
+  /// Gerstner waves move the surface horizontally, so this searches for the point
+  /// on the undisturbed plane that ends up at `p`.
+  ///
+  /// Returns zero for `WaveModel::Fft`.
+  pub fn height(&self, time: f32, amplitude: f32, p: Vec2) -> f32 {
+    match self {
+      Self::Fft => 0.0,
+      Self::Noise(noise) => amplitude * noise.height(time, p),
+      Self::Gerstner(_) => {
+        // Newton's method on `q + displacement(q).xz = p`.
+        let mut q = p;
+        for _ in 0..6 {
+          let mut offset = Vec2::ZERO;
+          let mut jacobian = Mat2::IDENTITY;
+          for wave in self.gerstner_waves() {
+            let k_vec = wave.wave_vector();
+            let k = k_vec.length();
+            if k == 0.0 {
+              continue;
+            }
+            let dir = k_vec / k;
+            let f = k * (dir.dot(q) - wave.speed * time);
+            let s = wave.steepness * amplitude;
+            offset += dir * (s / k) * f.cos();
+            jacobian -= Mat2::from_cols(dir * dir.x, dir * dir.y) * (s * f.sin());
+          }
+          let error = q + offset - p;
+          // The surface folds (steepness >= 1.0) where the Jacobian is singular, keep
+          // the last estimate there.
+          if error.length_squared() < 1e-8 || jacobian.determinant().abs() < 1e-4 {
+            break;
+          }
+          let next = q - jacobian.inverse() * error;
+          if !next.is_finite() {
+            break;
+          }
+          q = next;
+        }
+        amplitude * self.displacement(time, q).y
+      }
+    }
+  }
+}
@@ -0,0 +1,64 @@
+//! The displacement bounds used for the water mesh `Aabb`s hold the waves.
+use bevy::prelude::*;
+use bevy_water::{bounds::max_water_displacement, *};
+
+fn samples() -> impl Iterator<Item = (f32, Vec2)> {
+  (0..2000).map(|i| {
+    let i = i as f32;
+    (i * 0.037, Vec2::new(i * 1.73 - 900.0, i * -0.91 + 300.0))
+  })
+}
+
+#[test]
+fn gerstner_waves_stay_in_bounds() {
+  let model = WaveModel::gerstner_swell();
+  let amplitude = 1.5;
+  let bound = model.max_displacement(amplitude);
+  let mut max = Vec3::ZERO;
+  for (time, p) in samples() {
+    let d = (model.displacement(time, p) * amplitude).abs();
+    assert!(d.cmple(bound + 1e-4).all(), "{d} outside {bound}");
+    max = max.max(d);
+  }
+  // Not much larger than needed.
+  assert!(max.y > bound.y * 0.5, "{max} much smaller than {bound}");
+}
+
+#[test]
+fn noise_waves_stay_in_bounds() {
+  let model = WaveModel::default();
+  let amplitude = 2.0;
+  let bound = model.max_displacement(amplitude);
+  for (time, p) in samples() {
+    let height = model.height(time, amplitude, p).abs();
+    assert!(height <= bound.y, "{height} outside {bound}");
+  }
+}
+
+#[test]
+fn shoaling_waves_stay_in_bounds() {
+  let map = WaterDepthMap::new(Handle::default(), Rect::new(0.0, 0.0, 64.0, 64.0), 8.0);
+  let body = WaterBody {
+    amplitude: 2.0,
+    wave_model: WaveModel::gerstner_swell(),
+    depth_map: Some(map.clone()),
+    ..WaterBody::from(&WaterSettings::default())
+  };
+  let mat = body.material(map.rect);
+  let bound = max_water_displacement(&mat, None, None);
+  let deep = body.wave_model.max_displacement(body.amplitude);
+  for depth in (0..80).map(|i| i as f32 * 0.1) {
+    let d = deep * map.wave_scale(depth);
+    assert!(d.cmple(bound + 1e-4).all(), "{d} at {depth} m outside {bound}");
+  }
+}
+
+#[test]
+fn ripples_raise_the_bounds() {
+  let body = WaterBody::from(&WaterSettings::default());
+  let mat = body.material(Rect::new(0.0, 0.0, 1.0, 1.0));
+  let ripples = WaterRipples::default();
+  let calm = max_water_displacement(&mat, None, None);
+  let rippled = max_water_displacement(&mat, None, Some(&ripples));
+  assert_eq!(rippled - calm, Vec3::splat(ripples.max_height));
+}
@@ -0,0 +1,136 @@
+//! Simulates floating bodies on calm water.
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_water::*;
+
+const WATER_HEIGHT: f32 = 1.0;
+
+fn app() -> App {
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+      1.0 / 30.0,
+    )))
+    .insert_resource(WaterSettings {
+      height: WATER_HEIGHT,
+      amplitude: 0.0,
+      ..default()
+    })
+    .add_plugins(BuoyancyPlugin);
+  app
+}
+
+fn simulate(app: &mut App, seconds: f32) {
+  for _ in 0..(seconds * 30.0) as u32 {
+    app.update();
+  }
+}
+
+#[test]
+fn body_settles_at_its_waterline() {
+  let mut app = app();
+  // 1m cube with half the density of water.
+  let density = app.world.resource::<BuoyancySettings>().water_density;
+  let buoyancy = Buoyancy::from_hull(Vec3::ZERO, Vec3::splat(0.5), UVec3::splat(4), density / 2.0);
+  let body = app
+    .world
+    .spawn((
+      TransformBundle::from_transform(Transform::from_xyz(0.0, 3.0, 0.0)),
+      BuoyancyBundle {
+        buoyancy,
+        ..default()
+      },
+    ))
+    .id();
+  simulate(&mut app, 30.0);
+
+  let transform = app.world.get::<Transform>(body).unwrap();
+  assert!(
+    (transform.translation.y - WATER_HEIGHT).abs() < 0.05,
+    "Body floats at {}",
+    transform.translation.y
+  );
+  let velocity = app.world.get::<BuoyancyVelocity>(body).unwrap();
+  assert!(
+    velocity.linear.length() < 0.05,
+    "Body still moving: {velocity:?}"
+  );
+}
+
+#[test]
+fn heavy_body_sinks() {
+  let mut app = app();
+  let density = app.world.resource::<BuoyancySettings>().water_density;
+  let buoyancy = Buoyancy::from_hull(Vec3::ZERO, Vec3::splat(0.5), UVec3::splat(2), density * 2.0);
+  let body = app
+    .world
+    .spawn((
+      TransformBundle::default(),
+      BuoyancyBundle {
+        buoyancy,
+        ..default()
+      },
+    ))
+    .id();
+  simulate(&mut app, 2.0);
+  let transform = app.world.get::<Transform>(body).unwrap();
+  assert!(
+    transform.translation.y < -1.0,
+    "Body floats at {}",
+    transform.translation.y
+  );
+}
+
+#[test]
+fn tilted_hull_rights_itself() {
+  let mut app = app();
+  let density = app.world.resource::<BuoyancySettings>().water_density;
+  // A flat hull with a low centre of mass.
+  let half_extents = Vec3::new(2.0, 0.5, 4.0);
+  let mass =
+    density * 0.4 * (half_extents * 2.0).x * (half_extents * 2.0).y * (half_extents * 2.0).z;
+  let buoyancy = Buoyancy::from_hull(Vec3::ZERO, half_extents, UVec3::new(4, 2, 8), mass)
+    .with_center_of_mass(Vec3::new(0.0, -0.4, 0.0));
+  let body = app
+    .world
+    .spawn((
+      TransformBundle::from_transform(
+        Transform::from_xyz(0.0, WATER_HEIGHT, 0.0).with_rotation(Quat::from_rotation_z(0.4)),
+      ),
+      BuoyancyBundle {
+        buoyancy,
+        ..default()
+      },
+    ))
+    .id();
+  simulate(&mut app, 30.0);
+  let transform = app.world.get::<Transform>(body).unwrap();
+  let up = transform.rotation * Vec3::Y;
+  assert!(up.y > 0.99, "Hull didn't right itself: up = {up}");
+}
+
+#[test]
+fn small_off_center_body_tilts() {
+  let mut app = app();
+  let density = app.world.resource::<BuoyancySettings>().water_density;
+  // A 10cm cube, heavier on one side.
+  let half_extents = Vec3::splat(0.05);
+  let buoyancy = Buoyancy::from_hull(Vec3::ZERO, half_extents, UVec3::splat(4), density * 0.0005)
+    .with_center_of_mass(Vec3::new(0.03, 0.0, 0.0));
+  let body = app
+    .world
+    .spawn((
+      TransformBundle::from_transform(Transform::from_xyz(0.0, WATER_HEIGHT, 0.0)),
+      BuoyancyBundle {
+        buoyancy,
+        ..default()
+      },
+    ))
+    .id();
+  simulate(&mut app, 1.0);
+  let transform = app.world.get::<Transform>(body).unwrap();
+  let (_, angle) = transform.rotation.to_axis_angle();
+  assert!(angle > 0.1, "Body didn't tilt: {angle}");
+}
@@ -0,0 +1,49 @@
+//! The camera-following ocean levels nest without gaps.
+use bevy::{
+  prelude::*,
+  render::mesh::{Indices, VertexAttributeValues},
+};
+use bevy_water::*;
+
+#[test]
+fn levels_nest_on_the_coarser_grid() {
+  let clipmap = WaterClipmap::default();
+  let half = clipmap.grid_size as f32 / 2.0;
+  for i in 0..1000 {
+    let camera = Vec2::new(i as f32 * 7.31 - 3000.0, i as f32 * -3.17 + 1234.5);
+    let center = clipmap.level_center(0, camera);
+    assert!((camera - center).abs().max_element() < half * clipmap.cell_size);
+    for level in 1..clipmap.levels {
+      let cell_size = clipmap.level_cell_size(level);
+      let outer = clipmap.level_center(level, camera);
+      let inner = clipmap.level_center(level - 1, camera);
+      // The inner level is offset by whole cells of the outer level, at most one.
+      let hole = (inner - outer) / cell_size;
+      assert_eq!(hole, hole.round());
+      assert!(hole.abs().max_element() <= 1.0, "hole offset {hole}");
+    }
+  }
+}
+
+#[test]
+fn ring_mesh_has_a_hole_and_a_stitched_edge() {
+  let size = 8;
+  let mesh = clipmap_mesh(size, Some(IVec2::new(1, 0)));
+  let Some(Indices::U32(indices)) = mesh.indices() else {
+    panic!("missing indices");
+  };
+  // 8x8 cells without the 4x4 hole.
+  assert_eq!(indices.len(), (64 - 16) * 6);
+
+  let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+  else {
+    panic!("missing positions");
+  };
+  // Odd vertices of the outer edge are merged with the previous vertex.
+  let count = size as usize + 1;
+  assert_eq!(positions[1], positions[0]);
+  assert_eq!(positions[3], positions[2]);
+  assert_eq!(positions[count], positions[0]);
+  // Inner vertices are on the grid.
+  assert_eq!(positions[count + 1], [-3.0, 0.0, -3.0]);
+}
@@ -0,0 +1,1196 @@
+//! A small CPU interpreter for the subset of WGSL (naga IR) used by the water shaders.
+//!
+//! This lets the tests evaluate the real shader functions and compare them against the
+//! Rust implementation used by `WaterParam`.
+#![allow(dead_code)]
+
+pub mod physics;
+
+use std::collections::HashMap;
+
+use naga::{
+  BinaryOperator, Block, BuiltIn, Binding, Expression, Function, GlobalVariable, Handle,
+  ImageQuery, Literal, LocalVariable, MathFunction, Module, ScalarKind, Statement, SwitchValue,
+  TypeInner, UnaryOperator,
+};
+use naga_oil::compose::{
+  ComposableModuleDescriptor, Composer, NagaModuleDescriptor, ShaderDefValue, ShaderLanguage,
+  ShaderType,
+};
+
+/// Stub for the parts of `bevy_pbr` that the water shaders import.
+const VIEW_BINDINGS_STUB: &str = r#"
+#define_import_path bevy_pbr::mesh_view_bindings
+
+struct Globals {
+  time: f32,
+  delta_time: f32,
+  frame_count: u32,
+};
+
+@group(0) @binding(9) var<uniform> globals: Globals;
+"#;
+
+/// Stub for the `bevy_render` globals, used by the post-processing shaders.
+const GLOBALS_STUB: &str = r#"
+#define_import_path bevy_render::globals
+
+struct Globals {
+  time: f32,
+  delta_time: f32,
+  frame_count: u32,
+};
+"#;
+
+/// Stub for the `bevy_core_pipeline` fullscreen vertex output.
+const FULLSCREEN_STUB: &str = r#"
+#define_import_path bevy_core_pipeline::fullscreen_vertex_shader
+
+struct FullscreenVertexOutput {
+  @builtin(position) position: vec4<f32>,
+  @location(0) uv: vec2<f32>,
+};
+"#;
+
+/// Shader libraries in dependency order.
+const WATER_SHADERS: &[(&str, &str)] = &[
+  (
+    "shaders/noise/random.wgsl",
+    include_str!("../../assets/shaders/noise/random.wgsl"),
+  ),
+  (
+    "shaders/noise/vnoise.wgsl",
+    include_str!("../../assets/shaders/noise/vnoise.wgsl"),
+  ),
+  (
+    "shaders/noise/fbm.wgsl",
+    include_str!("../../assets/shaders/noise/fbm.wgsl"),
+  ),
+  (
+    "shaders/water_bindings.wgsl",
+    include_str!("../../assets/shaders/water_bindings.wgsl"),
+  ),
+  (
+    "shaders/water_functions.wgsl",
+    include_str!("../../assets/shaders/water_functions.wgsl"),
+  ),
+  (
+    "shaders/water_rain.wgsl",
+    include_str!("../../assets/shaders/water_rain.wgsl"),
+  ),
+  (
+    "shaders/water_foam.wgsl",
+    include_str!("../../assets/shaders/water_foam.wgsl"),
+  ),
+  (
+    "src/water/caustics_functions.wgsl",
+    include_str!("../../src/water/caustics_functions.wgsl"),
+  ),
+];
+
+/// Compose `entry` (which can `#import` any of the water shader libraries) into a naga module.
+pub fn compose_water_shader(entry: &str) -> Module {
+  compose_water_shader_with_defs(entry, &[])
+}
+
+/// Like `compose_water_shader`, with the boolean shader `defs` enabled.
+pub fn compose_water_shader_with_defs(entry: &str, defs: &[&str]) -> Module {
+  let mut composer = Composer::default();
+  let stubs = [
+    ("stubs/mesh_view_bindings.wgsl", VIEW_BINDINGS_STUB),
+    ("stubs/globals.wgsl", GLOBALS_STUB),
+    ("stubs/fullscreen_vertex_shader.wgsl", FULLSCREEN_STUB),
+  ];
+  for (file_path, source) in stubs.iter().chain(WATER_SHADERS.iter()) {
+    composer
+      .add_composable_module(ComposableModuleDescriptor {
+        source,
+        file_path,
+        language: ShaderLanguage::Wgsl,
+        ..Default::default()
+      })
+      .unwrap_or_else(|err| panic!("Failed to add {file_path}: {err:?}"));
+  }
+  composer
+    .make_naga_module(NagaModuleDescriptor {
+      source: entry,
+      file_path: "tests/entry.wgsl",
+      shader_type: ShaderType::Wgsl,
+      shader_defs: defs
+        .iter()
+        .map(|def| (def.to_string(), ShaderDefValue::Bool(true)))
+        .collect(),
+      ..Default::default()
+    })
+    .unwrap_or_else(|err| panic!("Failed to compose test shader: {err:?}"))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+  F32(f32),
+  I32(i32),
+  U32(u32),
+  Bool(bool),
+  /// Vectors, matrices (as columns), arrays and structs.
+  Composite(Vec<Value>),
+  Pointer(Pointer),
+  Texture(Texture),
+  Sampler(Sampler),
+}
+
+/// A 2D texture, texels row by row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Texture {
+  pub width: u32,
+  pub height: u32,
+  pub texels: Vec<[f32; 4]>,
+}
+
+impl Texture {
+  pub fn new(width: u32, height: u32) -> Self {
+    Self {
+      width,
+      height,
+      texels: vec![[0.0; 4]; (width * height) as usize],
+    }
+  }
+
+  pub fn get(&self, x: u32, y: u32) -> [f32; 4] {
+    self.texels[(y * self.width + x) as usize]
+  }
+
+  pub fn set(&mut self, x: u32, y: u32, texel: [f32; 4]) {
+    self.texels[(y * self.width + x) as usize] = texel;
+  }
+
+  /// The texel at `(x, y)`, zero outside the texture.
+  fn load(&self, x: i32, y: i32) -> [f32; 4] {
+    if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+      return [0.0; 4];
+    }
+    self.get(x as u32, y as u32)
+  }
+
+  fn sample(&self, sampler: Sampler, u: f32, v: f32) -> [f32; 4] {
+    let (w, h) = (self.width as i32, self.height as i32);
+    let texel = |x: i32, y: i32| match sampler.repeat {
+      true => self.get(x.rem_euclid(w) as u32, y.rem_euclid(h) as u32),
+      false => self.get(x.clamp(0, w - 1) as u32, y.clamp(0, h - 1) as u32),
+    };
+    let (x, y) = (u * w as f32, v * h as f32);
+    if !sampler.linear {
+      return texel(x.floor() as i32, y.floor() as i32);
+    }
+    let (x, y) = (x - 0.5, y - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    let mut out = [0.0; 4];
+    for (i, out) in out.iter_mut().enumerate() {
+      let top = texel(x0, y0)[i] * (1.0 - fx) + texel(x0 + 1, y0)[i] * fx;
+      let bottom = texel(x0, y0 + 1)[i] * (1.0 - fx) + texel(x0 + 1, y0 + 1)[i] * fx;
+      *out = top * (1.0 - fy) + bottom * fy;
+    }
+    out
+  }
+}
+
+/// Filtering and address mode of a sampler.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sampler {
+  pub linear: bool,
+  pub repeat: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pointer {
+  Local(Handle<LocalVariable>, Vec<usize>),
+  Global(Handle<GlobalVariable>, Vec<usize>),
+}
+
+impl Value {
+  pub fn vec2(x: f32, y: f32) -> Self {
+    Self::Composite(vec![Self::F32(x), Self::F32(y)])
+  }
+
+  pub fn vec4(x: f32, y: f32, z: f32, w: f32) -> Self {
+    Self::Composite(vec![
+      Self::F32(x),
+      Self::F32(y),
+      Self::F32(z),
+      Self::F32(w),
+    ])
+  }
+
+  pub fn as_f32(&self) -> f32 {
+    match self {
+      Self::F32(v) => *v,
+      v => panic!("Expected f32, got {v:?}"),
+    }
+  }
+
+  pub fn texel(texel: [f32; 4]) -> Self {
+    Self::vec4(texel[0], texel[1], texel[2], texel[3])
+  }
+
+  pub fn as_f32s(&self) -> Vec<f32> {
+    match self {
+      Self::Composite(v) => v.iter().map(Self::as_f32).collect(),
+      v => vec![v.as_f32()],
+    }
+  }
+
+  fn as_bool(&self) -> bool {
+    match self {
+      Self::Bool(v) => *v,
+      v => panic!("Expected bool, got {v:?}"),
+    }
+  }
+
+  pub fn as_texture(&self) -> &Texture {
+    match self {
+      Self::Texture(texture) => texture,
+      v => panic!("Expected texture, got {v:?}"),
+    }
+  }
+
+  fn as_i32(&self) -> i32 {
+    match self {
+      Self::I32(v) => *v,
+      Self::U32(v) => *v as i32,
+      v => panic!("Expected integer, got {v:?}"),
+    }
+  }
+
+  fn as_index(&self) -> usize {
+    match self {
+      Self::I32(v) => *v as usize,
+      Self::U32(v) => *v as usize,
+      v => panic!("Expected index, got {v:?}"),
+    }
+  }
+
+  fn components(&self) -> &[Value] {
+    match self {
+      Self::Composite(v) => v,
+      v => panic!("Expected composite, got {v:?}"),
+    }
+  }
+
+  fn is_matrix(&self) -> bool {
+    matches!(self, Self::Composite(cols) if matches!(cols.first(), Some(Self::Composite(_))))
+  }
+
+  fn get_path(&self, path: &[usize]) -> &Value {
+    path.iter().fold(self, |v, idx| &v.components()[*idx])
+  }
+
+  fn get_path_mut(&mut self, path: &[usize]) -> &mut Value {
+    path.iter().fold(self, |v, idx| match v {
+      Self::Composite(c) => &mut c[*idx],
+      v => panic!("Expected composite, got {v:?}"),
+    })
+  }
+}
+
+enum Flow {
+  Normal,
+  Break,
+  Continue,
+  Return(Option<Value>),
+}
+
+struct Frame<'a> {
+  func: &'a Function,
+  args: Vec<Value>,
+  exprs: Vec<Option<Value>>,
+  locals: Vec<Value>,
+}
+
+/// Evaluates functions of a naga module on the CPU.
+pub struct Interpreter {
+  module: Module,
+  globals: HashMap<Handle<GlobalVariable>, Value>,
+}
+
+/// naga_oil mangles the names of items from imported modules.
+fn name_matches(name: Option<&String>, wanted: &str) -> bool {
+  name.is_some_and(|name| {
+    name == wanted || name.starts_with(&format!("{wanted}X_naga_oil_mod_X"))
+  })
+}
+
+impl Interpreter {
+  pub fn new(module: Module) -> Self {
+    let mut interp = Self {
+      module,
+      globals: HashMap::new(),
+    };
+    let globals = interp
+      .module
+      .global_variables
+      .iter()
+      .map(|(handle, var)| (handle, interp.zero_value(var.ty)))
+      .collect();
+    interp.globals = globals;
+    interp
+  }
+
+  fn global_handle(&self, name: &str) -> Handle<GlobalVariable> {
+    self
+      .module
+      .global_variables
+      .iter()
+      .find(|(_, var)| name_matches(var.name.as_ref(), name))
+      .map(|(handle, _)| handle)
+      .unwrap_or_else(|| panic!("Missing global variable: {name}"))
+  }
+
+  /// Set a member of a global uniform struct, e.g. `("material", "amplitude")`.
+  pub fn set_global_member(&mut self, global: &str, member: &str, value: Value) {
+    let handle = self.global_handle(global);
+    let ty = self.module.global_variables[handle].ty;
+    let idx = match &self.module.types[ty].inner {
+      TypeInner::Struct { members, .. } => members
+        .iter()
+        .position(|m| m.name.as_deref() == Some(member))
+        .unwrap_or_else(|| panic!("Missing member {global}.{member}")),
+      inner => panic!("Global {global} isn't a struct: {inner:?}"),
+    };
+    let global = self.globals.get_mut(&handle).expect("Global not initialized");
+    *global.get_path_mut(&[idx]) = value;
+  }
+
+  /// Replace a whole global, e.g. a texture or a storage buffer.
+  pub fn set_global(&mut self, global: &str, value: Value) {
+    let handle = self.global_handle(global);
+    self.globals.insert(handle, value);
+  }
+
+  pub fn global(&self, global: &str) -> &Value {
+    &self.globals[&self.global_handle(global)]
+  }
+
+  /// Call the function named `name`.
+  pub fn call(&self, name: &str, args: Vec<Value>) -> Value {
+    let handle = self
+      .module
+      .functions
+      .iter()
+      .find(|(_, f)| name_matches(f.name.as_ref(), name))
+      .map(|(handle, _)| handle)
+      .unwrap_or_else(|| panic!("Missing function: {name}"));
+    let mut globals = self.globals.clone();
+    self
+      .call_function(&self.module.functions[handle], args, &mut globals)
+      .expect("Function didn't return a value")
+  }
+
+  /// Run the compute entry point `name` once for every `global_invocation_id` in
+  /// `size.0 x size.1`.  The writes to the globals are kept.
+  pub fn dispatch(&mut self, name: &str, size: (u32, u32)) {
+    let entry = self
+      .module
+      .entry_points
+      .iter()
+      .find(|entry| entry.name == name)
+      .unwrap_or_else(|| panic!("Missing entry point: {name}"));
+    let mut globals = std::mem::take(&mut self.globals);
+    for y in 0..size.1 {
+      for x in 0..size.0 {
+        let args = entry
+          .function
+          .arguments
+          .iter()
+          .map(|arg| match arg.binding {
+            Some(Binding::BuiltIn(BuiltIn::GlobalInvocationId)) => {
+              Value::Composite(vec![Value::U32(x), Value::U32(y), Value::U32(0)])
+            }
+            ref binding => panic!("Unsupported entry point argument: {binding:?}"),
+          })
+          .collect();
+        self.call_function(&entry.function, args, &mut globals);
+      }
+    }
+    self.globals = globals;
+  }
+
+  fn zero_value(&self, ty: Handle<naga::Type>) -> Value {
+    self.zero_inner(&self.module.types[ty].inner)
+  }
+
+  fn zero_inner(&self, inner: &TypeInner) -> Value {
+    let scalar = |kind: ScalarKind| match kind {
+      ScalarKind::Float | ScalarKind::AbstractFloat => Value::F32(0.0),
+      ScalarKind::Sint | ScalarKind::AbstractInt => Value::I32(0),
+      ScalarKind::Uint => Value::U32(0),
+      ScalarKind::Bool => Value::Bool(false),
+    };
+    match inner {
+      TypeInner::Scalar(s) | TypeInner::Atomic(s) => scalar(s.kind),
+      TypeInner::Vector { size, scalar: s } => Value::Composite(vec![scalar(s.kind); *size as usize]),
+      TypeInner::Matrix {
+        columns,
+        rows,
+        scalar: s,
+      } => Value::Composite(vec![
+        Value::Composite(vec![scalar(s.kind); *rows as usize]);
+        *columns as usize
+      ]),
+      TypeInner::Array { base, size, .. } => {
+        let len = match size {
+          naga::ArraySize::Constant(len) => len.get() as usize,
+          naga::ArraySize::Dynamic => 0,
+        };
+        Value::Composite(vec![self.zero_value(*base); len])
+      }
+      TypeInner::Struct { members, .. } => {
+        Value::Composite(members.iter().map(|m| self.zero_value(m.ty)).collect())
+      }
+      // Opaque types (textures, samplers) have no CPU representation.
+      _ => Value::Composite(vec![]),
+    }
+  }
+
+  fn call_function(
+    &self,
+    func: &Function,
+    args: Vec<Value>,
+    globals: &mut HashMap<Handle<GlobalVariable>, Value>,
+  ) -> Option<Value> {
+    let mut frame = Frame {
+      func,
+      args,
+      exprs: vec![None; func.expressions.len()],
+      locals: Vec::with_capacity(func.local_variables.len()),
+    };
+    for (_, local) in func.local_variables.iter() {
+      let value = match local.init {
+        Some(init) => self.eval(&mut frame, init, globals),
+        None => self.zero_value(local.ty),
+      };
+      frame.locals.push(value);
+    }
+    match self.exec_block(&mut frame, &func.body, globals) {
+      Flow::Return(value) => value,
+      _ => None,
+    }
+  }
+
+  fn exec_block(
+    &self,
+    frame: &mut Frame,
+    block: &Block,
+    globals: &mut HashMap<Handle<GlobalVariable>, Value>,
+  ) -> Flow {
+    for stmt in block.iter() {
+      let flow = self.exec(frame, stmt, globals);
+      if !matches!(flow, Flow::Normal) {
+        return flow;
+      }
+    }
+    Flow::Normal
+  }
+
+  fn exec(
+    &self,
+    frame: &mut Frame,
+    stmt: &Statement,
+    globals: &mut HashMap<Handle<GlobalVariable>, Value>,
+  ) -> Flow {
+    match stmt {
+      Statement::Emit(range) => {
+        for h in range.clone() {
+          let value = self.compute(frame, h, globals);
+          frame.exprs[h.index()] = Some(value);
+        }
+      }
+      Statement::Block(block) => return self.exec_block(frame, block, globals),
+      Statement::If {
+        condition,
+        accept,
+        reject,
+      } => {
+        let block = if self.eval(frame, *condition, globals).as_bool() {
+          accept
+        } else {
+          reject
+        };
+        return self.exec_block(frame, block, globals);
+      }
+      Statement::Switch { selector, cases } => {
+        let selector = self.eval(frame, *selector, globals);
+        let start = cases
+          .iter()
+          .position(|case| match (case.value, &selector) {
+            (SwitchValue::I32(v), Value::I32(s)) => v == *s,
+            (SwitchValue::U32(v), Value::U32(s)) => v == *s,
+            _ => false,
+          })
+          .or_else(|| cases.iter().position(|c| c.value == SwitchValue::Default));
+        if let Some(start) = start {
+          for case in &cases[start..] {
+            match self.exec_block(frame, &case.body, globals) {
+              Flow::Normal if case.fall_through => continue,
+              Flow::Normal | Flow::Break => break,
+              flow => return flow,
+            }
+          }
+        }
+      }
+      Statement::Loop {
+        body,
+        continuing,
+        break_if,
+      } => loop {
+        match self.exec_block(frame, body, globals) {
+          Flow::Break => break,
+          Flow::Return(value) => return Flow::Return(value),
+          Flow::Normal | Flow::Continue => {}
+        }
+        if let Flow::Return(value) = self.exec_block(frame, continuing, globals) {
+          return Flow::Return(value);
+        }
+        if let Some(break_if) = break_if {
+          if self.eval(frame, *break_if, globals).as_bool() {
+            break;
+          }
+        }
+      },
+      Statement::Break => return Flow::Break,
+      Statement::Continue => return Flow::Continue,
+      Statement::Return { value } => {
+        return Flow::Return(value.map(|v| self.eval(frame, v, globals)));
+      }
+      Statement::Store { pointer, value } => {
+        let value = self.eval(frame, *value, globals);
+        match self.eval(frame, *pointer, globals) {
+          Value::Pointer(Pointer::Local(local, path)) => {
+            *frame.locals[local.index()].get_path_mut(&path) = value;
+          }
+          Value::Pointer(Pointer::Global(global, path)) => {
+            *globals.get_mut(&global).unwrap().get_path_mut(&path) = value;
+          }
+          v => panic!("Store to non-pointer: {v:?}"),
+        }
+      }
+      Statement::Call {
+        function,
+        arguments,
+        result,
+      } => {
+        let args = arguments
+          .iter()
+          .map(|arg| self.eval(frame, *arg, globals))
+          .collect();
+        let value = self.call_function(&self.module.functions[*function], args, globals);
+        if let Some(result) = result {
+          frame.exprs[result.index()] = value;
+        }
+      }
+      Statement::ImageStore {
+        image,
+        coordinate,
+        value,
+        ..
+      } => {
+        let value = self.eval(frame, *value, globals).as_f32s();
+        let coordinate = self.eval(frame, *coordinate, globals);
+        let c = coordinate.components();
+        let Value::Pointer(Pointer::Global(global, path)) = self.eval(frame, *image, globals) else {
+          panic!("Store to a texture that isn't a global");
+        };
+        let Value::Texture(texture) = globals.get_mut(&global).unwrap().get_path_mut(&path) else {
+          panic!("Store to a global that isn't a texture");
+        };
+        let (x, y) = (c[0].as_i32(), c[1].as_i32());
+        if x >= 0 && y >= 0 && (x as u32) < texture.width && (y as u32) < texture.height {
+          texture.set(x as u32, y as u32, [value[0], value[1], value[2], value[3]]);
+        }
+      }
+      stmt => panic!("Unsupported statement: {stmt:?}"),
+    }
+    Flow::Normal
+  }
+
+  /// Get the value of an expression that was already emitted (or doesn't need emitting).
+  fn eval(
+    &self,
+    frame: &mut Frame,
+    h: Handle<Expression>,
+    globals: &mut HashMap<Handle<GlobalVariable>, Value>,
+  ) -> Value {
+    if let Some(value) = &frame.exprs[h.index()] {
+      return value.clone();
+    }
+    self.compute(frame, h, globals)
+  }
+
+  fn compute(
+    &self,
+    frame: &mut Frame,
+    h: Handle<Expression>,
+    globals: &mut HashMap<Handle<GlobalVariable>, Value>,
+  ) -> Value {
+    let func = frame.func;
+    match &func.expressions[h] {
+      Expression::Literal(lit) => literal(lit),
+      Expression::Constant(c) => self.eval_const(self.module.constants[*c].init),
+      Expression::ZeroValue(ty) => self.zero_value(*ty),
+      Expression::FunctionArgument(idx) => frame.args[*idx as usize].clone(),
+      Expression::GlobalVariable(g) => Value::Pointer(Pointer::Global(*g, vec![])),
+      Expression::LocalVariable(l) => Value::Pointer(Pointer::Local(*l, vec![])),
+      Expression::Compose { ty, components } => {
+        let values = components
+          .iter()
+          .map(|c| self.eval(frame, *c, globals))
+          .collect();
+        self.compose(*ty, values)
+      }
+      Expression::Access { base, index } => {
+        let index = self.eval(frame, *index, globals).as_index();
+        access(self.eval(frame, *base, globals), index)
+      }
+      Expression::AccessIndex { base, index } => {
+        access(self.eval(frame, *base, globals), *index as usize)
+      }
+      Expression::Splat { size, value } => {
+        Value::Composite(vec![self.eval(frame, *value, globals); *size as usize])
+      }
+      Expression::Swizzle {
+        size,
+        vector,
+        pattern,
+      } => {
+        let vector = self.eval(frame, *vector, globals);
+        let vector = vector.components();
+        Value::Composite(
+          pattern[..*size as usize]
+            .iter()
+            .map(|c| vector[*c as usize].clone())
+            .collect(),
+        )
+      }
+      Expression::Load { pointer } => match self.eval(frame, *pointer, globals) {
+        Value::Pointer(Pointer::Local(local, path)) => {
+          frame.locals[local.index()].get_path(&path).clone()
+        }
+        Value::Pointer(Pointer::Global(global, path)) => globals[&global].get_path(&path).clone(),
+        v => panic!("Load from non-pointer: {v:?}"),
+      },
+      Expression::Unary { op, expr } => unary(*op, self.eval(frame, *expr, globals)),
+      Expression::Binary { op, left, right } => {
+        let left = self.eval(frame, *left, globals);
+        let right = self.eval(frame, *right, globals);
+        binary(*op, left, right)
+      }
+      Expression::Select {
+        condition,
+        accept,
+        reject,
+      } => {
+        let condition = self.eval(frame, *condition, globals);
+        let accept = self.eval(frame, *accept, globals);
+        let reject = self.eval(frame, *reject, globals);
+        select(&condition, accept, reject)
+      }
+      Expression::Math {
+        fun,
+        arg,
+        arg1,
+        arg2,
+        ..
+      } => {
+        let arg = self.eval(frame, *arg, globals);
+        let arg1 = arg1.map(|a| self.eval(frame, a, globals));
+        let arg2 = arg2.map(|a| self.eval(frame, a, globals));
+        math(*fun, arg, arg1, arg2)
+      }
+      Expression::As {
+        expr,
+        kind,
+        convert,
+      } => {
+        let value = self.eval(frame, *expr, globals);
+        map1(&value, &|v| cast(v, *kind, convert.is_some()))
+      }
+      Expression::Relational { fun, argument } => {
+        let argument = self.eval(frame, *argument, globals);
+        let bools = argument.components().iter().map(Value::as_bool);
+        Value::Bool(match fun {
+          naga::RelationalFunction::All => bools.into_iter().all(|b| b),
+          naga::RelationalFunction::Any => bools.into_iter().any(|b| b),
+          fun => panic!("Unsupported relational function: {fun:?}"),
+        })
+      }
+      Expression::ImageLoad {
+        image, coordinate, ..
+      } => {
+        let image = self.eval(frame, *image, globals);
+        let coordinate = self.eval(frame, *coordinate, globals);
+        let c = coordinate.components();
+        Value::texel(texture(&image, globals).load(c[0].as_i32(), c[1].as_i32()))
+      }
+      Expression::ImageSample {
+        image,
+        sampler,
+        coordinate,
+        ..
+      } => {
+        let image = self.eval(frame, *image, globals);
+        let sampler = match resolve_global(&self.eval(frame, *sampler, globals), globals) {
+          Value::Sampler(sampler) => *sampler,
+          // Unset samplers are nearest and clamp to the edge.
+          _ => Sampler::default(),
+        };
+        let uv = self.eval(frame, *coordinate, globals).as_f32s();
+        Value::texel(texture(&image, globals).sample(sampler, uv[0], uv[1]))
+      }
+      Expression::ImageQuery {
+        image,
+        query: ImageQuery::Size { .. },
+      } => {
+        let image = self.eval(frame, *image, globals);
+        let texture = texture(&image, globals);
+        Value::Composite(vec![Value::U32(texture.width), Value::U32(texture.height)])
+      }
+      Expression::CallResult(_) => panic!("Call result used before the call"),
+      expr => panic!("Unsupported expression: {expr:?}"),
+    }
+  }
+
+  fn eval_const(&self, h: Handle<Expression>) -> Value {
+    match &self.module.const_expressions[h] {
+      Expression::Literal(lit) => literal(lit),
+      Expression::Constant(c) => self.eval_const(self.module.constants[*c].init),
+      Expression::ZeroValue(ty) => self.zero_value(*ty),
+      Expression::Compose { ty, components } => {
+        let values = components.iter().map(|c| self.eval_const(*c)).collect();
+        self.compose(*ty, values)
+      }
+      Expression::Splat { size, value } => {
+        Value::Composite(vec![self.eval_const(*value); *size as usize])
+      }
+      expr => panic!("Unsupported constant expression: {expr:?}"),
+    }
+  }
+
+  fn compose(&self, ty: Handle<naga::Type>, values: Vec<Value>) -> Value {
+    match &self.module.types[ty].inner {
+      // Vectors can be composed from smaller vectors.
+      TypeInner::Vector { .. } => Value::Composite(
+        values
+          .into_iter()
+          .flat_map(|v| match v {
+            Value::Composite(c) => c,
+            v => vec![v],
+          })
+          .collect(),
+      ),
+      _ => Value::Composite(values),
+    }
+  }
+}
+
+/// Textures and samplers are used through their global variables.
+fn resolve_global<'a>(
+  value: &'a Value,
+  globals: &'a HashMap<Handle<GlobalVariable>, Value>,
+) -> &'a Value {
+  match value {
+    Value::Pointer(Pointer::Global(global, path)) => globals[global].get_path(path),
+    value => value,
+  }
+}
+
+fn texture<'a>(value: &'a Value, globals: &'a HashMap<Handle<GlobalVariable>, Value>) -> &'a Texture {
+  resolve_global(value, globals).as_texture()
+}
+
+fn literal(lit: &Literal) -> Value {
+  match *lit {
+    Literal::F32(v) => Value::F32(v),
+    Literal::F64(v) | Literal::AbstractFloat(v) => Value::F32(v as f32),
+    Literal::I32(v) => Value::I32(v),
+    Literal::I64(v) | Literal::AbstractInt(v) => Value::I32(v as i32),
+    Literal::U32(v) => Value::U32(v),
+    Literal::Bool(v) => Value::Bool(v),
+  }
+}
+
+fn access(base: Value, index: usize) -> Value {
+  match base {
+    Value::Pointer(Pointer::Local(local, mut path)) => {
+      path.push(index);
+      Value::Pointer(Pointer::Local(local, path))
+    }
+    Value::Pointer(Pointer::Global(global, mut path)) => {
+      path.push(index);
+      Value::Pointer(Pointer::Global(global, path))
+    }
+    base => base.components()[index].clone(),
+  }
+}
+
+fn cast(v: &Value, kind: ScalarKind, convert: bool) -> Value {
+  match (kind, convert) {
+    (ScalarKind::Float, true) => Value::F32(match *v {
+      Value::F32(v) => v,
+      Value::I32(v) => v as f32,
+      Value::U32(v) => v as f32,
+      Value::Bool(v) => v as u32 as f32,
+      ref v => panic!("Can't convert {v:?}"),
+    }),
+    (ScalarKind::Sint, true) => Value::I32(match *v {
+      Value::F32(v) => v as i32,
+      Value::I32(v) => v,
+      Value::U32(v) => v as i32,
+      Value::Bool(v) => v as i32,
+      ref v => panic!("Can't convert {v:?}"),
+    }),
+    (ScalarKind::Uint, true) => Value::U32(match *v {
+      Value::F32(v) => v as u32,
+      Value::I32(v) => v as u32,
+      Value::U32(v) => v,
+      Value::Bool(v) => v as u32,
+      ref v => panic!("Can't convert {v:?}"),
+    }),
+    (ScalarKind::Bool, true) => Value::Bool(match *v {
+      Value::F32(v) => v != 0.0,
+      Value::I32(v) => v != 0,
+      Value::U32(v) => v != 0,
+      Value::Bool(v) => v,
+      ref v => panic!("Can't convert {v:?}"),
+    }),
+    // Bitcasts.
+    (ScalarKind::Float, false) => Value::F32(match *v {
+      Value::I32(v) => f32::from_bits(v as u32),
+      Value::U32(v) => f32::from_bits(v),
+      Value::F32(v) => v,
+      ref v => panic!("Can't bitcast {v:?}"),
+    }),
+    (ScalarKind::Uint, false) => Value::U32(match *v {
+      Value::F32(v) => v.to_bits(),
+      Value::I32(v) => v as u32,
+      Value::U32(v) => v,
+      ref v => panic!("Can't bitcast {v:?}"),
+    }),
+    (ScalarKind::Sint, false) => Value::I32(match *v {
+      Value::F32(v) => v.to_bits() as i32,
+      Value::I32(v) => v,
+      Value::U32(v) => v as i32,
+      ref v => panic!("Can't bitcast {v:?}"),
+    }),
+    (kind, _) => panic!("Unsupported cast to {kind:?}"),
+  }
+}
+
+/// Apply `f` to every scalar component.
+fn map1(a: &Value, f: &dyn Fn(&Value) -> Value) -> Value {
+  match a {
+    Value::Composite(c) => Value::Composite(c.iter().map(|v| map1(v, f)).collect()),
+    v => f(v),
+  }
+}
+
+/// Apply `f` component-wise, broadcasting scalars.
+fn map2(a: &Value, b: &Value, f: &dyn Fn(&Value, &Value) -> Value) -> Value {
+  match (a, b) {
+    (Value::Composite(a), Value::Composite(b)) => {
+      Value::Composite(a.iter().zip(b).map(|(a, b)| map2(a, b, f)).collect())
+    }
+    (Value::Composite(a), b) => Value::Composite(a.iter().map(|a| map2(a, b, f)).collect()),
+    (a, Value::Composite(b)) => Value::Composite(b.iter().map(|b| map2(a, b, f)).collect()),
+    (a, b) => f(a, b),
+  }
+}
+
+fn map3(a: &Value, b: &Value, c: &Value, f: &dyn Fn(f32, f32, f32) -> f32) -> Value {
+  let n = [a, b, c]
+    .iter()
+    .find_map(|v| match v {
+      Value::Composite(c) => Some(c.len()),
+      _ => None,
+    });
+  let get = |v: &Value, i: usize| match v {
+    Value::Composite(c) => c[i].as_f32(),
+    v => v.as_f32(),
+  };
+  match n {
+    Some(n) => Value::Composite(
+      (0..n)
+        .map(|i| Value::F32(f(get(a, i), get(b, i), get(c, i))))
+        .collect(),
+    ),
+    None => Value::F32(f(a.as_f32(), b.as_f32(), c.as_f32())),
+  }
+}
+
+fn f32_fn(f: fn(f32) -> f32) -> impl Fn(&Value) -> Value {
+  move |v| Value::F32(f(v.as_f32()))
+}
+
+fn unary(op: UnaryOperator, v: Value) -> Value {
+  map1(&v, &|v| match (op, v) {
+    (UnaryOperator::Negate, Value::F32(v)) => Value::F32(-v),
+    (UnaryOperator::Negate, Value::I32(v)) => Value::I32(v.wrapping_neg()),
+    (UnaryOperator::LogicalNot, Value::Bool(v)) => Value::Bool(!v),
+    (UnaryOperator::BitwiseNot, Value::I32(v)) => Value::I32(!v),
+    (UnaryOperator::BitwiseNot, Value::U32(v)) => Value::U32(!v),
+    (op, v) => panic!("Unsupported unary op {op:?} on {v:?}"),
+  })
+}
+
+fn dot(a: &Value, b: &Value) -> f32 {
+  a.components()
+    .iter()
+    .zip(b.components())
+    .map(|(a, b)| a.as_f32() * b.as_f32())
+    .fold(0.0, |sum, v| sum + v)
+}
+
+fn binary(op: BinaryOperator, left: Value, right: Value) -> Value {
+  if op == BinaryOperator::Multiply && (left.is_matrix() || right.is_matrix()) {
+    return match (left.is_matrix(), right.is_matrix(), &right) {
+      // matrix * matrix
+      (true, true, _) => Value::Composite(
+        right
+          .components()
+          .iter()
+          .map(|col| binary(op, left.clone(), col.clone()))
+          .collect(),
+      ),
+      // matrix * vector
+      (true, false, Value::Composite(v)) => left
+        .components()
+        .iter()
+        .zip(v)
+        .map(|(col, s)| map2(col, s, &|a, b| scalar_binary(op, a, b)))
+        .reduce(|sum, col| map2(&sum, &col, &|a, b| scalar_binary(BinaryOperator::Add, a, b)))
+        .unwrap(),
+      // vector * matrix
+      (false, true, _) if matches!(left, Value::Composite(_)) => Value::Composite(
+        right
+          .components()
+          .iter()
+          .map(|col| Value::F32(dot(&left, col)))
+          .collect(),
+      ),
+      // matrix * scalar
+      _ => map2(&left, &right, &|a, b| scalar_binary(op, a, b)),
+    };
+  }
+  map2(&left, &right, &|a, b| scalar_binary(op, a, b))
+}
+
+fn scalar_binary(op: BinaryOperator, a: &Value, b: &Value) -> Value {
+  use BinaryOperator as B;
+  match (a, b) {
+    (Value::F32(a), Value::F32(b)) => {
+      let (a, b) = (*a, *b);
+      match op {
+        B::Add => Value::F32(a + b),
+        B::Subtract => Value::F32(a - b),
+        B::Multiply => Value::F32(a * b),
+        B::Divide => Value::F32(a / b),
+        B::Modulo => Value::F32(a % b),
+        B::Equal => Value::Bool(a == b),
+        B::NotEqual => Value::Bool(a != b),
+        B::Less => Value::Bool(a < b),
+        B::LessEqual => Value::Bool(a <= b),
+        B::Greater => Value::Bool(a > b),
+        B::GreaterEqual => Value::Bool(a >= b),
+        op => panic!("Unsupported f32 op: {op:?}"),
+      }
+    }
+    (Value::I32(a), Value::I32(b)) => {
+      let (a, b) = (*a, *b);
+      match op {
+        B::Add => Value::I32(a.wrapping_add(b)),
+        B::Subtract => Value::I32(a.wrapping_sub(b)),
+        B::Multiply => Value::I32(a.wrapping_mul(b)),
+        B::Divide => Value::I32(a / b),
+        B::Modulo => Value::I32(a % b),
+        B::Equal => Value::Bool(a == b),
+        B::NotEqual => Value::Bool(a != b),
+        B::Less => Value::Bool(a < b),
+        B::LessEqual => Value::Bool(a <= b),
+        B::Greater => Value::Bool(a > b),
+        B::GreaterEqual => Value::Bool(a >= b),
+        B::And => Value::I32(a & b),
+        B::InclusiveOr => Value::I32(a | b),
+        B::ExclusiveOr => Value::I32(a ^ b),
+        op => panic!("Unsupported i32 op: {op:?}"),
+      }
+    }
+    (Value::U32(a), Value::U32(b)) => {
+      let (a, b) = (*a, *b);
+      match op {
+        B::Add => Value::U32(a.wrapping_add(b)),
+        B::Subtract => Value::U32(a.wrapping_sub(b)),
+        B::Multiply => Value::U32(a.wrapping_mul(b)),
+        B::Divide => Value::U32(a / b),
+        B::Modulo => Value::U32(a % b),
+        B::Equal => Value::Bool(a == b),
+        B::NotEqual => Value::Bool(a != b),
+        B::Less => Value::Bool(a < b),
+        B::LessEqual => Value::Bool(a <= b),
+        B::Greater => Value::Bool(a > b),
+        B::GreaterEqual => Value::Bool(a >= b),
+        B::And => Value::U32(a & b),
+        B::InclusiveOr => Value::U32(a | b),
+        B::ExclusiveOr => Value::U32(a ^ b),
+        B::ShiftLeft => Value::U32(a << b),
+        B::ShiftRight => Value::U32(a >> b),
+        op => panic!("Unsupported u32 op: {op:?}"),
+      }
+    }
+    (Value::I32(a), Value::U32(b)) if matches!(op, B::ShiftLeft | B::ShiftRight) => match op {
+      B::ShiftLeft => Value::I32(a << b),
+      _ => Value::I32(a >> b),
+    },
+    (Value::Bool(a), Value::Bool(b)) => Value::Bool(match op {
+      B::LogicalAnd | B::And => *a && *b,
+      B::LogicalOr | B::InclusiveOr => *a || *b,
+      B::Equal => a == b,
+      B::NotEqual => a != b,
+      op => panic!("Unsupported bool op: {op:?}"),
+    }),
+    (a, b) => panic!("Unsupported operands for {op:?}: {a:?}, {b:?}"),
+  }
+}
+
+fn select(condition: &Value, accept: Value, reject: Value) -> Value {
+  match condition {
+    Value::Bool(true) => accept,
+    Value::Bool(false) => reject,
+    Value::Composite(c) => Value::Composite(
+      c.iter()
+        .zip(accept.components().iter().zip(reject.components()))
+        .map(|(c, (a, r))| select(c, a.clone(), r.clone()))
+        .collect(),
+    ),
+    v => panic!("Invalid select condition: {v:?}"),
+  }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+  let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+  t * t * (3.0 - 2.0 * t)
+}
+
+fn math(fun: MathFunction, arg: Value, arg1: Option<Value>, arg2: Option<Value>) -> Value {
+  use MathFunction as M;
+  let arg1 = || arg1.clone().expect("Missing argument");
+  let arg2 = || arg2.clone().expect("Missing argument");
+  match fun {
+    M::Abs => map1(&arg, &|v| match v {
+      Value::F32(v) => Value::F32(v.abs()),
+      Value::I32(v) => Value::I32(v.wrapping_abs()),
+      v => v.clone(),
+    }),
+    M::Min => map2(&arg, &arg1(), &|a, b| match (a, b) {
+      (Value::F32(a), Value::F32(b)) => Value::F32(a.min(*b)),
+      (Value::I32(a), Value::I32(b)) => Value::I32(*a.min(b)),
+      (Value::U32(a), Value::U32(b)) => Value::U32(*a.min(b)),
+      (a, b) => panic!("Invalid min({a:?}, {b:?})"),
+    }),
+    M::Max => map2(&arg, &arg1(), &|a, b| match (a, b) {
+      (Value::F32(a), Value::F32(b)) => Value::F32(a.max(*b)),
+      (Value::I32(a), Value::I32(b)) => Value::I32(*a.max(b)),
+      (Value::U32(a), Value::U32(b)) => Value::U32(*a.max(b)),
+      (a, b) => panic!("Invalid max({a:?}, {b:?})"),
+    }),
+    M::Clamp => map3(&arg, &arg1(), &arg2(), &|x, lo, hi| x.max(lo).min(hi)),
+    M::Saturate => map1(&arg, &f32_fn(|v| v.clamp(0.0, 1.0))),
+    M::Cos => map1(&arg, &f32_fn(f32::cos)),
+    M::Sin => map1(&arg, &f32_fn(f32::sin)),
+    M::Tan => map1(&arg, &f32_fn(f32::tan)),
+    M::Tanh => map1(&arg, &f32_fn(f32::tanh)),
+    M::Acos => map1(&arg, &f32_fn(f32::acos)),
+    M::Asin => map1(&arg, &f32_fn(f32::asin)),
+    M::Atan => map1(&arg, &f32_fn(f32::atan)),
+    M::Atan2 => map2(&arg, &arg1(), &|a, b| Value::F32(a.as_f32().atan2(b.as_f32()))),
+    M::Radians => map1(&arg, &f32_fn(f32::to_radians)),
+    M::Degrees => map1(&arg, &f32_fn(f32::to_degrees)),
+    M::Ceil => map1(&arg, &f32_fn(f32::ceil)),
+    M::Floor => map1(&arg, &f32_fn(f32::floor)),
+    M::Round => map1(&arg, &f32_fn(f32::round_ties_even)),
+    M::Fract => map1(&arg, &f32_fn(|v| v - v.floor())),
+    M::Trunc => map1(&arg, &f32_fn(f32::trunc)),
+    M::Exp => map1(&arg, &f32_fn(f32::exp)),
+    M::Exp2 => map1(&arg, &f32_fn(f32::exp2)),
+    M::Log => map1(&arg, &f32_fn(f32::ln)),
+    M::Log2 => map1(&arg, &f32_fn(f32::log2)),
+    M::Pow => map2(&arg, &arg1(), &|a, b| Value::F32(a.as_f32().powf(b.as_f32()))),
+    M::Dot => Value::F32(dot(&arg, &arg1())),
+    M::Cross => {
+      let (a, b) = (arg.as_f32s(), arg1().as_f32s());
+      Value::Composite(vec![
+        Value::F32(a[1] * b[2] - a[2] * b[1]),
+        Value::F32(a[2] * b[0] - a[0] * b[2]),
+        Value::F32(a[0] * b[1] - a[1] * b[0]),
+      ])
+    }
+    M::Length => Value::F32(match &arg {
+      Value::Composite(_) => dot(&arg, &arg).sqrt(),
+      v => v.as_f32().abs(),
+    }),
+    M::Distance => math(
+      M::Length,
+      binary(BinaryOperator::Subtract, arg, arg1()),
+      None,
+      None,
+    ),
+    M::Normalize => {
+      let len = dot(&arg, &arg).sqrt();
+      map1(&arg, &|v| Value::F32(v.as_f32() / len))
+    }
+    M::Reflect => {
+      let n = arg1();
+      let d = Value::F32(2.0 * dot(&n, &arg));
+      binary(
+        BinaryOperator::Subtract,
+        arg,
+        binary(BinaryOperator::Multiply, d, n),
+      )
+    }
+    M::Refract => {
+      let (n, eta) = (arg1(), arg2().as_f32());
+      let n_dot_i = dot(&n, &arg);
+      let k = 1.0 - eta * eta * (1.0 - n_dot_i * n_dot_i);
+      if k < 0.0 {
+        return map1(&arg, &|_| Value::F32(0.0));
+      }
+      binary(
+        BinaryOperator::Subtract,
+        binary(BinaryOperator::Multiply, Value::F32(eta), arg),
+        binary(
+          BinaryOperator::Multiply,
+          Value::F32(eta * n_dot_i + k.sqrt()),
+          n,
+        ),
+      )
+    }
+    M::Sign => map1(&arg, &|v| match v {
+      Value::F32(v) => Value::F32(if *v > 0.0 {
+        1.0
+      } else if *v < 0.0 {
+        -1.0
+      } else {
+        0.0
+      }),
+      Value::I32(v) => Value::I32(v.signum()),
+      v => panic!("Invalid sign({v:?})"),
+    }),
+    M::Fma => map3(&arg, &arg1(), &arg2(), &|a, b, c| a.mul_add(b, c)),
+    M::Mix => map3(&arg, &arg1(), &arg2(), &|x, y, a| x * (1.0 - a) + y * a),
+    M::Step => map2(&arg, &arg1(), &|edge, x| {
+      Value::F32(if x.as_f32() < edge.as_f32() { 0.0 } else { 1.0 })
+    }),
+    M::SmoothStep => map3(&arg, &arg1(), &arg2(), &smoothstep),
+    M::Sqrt => map1(&arg, &f32_fn(f32::sqrt)),
+    M::InverseSqrt => map1(&arg, &f32_fn(|v| 1.0 / v.sqrt())),
+    fun => panic!("Unsupported math function: {fun:?}"),
+  }
+}
@@ -0,0 +1,67 @@
+//! Fixture of the physics engine adapter tests, see `rapier_buoyancy.rs` and `xpbd_buoyancy.rs`.
+use bevy::prelude::*;
+use bevy_water::{physics::ColliderBuoyancy, *};
+
+pub const WATER_HEIGHT: f32 = 1.0;
+
+/// What differs between the physics engines.
+pub trait Engine {
+  /// Add the physics engine and its buoyancy plugin.
+  fn add_plugins(app: &mut App);
+  /// Make `body` a dynamic rigid body with a 1m cube collider.
+  fn insert_cube(body: &mut EntityWorldMut);
+  /// External force applied to the body.
+  fn force(app: &App, body: Entity) -> Vec3;
+  /// External torque applied to the body.
+  fn torque(app: &App, body: Entity) -> Vec3;
+}
+
+pub fn app<E: Engine>() -> App {
+  let mut app = App::new();
+  app
+    .add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin))
+    .insert_resource(WaterSettings {
+      height: WATER_HEIGHT,
+      amplitude: 0.0,
+      ..default()
+    });
+  E::add_plugins(&mut app);
+  app
+}
+
+/// Spawn the cube at `transform`, and run until its buoyancy is applied.
+pub fn float_cube<E: Engine>(app: &mut App, transform: Transform) -> Entity {
+  let mut body = app.world.spawn((
+    TransformBundle::from_transform(transform),
+    ColliderBuoyancy::default(),
+  ));
+  E::insert_cube(&mut body);
+  let body = body.id();
+  // Add the buoyancy, then apply it.
+  app.update();
+  app.update();
+  body
+}
+
+pub fn submerged_body_is_pushed_up<E: Engine>() {
+  let mut app = app::<E>();
+  let body = float_cube::<E>(&mut app, Transform::from_xyz(0.0, WATER_HEIGHT - 3.0, 0.0));
+  let force = E::force(&app, body);
+  let settings = app.world.resource::<BuoyancySettings>();
+  // The weight of the displaced water of the 1m cube.
+  let expected = settings.water_density * 9.81;
+  assert!(
+    force.y > expected * 0.9 && force.y < expected * 1.1,
+    "Buoyant force {force}, expected {expected}"
+  );
+  // Pushed up through the centre of mass.
+  let torque = E::torque(&app, body);
+  assert!(torque.length() < expected * 1e-3, "Torque {torque}");
+}
+
+pub fn body_above_the_water_has_no_force<E: Engine>() {
+  let mut app = app::<E>();
+  let body = float_cube::<E>(&mut app, Transform::from_xyz(0.0, WATER_HEIGHT + 3.0, 0.0));
+  assert_eq!(E::force(&app, body), Vec3::ZERO);
+  assert_eq!(E::torque(&app, body), Vec3::ZERO);
+}
@@ -0,0 +1,196 @@
+//! Baked water depth maps.
+mod common;
+
+use bevy::{ecs::system::SystemState, prelude::*};
+use bevy_water::{material::WaterMaterialUniform, *};
+use common::*;
+
+const ENTRY: &str = r#"
+#import bevy_water::water_functions as water_fn
+
+fn eval_depth_wave_scale(p: vec2<f32>) -> f32 {
+  return water_fn::depth_wave_scale(p);
+}
+
+@compute @workgroup_size(1)
+fn main() {}
+"#;
+
+/// A beach sloping down 1 meter per meter towards +X, dry land at x < 2.
+fn beach(p: Vec2) -> f32 {
+  2.0 - p.x
+}
+
+#[test]
+fn heightfield_depth_is_normalized() {
+  let mut images = Assets::<Image>::default();
+  let map = WaterDepthMap::from_heightfield(
+    Rect::new(0.0, 0.0, 8.0, 2.0),
+    UVec2::new(8, 2),
+    0.0,
+    4.0,
+    beach,
+    &mut images,
+  );
+  let image = images.get(&map.image).unwrap();
+  // Texel centres at x = 0.5, 1.5, ... 7.5.
+  let row = &image.data[..8];
+  assert_eq!(row[..2], [0, 0], "dry land");
+  assert_eq!(row[2], 32, "0.5 meters deep");
+  assert_eq!(row[5], 223, "3.5 meters deep");
+  assert_eq!(row[6..], [255, 255], "clamped at the max depth");
+  assert_eq!(image.data[8..], image.data[..8]);
+}
+
+#[test]
+fn shader_damps_the_waves_over_the_beach() {
+  let mut images = Assets::<Image>::default();
+  let rect = Rect::new(0.0, 0.0, 16.0, 2.0);
+  let map = WaterDepthMap::from_heightfield(rect, UVec2::new(32, 2), 0.0, 8.0, beach, &mut images);
+  let body = WaterBody {
+    depth_map: Some(map.clone()),
+    ..default()
+  };
+  let uniform = WaterMaterialUniform::from(body.material(rect).extension);
+
+  let mut shader = Interpreter::new(compose_water_shader_with_defs(ENTRY, &["WATER_DEPTH_MAP"]));
+  let image = images.get(&map.image).unwrap();
+  let mut texture = Texture::new(image.width(), image.height());
+  for (texel, depth) in texture.texels.iter_mut().zip(&image.data) {
+    *texel = [*depth as f32 / 255.0, 0.0, 0.0, 1.0];
+  }
+  shader.set_global("depth_map", Value::Texture(texture));
+  shader.set_global(
+    "depth_map_sampler",
+    Value::Sampler(Sampler {
+      linear: true,
+      repeat: false,
+    }),
+  );
+  let r = uniform.depth_map_rect;
+  shader.set_global_member("material", "depth_map_rect", Value::vec4(r.x, r.y, r.z, r.w));
+  for (member, value) in [
+    ("depth_map_max_depth", uniform.depth_map_max_depth),
+    ("wave_damping_depth", uniform.wave_damping_depth),
+    ("wave_shoaling", uniform.wave_shoaling),
+  ] {
+    shader.set_global_member("material", member, Value::F32(value));
+  }
+
+  let scale = |x: f32| {
+    shader
+      .call("eval_depth_wave_scale", vec![Value::vec2(x, 1.0)])
+      .as_f32()
+  };
+  assert_eq!(scale(1.0), 0.0, "no waves on dry land");
+  assert_eq!(scale(30.0), 1.0, "open water outside the map");
+  for x in [2.5, 3.0, 4.0, 5.0, 7.0, 12.0] {
+    let depth = map.depth(&images, Vec2::new(x, 1.0)).unwrap();
+    let expected = map.wave_scale(depth);
+    assert!(
+      (scale(x) - expected).abs() < 0.02,
+      "at x = {x} ({depth} m deep): shader {}, cpu {expected}",
+      scale(x)
+    );
+  }
+  // Calm in the shallows, shoaling towards the damping depth, unchanged in deep water.
+  assert!(scale(2.5) < 0.5);
+  assert!(scale(4.0) > 1.0);
+  assert_eq!(scale(12.0), 1.0);
+}
+
+#[test]
+fn meshes_are_rasterized_from_above() {
+  let mut images = Assets::<Image>::default();
+  // Top at y = -1 over x, z = 0..2.
+  let mesh = Mesh::from(Cuboid::new(2.0, 2.0, 2.0));
+  let transform = GlobalTransform::from_xyz(1.0, -2.0, 1.0);
+  let map = WaterDepthMap::from_meshes(
+    Rect::new(0.0, 0.0, 4.0, 4.0),
+    UVec2::splat(4),
+    0.0,
+    4.0,
+    [(&mesh, &transform)],
+    &mut images,
+  );
+  let image = images.get(&map.image).unwrap();
+  assert_eq!(image.data[..4], [64, 64, 255, 255]);
+  assert_eq!(image.data[4..8], [64, 64, 255, 255]);
+  assert!(image.data[8..].iter().all(|d| *d == 255));
+
+  let depth = |p: Vec2| map.depth(&images, p).unwrap();
+  assert!((depth(Vec2::new(1.0, 1.0)) - 1.0).abs() < 0.01);
+  assert!((depth(Vec2::new(2.0, 0.5)) - 2.5).abs() < 0.01, "bilinear");
+  assert_eq!(depth(Vec2::new(-5.0, 0.0)), 4.0, "deep outside the map");
+}
+
+#[test]
+fn bridges_keep_the_ground_under_the_water() {
+  let mut images = Assets::<Image>::default();
+  // River bed 2 meters deep over x, z = 0..4, a bridge above the water over x = 0..2 and an
+  // island over z = 3..4.
+  let bed = Mesh::from(Cuboid::new(4.0, 1.0, 4.0));
+  let bed_transform = GlobalTransform::from_xyz(2.0, -2.5, 2.0);
+  let bridge = Mesh::from(Cuboid::new(2.0, 0.5, 4.0));
+  let bridge_transform = GlobalTransform::from_xyz(1.0, 1.0, 2.0);
+  let island = Mesh::from(Cuboid::new(4.0, 3.0, 1.0));
+  let island_transform = GlobalTransform::from_xyz(2.0, -1.0, 3.5);
+  let map = WaterDepthMap::from_meshes(
+    Rect::new(0.0, 0.0, 4.0, 4.0),
+    UVec2::splat(4),
+    0.0,
+    4.0,
+    [
+      (&bed, &bed_transform),
+      (&bridge, &bridge_transform),
+      (&island, &island_transform),
+    ],
+    &mut images,
+  );
+  let image = images.get(&map.image).unwrap();
+  assert_eq!(image.data[..4], [128, 128, 128, 128], "under the bridge");
+  assert_eq!(image.data[12..], [0, 0, 0, 0], "dry island");
+}
+
+#[test]
+fn waves_shoal_then_calm_down_in_shallow_water() {
+  let map = WaterDepthMap::new(Handle::default(), Rect::new(0.0, 0.0, 1.0, 1.0), 4.0);
+  let scale = |depth| map.wave_scale(depth);
+  assert_eq!(scale(0.0), 0.0);
+  assert!((scale(0.5) - 0.65).abs() < 1e-5);
+  assert!(
+    (scale(1.0) - 1.3).abs() < 1e-5,
+    "highest at the damping depth"
+  );
+  assert!((scale(1.5) - 1.15).abs() < 1e-5);
+  assert_eq!(scale(3.0), 1.0);
+}
+
+#[test]
+fn water_param_reports_the_water_depth() {
+  let mut world = World::new();
+  world.init_resource::<Time>();
+  let mut images = Assets::<Image>::default();
+  let depth_map = WaterDepthMap::from_heightfield(
+    Rect::new(0.0, 0.0, 8.0, 2.0),
+    UVec2::new(8, 2),
+    0.0,
+    4.0,
+    beach,
+    &mut images,
+  );
+  world.insert_resource(images);
+  world.insert_resource(WaterSettings {
+    height: 0.0,
+    amplitude: 0.0,
+    depth_map: Some(depth_map),
+    ..default()
+  });
+
+  let mut state = SystemState::<WaterParam>::new(&mut world);
+  let water = state.get(&world);
+  let depth = |x: f32| water.water_depth(Vec3::new(x, 0.0, 1.0)).unwrap();
+  assert_eq!(depth(0.5), 0.0, "dry land");
+  assert!((depth(3.5) - 1.5).abs() < 0.02);
+  assert!((depth(5.5) - 3.5).abs() < 0.02);
+}
@@ -0,0 +1,136 @@
+//! Checks the CPU reference of the FFT ocean used by `WaterParam`.
+mod common;
+
+use bevy::prelude::*;
+use bevy_water::fft::{FftOcean, FftOceanState};
+use common::*;
+
+fn ocean() -> FftOcean {
+  FftOcean {
+    size: 32,
+    patch_size: 100.0,
+    ..default()
+  }
+}
+
+#[test]
+fn fft_ocean_tiles_seamlessly() {
+  let ocean = ocean();
+  let mut state = FftOceanState::new(&ocean);
+  state.update(3.0);
+  for i in 0..20 {
+    let p = Vec2::new(i as f32 * 4.7, i as f32 * -3.1);
+    let offset = p + Vec2::new(ocean.patch_size, -2.0 * ocean.patch_size);
+    assert!(state.displacement(p).abs_diff_eq(state.displacement(offset), 1e-4));
+  }
+}
+
+#[test]
+fn fft_ocean_has_waves() {
+  let mut state = FftOceanState::new(&ocean());
+  state.update(3.0);
+  let max = (0..64)
+    .map(|i| state.displacement(Vec2::new(i as f32 * 1.3, i as f32 * 0.7)).y.abs())
+    .fold(0.0, f32::max);
+  assert!(max > 0.01, "FFT ocean is flat: {max}");
+}
+
+#[test]
+fn fft_sizes_are_rounded_to_a_power_of_two() {
+  let odd = FftOcean {
+    size: 100,
+    ..ocean()
+  };
+  assert_eq!(odd.simulation_size(), 128);
+  assert_eq!(FftOcean { size: 0, ..ocean() }.simulation_size(), 16);
+  let mut state = FftOceanState::new(&odd);
+  state.update(3.0);
+  let mut rounded = FftOceanState::new(&FftOcean { size: 128, ..ocean() });
+  rounded.update(3.0);
+  for i in 0..20 {
+    let p = Vec2::new(i as f32 * 4.7, i as f32 * -3.1);
+    assert!(state.height(1.0, p).is_finite());
+    assert_eq!(state.displacement(p), rounded.displacement(p));
+  }
+}
+
+#[test]
+fn fft_height_follows_displaced_surface() {
+  let mut state = FftOceanState::new(&ocean());
+  state.update(11.0);
+  for i in 0..20 {
+    let p = Vec2::new(i as f32 * 4.7, i as f32 * 2.3);
+    let d = state.displacement(p);
+    let surface = p + Vec2::new(d.x, d.z);
+    let height = state.height(1.0, surface);
+    assert!((height - d.y).abs() < 0.05, "at {surface}: {height} != {}", d.y);
+  }
+}
+
+/// Run the `spectrum`, `fft` and `resolve` passes of `fft_ocean.wgsl` like `FftOceanNode`.
+fn gpu_displacement(ocean: &FftOcean, time: f32) -> Texture {
+  let n = ocean.size;
+  let mut shader = Interpreter::new(compose_water_shader(include_str!(
+    "../assets/shaders/fft_ocean.wgsl"
+  )));
+  shader.set_global_member("ocean", "time", Value::F32(time));
+  shader.set_global_member("ocean", "choppiness", Value::F32(ocean.choppiness));
+  shader.set_global_member("ocean", "patch_size", Value::F32(ocean.patch_size));
+  shader.set_global_member("ocean", "size", Value::U32(n));
+  let mut h0 = Texture::new(n, n);
+  for (texel, [h0, h0_minus]) in h0.texels.iter_mut().zip(ocean.initial_spectrum()) {
+    *texel = [h0.x, h0.y, h0_minus.x, h0_minus.y];
+  }
+  shader.set_global("h0_texture", Value::Texture(h0));
+  let buffer = Value::Composite(vec![Value::vec4(0.0, 0.0, 0.0, 0.0); (n * n) as usize]);
+  for global in ["src", "dst", "foam"] {
+    shader.set_global(global, buffer.clone());
+  }
+  for texture in ["displacement_texture", "derivatives_texture", "foam_texture"] {
+    shader.set_global(texture, Value::Texture(Texture::new(n, n)));
+  }
+  // Every pass reads the output of the previous one.
+  let swap = |shader: &mut Interpreter| {
+    let dst = shader.global("dst").clone();
+    shader.set_global("src", dst);
+  };
+
+  shader.dispatch("spectrum", (n, n));
+  for direction in 0..2 {
+    for i in 0..n.trailing_zeros() {
+      swap(&mut shader);
+      shader.set_global_member("stage", "ns", Value::U32(1 << i));
+      shader.set_global_member("stage", "direction", Value::U32(direction));
+      shader.dispatch("fft", (n / 2, n));
+    }
+  }
+  swap(&mut shader);
+  shader.dispatch("resolve", (n, n));
+  shader.global("displacement_texture").as_texture().clone()
+}
+
+#[test]
+fn fft_shader_matches_cpu_reference() {
+  let ocean = FftOcean {
+    size: 16,
+    ..ocean()
+  };
+  let time = 5.5;
+  let mut state = FftOceanState::new(&ocean);
+  state.update(time);
+  let gpu = gpu_displacement(&ocean, time);
+  let texel_size = ocean.patch_size / ocean.size as f32;
+  let max = state.max_displacement().max_element();
+  assert!(max > 0.01, "FFT ocean is flat: {max}");
+  for y in 0..ocean.size {
+    for x in 0..ocean.size {
+      let cpu = state.displacement(Vec2::new(x as f32, y as f32) * texel_size);
+      let [dx, dy, dz, _] = gpu.get(x, y);
+      let gpu = Vec3::new(dx, dy, dz);
+      assert!(
+        gpu.abs_diff_eq(cpu, 1e-3 * max),
+        "FFT diverged at ({x}, {y}): shader {gpu}, cpu {cpu}",
+      );
+    }
+  }
+}
@@ -0,0 +1,202 @@
+//! River flow maps.
+mod common;
+
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemState, prelude::*, time::TimeUpdateStrategy};
+use bevy_water::{material::WaterMaterialUniform, *};
+use common::*;
+
+/// A river flowing towards +X, faster in the middle (z = 0).
+fn river(p: Vec2) -> Vec2 {
+  Vec2::new(2.0 - p.y.abs(), 0.0)
+}
+
+fn river_map(images: &mut Assets<Image>) -> WaterFlowMap {
+  WaterFlowMap::from_fn(
+    Rect::new(-10.0, -2.0, 10.0, 2.0),
+    UVec2::new(4, 8),
+    2.0,
+    river,
+    images,
+  )
+}
+
+#[test]
+fn flow_velocity_round_trips_through_the_image() {
+  let mut images = Assets::<Image>::default();
+  let map = river_map(&mut images);
+  let velocity = |x: f32, z: f32| map.velocity(&images, Vec2::new(x, z)).unwrap();
+  assert!((velocity(0.0, 0.25) - Vec2::new(1.75, 0.0)).length() < 0.02);
+  assert!((velocity(5.0, -1.25) - Vec2::new(0.75, 0.0)).length() < 0.02);
+  assert_eq!(velocity(0.0, 5.0), Vec2::ZERO, "still outside the map");
+}
+
+#[test]
+fn phases_blend_before_jumping_back() {
+  let map = WaterFlowMap::new(Handle::default(), Rect::new(0.0, 0.0, 1.0, 1.0), 1.0);
+  let velocity = Vec2::new(1.0, 0.0);
+  let phases = map.phases(velocity, 0.0);
+  assert_eq!(phases.offsets, [Vec2::ZERO, Vec2::new(-1.0, 0.0)]);
+  assert_eq!(phases.blend, 1.0, "only the second phase at its reset");
+
+  let phases = map.phases(velocity, 1.0);
+  assert_eq!(phases.offsets, [Vec2::new(-1.0, 0.0), Vec2::ZERO]);
+  assert_eq!(
+    phases.blend, 0.0,
+    "only the first phase at the reset of the second"
+  );
+}
+
+const ENTRY: &str = r#"
+#import bevy_water::water_functions as water_fn
+
+fn eval_flow_phases(coord: vec2<f32>, p: vec2<f32>) -> vec4<f32> {
+  let phases = water_fn::flow_phases(coord, p);
+  return vec4<f32>(phases.coord_a - coord, phases.coord_b - coord);
+}
+
+fn eval_flow_blend(p: vec2<f32>) -> f32 {
+  return water_fn::flow_phases(vec2<f32>(0.0), p).blend;
+}
+
+@compute @workgroup_size(1)
+fn main() {}
+"#;
+
+#[test]
+fn shader_blends_the_flow_phases() {
+  let mut images = Assets::<Image>::default();
+  let map = river_map(&mut images);
+  let body = WaterBody {
+    flow_map: Some(map.clone()),
+    ..default()
+  };
+  let uniform = WaterMaterialUniform::from(body.material(map.rect).extension);
+
+  let mut shader = Interpreter::new(compose_water_shader_with_defs(ENTRY, &["WATER_FLOW_MAP"]));
+  let image = images.get(&map.image).unwrap();
+  let mut texture = Texture::new(image.width(), image.height());
+  for (texel, velocity) in texture.texels.iter_mut().zip(image.data.chunks_exact(2)) {
+    let decode = |byte: u8| (byte as i8 as f32 / 127.0).max(-1.0);
+    *texel = [decode(velocity[0]), decode(velocity[1]), 0.0, 1.0];
+  }
+  shader.set_global("flow_map", Value::Texture(texture));
+  shader.set_global(
+    "flow_map_sampler",
+    Value::Sampler(Sampler {
+      linear: true,
+      repeat: false,
+    }),
+  );
+  let r = uniform.flow_map_rect;
+  shader.set_global_member("material", "flow_map_rect", Value::vec4(r.x, r.y, r.z, r.w));
+  shader.set_global_member("material", "flow_max_speed", Value::F32(uniform.flow_max_speed));
+  shader.set_global_member("material", "flow_cycle", Value::F32(uniform.flow_cycle));
+
+  let p = Vec2::new(1.0, 0.25);
+  let velocity = map.velocity(&images, p).unwrap();
+  let mut last: Option<FlowPhases> = None;
+  for step in 0..=80 {
+    let time = step as f32 * 0.05;
+    shader.set_global_member("globals", "time", Value::F32(time));
+    let offsets = shader
+      .call("eval_flow_phases", vec![Value::vec2(3.0, -1.0), Value::vec2(p.x, p.y)])
+      .as_f32s();
+    let blend = shader
+      .call("eval_flow_blend", vec![Value::vec2(p.x, p.y)])
+      .as_f32();
+    let phases = FlowPhases {
+      offsets: [
+        Vec2::new(offsets[0], offsets[1]),
+        Vec2::new(offsets[2], offsets[3]),
+      ],
+      blend,
+    };
+    let cpu = map.phases(velocity, time);
+    for (gpu, cpu) in phases.offsets.iter().zip(cpu.offsets) {
+      assert!(gpu.abs_diff_eq(cpu, 0.02), "at {time}: shader {gpu}, cpu {cpu}");
+    }
+    assert!((phases.blend - cpu.blend).abs() < 1e-4);
+
+    // A phase only jumps back while it is hidden.
+    if let Some(last) = last {
+      let weights = |phases: &FlowPhases| [1.0 - phases.blend, phases.blend];
+      for i in 0..2 {
+        let jump = (phases.offsets[i] - last.offsets[i]).length();
+        if jump > velocity.length() {
+          assert!(weights(&last)[i] < 0.1 && weights(&phases)[i] < 0.1, "phase {i} pops at {time}");
+        }
+      }
+      assert!((phases.blend - last.blend).abs() <= 0.05 + 1e-4, "the blend jumps at {time}");
+    }
+    last = Some(phases);
+  }
+}
+
+#[test]
+fn water_param_reports_the_flow_velocity() {
+  let mut world = World::new();
+  world.init_resource::<Time>();
+  let mut images = Assets::<Image>::default();
+  let flow_map = river_map(&mut images);
+  world.insert_resource(images);
+  world.insert_resource(WaterSettings {
+    flow_map: Some(flow_map),
+    ..default()
+  });
+
+  let mut state = SystemState::<WaterParam>::new(&mut world);
+  let water = state.get(&world);
+  let velocity = water.flow_velocity(Vec3::new(0.0, 1.0, 0.25));
+  assert!((velocity - Vec3::new(1.75, 0.0, 0.0)).length() < 0.02);
+  assert_eq!(water.flow_velocity(Vec3::new(20.0, 1.0, 0.0)), Vec3::ZERO);
+}
+
+#[test]
+fn floating_bodies_drift_with_the_flow() {
+  let mut images = Assets::<Image>::default();
+  let flow_map = river_map(&mut images);
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+      1.0 / 30.0,
+    )))
+    .insert_resource(images)
+    .insert_resource(WaterSettings {
+      height: 1.0,
+      amplitude: 0.0,
+      flow_map: Some(flow_map),
+      ..default()
+    })
+    .add_plugins(BuoyancyPlugin);
+  let density = app.world.resource::<BuoyancySettings>().water_density;
+  let buoyancy = Buoyancy::from_hull(
+    Vec3::ZERO,
+    Vec3::splat(0.25),
+    UVec3::splat(2),
+    density / 2.0,
+  );
+  let body = app
+    .world
+    .spawn((
+      TransformBundle::from_transform(Transform::from_xyz(-5.0, 1.0, 0.0)),
+      BuoyancyBundle {
+        buoyancy,
+        ..default()
+      },
+    ))
+    .id();
+  for _ in 0..60 {
+    app.update();
+  }
+
+  let transform = app.world.get::<Transform>(body).unwrap();
+  assert!(
+    transform.translation.x > -4.0,
+    "Body stays at {}",
+    transform.translation
+  );
+  assert!(transform.translation.z.abs() < 0.1);
+}
@@ -0,0 +1,182 @@
+//! Checks the wave folding that places the foam, and how the FFT ocean foam fades.
+mod common;
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use bevy_water::{material::Foam, WaveModel, MAX_GERSTNER_WAVES};
+use common::*;
+
+const ENTRY: &str = r#"
+#import bevy_water::water_foam
+#import bevy_water::water_functions as water_fn
+
+fn eval_gerstner_folding(p: vec2<f32>) -> f32 {
+  return water_foam::gerstner_folding(p, 0.0);
+}
+
+fn eval_noise_folding(p: vec2<f32>) -> f32 {
+  return water_foam::noise_folding(p, 0.0);
+}
+
+fn eval_gerstner_displacement(p: vec2<f32>) -> vec3<f32> {
+  return water_fn::get_gerstner_displacement(p);
+}
+
+@compute @workgroup_size(1)
+fn main() {}
+"#;
+
+fn shader(amplitude: f32) -> Interpreter {
+  let mut shader = Interpreter::new(compose_water_shader(ENTRY));
+  shader.set_global_member("globals", "time", Value::F32(0.0));
+  shader.set_global_member("material", "amplitude", Value::F32(amplitude));
+  shader.set_global_member("material", "noise_rotation", Value::vec2(1.0, 0.0));
+  shader.set_global_member("material", "noise_scale", Value::F32(1.0));
+  let waves = WaveModel::gerstner_swell().gerstner_waves().to_vec();
+  let mut packed: Vec<Value> = waves
+    .iter()
+    .map(|wave| {
+      let k = wave.wave_vector();
+      Value::vec4(k.x, k.y, wave.steepness, wave.speed)
+    })
+    .collect();
+  packed.resize(MAX_GERSTNER_WAVES, Value::vec4(0.0, 0.0, 0.0, 0.0));
+  shader.set_global_member("material", "wave_count", Value::U32(waves.len() as u32));
+  shader.set_global_member("material", "waves", Value::Composite(packed));
+  shader
+}
+
+fn sample_points() -> impl Iterator<Item = Vec2> {
+  (-5..=5).flat_map(|x| (-5..=5).map(move |z| Vec2::new(x as f32 * 3.7, z as f32 * 5.3 - 0.41)))
+}
+
+#[test]
+fn gerstner_folding_is_the_jacobian_of_the_displacement() {
+  let shader = shader(1.0);
+  let displacement = |p: Vec2| {
+    let d = shader.call("eval_gerstner_displacement", vec![Value::vec2(p.x, p.y)]);
+    let d = d.as_f32s();
+    Vec2::new(d[0], d[2])
+  };
+  let delta = 0.01;
+  for p in sample_points() {
+    let ddx = (displacement(p + Vec2::X * delta) - displacement(p - Vec2::X * delta)) / (2.0 * delta);
+    let ddz = (displacement(p + Vec2::Y * delta) - displacement(p - Vec2::Y * delta)) / (2.0 * delta);
+    let jacobian = (1.0 + ddx.x) * (1.0 + ddz.y) - ddz.x * ddx.y;
+    let folding = shader
+      .call("eval_gerstner_folding", vec![Value::vec2(p.x, p.y)])
+      .as_f32();
+    assert!(
+      (folding - (1.0 - jacobian)).abs() < 1e-2,
+      "at {p}: folding {folding}, 1 - jacobian {}",
+      1.0 - jacobian
+    );
+  }
+}
+
+#[test]
+fn noise_folding_grows_with_the_amplitude() {
+  let folding = |amplitude: f32| -> Vec<f32> {
+    let shader = shader(amplitude);
+    sample_points()
+      .map(|p| {
+        shader
+          .call("eval_noise_folding", vec![Value::vec2(p.x, p.y)])
+          .as_f32()
+      })
+      .collect()
+  };
+  assert!(folding(0.0).iter().all(|folding| *folding == 0.0));
+  let calm = folding(0.5);
+  let rough = folding(2.0);
+  let max = |folding: &[f32]| folding.iter().copied().fold(f32::MIN, f32::max);
+  assert!(max(&calm) > 0.0, "calm noise waves don't fold");
+  assert!(max(&rough) > max(&calm));
+  // Folding is a Jacobian, not a slope: it is negative where the surface is stretched.
+  assert!(rough.iter().any(|folding| *folding < 0.0));
+}
+
+#[test]
+fn fft_foam_fades_at_the_decay_rates() {
+  const SIZE: u32 = 16;
+  const PATCH_SIZE: f32 = 100.0;
+  const DELTA_TIME: f32 = 0.25;
+  let decay: [f32; 4] = [0.5, 1.0, 2.0, 4.0];
+  let mut shader = Interpreter::new(compose_water_shader(include_str!(
+    "../assets/shaders/fft_ocean.wgsl"
+  )));
+  shader.set_global_member("ocean", "choppiness", Value::F32(1.0));
+  shader.set_global_member("ocean", "patch_size", Value::F32(PATCH_SIZE));
+  shader.set_global_member("ocean", "size", Value::U32(SIZE));
+  shader.set_global_member("ocean", "delta_time", Value::F32(DELTA_TIME));
+  for texture in ["displacement_texture", "derivatives_texture", "foam_texture"] {
+    shader.set_global(texture, Value::Texture(Texture::new(SIZE, SIZE)));
+  }
+  let flat = Value::Composite(vec![Value::vec4(0.0, 0.0, 0.0, 0.0); (SIZE * SIZE) as usize]);
+  shader.set_global("foam", flat.clone());
+
+  // A choppy wave along x, two wavelengths across the patch: xy = height + i * x displacement.
+  let k = TAU * 2.0 / PATCH_SIZE;
+  let a = 0.8 / k;
+  let wave = |x: u32| {
+    let x = x as f32 * PATCH_SIZE / SIZE as f32;
+    (a * (k * x).sin(), a * (k * x).cos())
+  };
+  let texels = (0..SIZE * SIZE)
+    .map(|i| {
+      let (height, dx) = wave(i % SIZE);
+      Value::vec4(height, dx, 0.0, 0.0)
+    })
+    .collect();
+  shader.set_global("src", Value::Composite(texels));
+  shader.dispatch("resolve", (SIZE, SIZE));
+
+  let foam = |shader: &Interpreter, x: u32| {
+    let Value::Composite(texels) = shader.global("foam") else {
+      panic!("foam is not an array");
+    };
+    texels[x as usize].as_f32s()
+  };
+  let step = 2.0 * PATCH_SIZE / SIZE as f32;
+  let folded: Vec<Vec<f32>> = (0..SIZE).map(|x| foam(&shader, x)).collect();
+  for x in 0..SIZE {
+    let ddx = (wave((x + 1) % SIZE).1 - wave((x + SIZE - 1) % SIZE).1) / step;
+    let folding = (-ddx).max(0.0);
+    for channel in &folded[x as usize] {
+      assert!((channel - folding).abs() < 1e-4, "at {x}: foam {channel}, folding {folding}");
+    }
+  }
+  assert!(folded.iter().any(|foam| foam[0] > 0.5), "the wave doesn't fold");
+
+  // The waves calm down, the foam fades.
+  shader.set_global("src", flat);
+  let frames = 4;
+  for _ in 0..frames {
+    shader.dispatch("resolve", (SIZE, SIZE));
+  }
+  for x in 0..SIZE {
+    let faded = foam(&shader, x);
+    for (channel, rate) in decay.iter().enumerate() {
+      let expected = folded[x as usize][channel] * (-rate * DELTA_TIME * frames as f32).exp();
+      assert!(
+        (faded[channel] - expected).abs() < 1e-4,
+        "at {x}, decay {rate}: foam {}, expected {expected}",
+        faded[channel]
+      );
+    }
+  }
+  let texture = shader.global("foam_texture").as_texture();
+  assert_eq!(texture.get(3, 0).to_vec(), foam(&shader, 3));
+}
+
+#[test]
+fn only_fft_foam_uses_the_decay() {
+  let foam = Foam {
+    decay: 3.0,
+    ..default()
+  };
+  assert!(!foam.ignores_decay(&WaveModel::Fft));
+  assert!(foam.ignores_decay(&WaveModel::default()));
+  assert!(!Foam::default().ignores_decay(&WaveModel::default()));
+}
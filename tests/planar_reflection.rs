@@ -0,0 +1,69 @@
+//! The planar reflection camera mirrors the view and clips below the water.
+use bevy::{prelude::*, render::camera::CameraProjection};
+use bevy_water::*;
+
+fn ndc(view_proj: Mat4, p: Vec3) -> Vec3 {
+  view_proj.project_point3(p)
+}
+
+#[test]
+fn mirrored_camera_sees_reflection_flipped() {
+  let height = 1.0;
+  let camera = Transform::from_xyz(3.0, 6.0, 5.0).looking_at(Vec3::new(20.0, 0.0, -30.0), Vec3::Y);
+  let mirrored = mirror_transform(&camera, height);
+  let projection = PerspectiveProjection::default().get_projection_matrix();
+  let view_proj = |t: &Transform| projection * t.compute_matrix().inverse();
+
+  for p in [
+    Vec3::new(18.0, 4.0, -25.0),
+    Vec3::new(10.0, 2.5, -40.0),
+    Vec3::new(30.0, 8.0, -20.0),
+  ] {
+    // The reflection of `p` seen by the camera, and `p` seen by the mirrored camera.
+    let reflected = Vec3::new(p.x, height * 2.0 - p.y, p.z);
+    let seen = ndc(view_proj(&camera), reflected);
+    let mirror_seen = ndc(view_proj(&mirrored), p);
+    assert!(
+      (seen.x + mirror_seen.x).abs() < 1e-4,
+      "{seen} {mirror_seen}"
+    );
+    assert!(
+      (seen.y - mirror_seen.y).abs() < 1e-4,
+      "{seen} {mirror_seen}"
+    );
+  }
+}
+
+#[test]
+fn clip_plane_replaces_near_plane() {
+  // Looking down -Z from the origin, keep everything above y = 1.
+  let projection = PlanarReflectionProjection {
+    perspective: PerspectiveProjection::default(),
+    clip_plane: Vec4::new(0.0, 1.0, 0.0, -1.0),
+  };
+  let matrix = projection.get_projection_matrix();
+  let depth = |p: Vec3| {
+    let clip = matrix * p.extend(1.0);
+    clip.z / clip.w
+  };
+
+  assert!(
+    depth(Vec3::new(0.0, 0.5, -10.0)) > 1.0,
+    "below the plane is clipped"
+  );
+  let far = 1.0e5;
+  let tan = (projection.perspective.fov * 0.5).tan();
+  let aspect = projection.perspective.aspect_ratio;
+  for p in [
+    Vec3::new(0.0, 1.1, -2.0),
+    Vec3::new(0.0, 3.0, -10.0),
+    Vec3::new(far * tan * aspect, far * tan, -far),
+    Vec3::new(-far * tan * aspect, far * tan, -far),
+  ] {
+    let d = depth(p);
+    assert!((0.0..=1.0).contains(&d), "{p} depth {d}");
+  }
+  // Closer along a view ray is deeper (reverse z).
+  let ray = Vec3::new(0.1, 0.4, -1.0);
+  assert!(depth(ray * 5.0) > depth(ray * 50.0));
+}
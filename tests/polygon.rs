@@ -0,0 +1,104 @@
+//! Polygon shaped water bodies.
+use bevy::{
+  ecs::system::SystemState,
+  prelude::*,
+  render::mesh::{Indices, VertexAttributeValues},
+};
+use bevy_water::*;
+
+/// An L shaped lake, clockwise, with the notch at x > 10, z > 10.
+fn l_shape() -> Vec<Vec2> {
+  vec![
+    Vec2::new(0.0, 0.0),
+    Vec2::new(0.0, 20.0),
+    Vec2::new(10.0, 20.0),
+    Vec2::new(10.0, 10.0),
+    Vec2::new(20.0, 10.0),
+    Vec2::new(20.0, 0.0),
+  ]
+}
+
+#[test]
+fn polygons_are_triangulated() {
+  let points = l_shape();
+  assert_eq!(polygon_area(&points), -300.0);
+  let triangles = triangulate_polygon(&points);
+  assert_eq!(triangles.len(), 4);
+  let area: f32 = triangles
+    .iter()
+    .map(|[a, b, c]| polygon_area(&[points[*a], points[*b], points[*c]]))
+    .sum();
+  assert_eq!(area, 300.0, "counter-clockwise triangles");
+}
+
+#[test]
+fn polygon_mesh_is_subdivided() {
+  let mesh = water_polygon_mesh(&l_shape());
+  let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+  else {
+    panic!("positions");
+  };
+  let Some(Indices::U32(indices)) = mesh.indices() else {
+    panic!("indices");
+  };
+  let mut area = 0.0;
+  for triangle in indices.chunks_exact(3) {
+    let [a, b, c] = [0, 1, 2].map(|i| Vec3::from_array(positions[triangle[i] as usize]));
+    for (from, to) in [(a, b), (b, c), (c, a)] {
+      assert!(from.distance(to) <= 4.0, "long edge {from} {to}");
+    }
+    let normal = (b - a).cross(c - a);
+    assert!(normal.y > 0.0, "facing up");
+    area += normal.length() / 2.0;
+  }
+  assert!((area - 300.0).abs() < 1e-2);
+
+  // Centred on the bounds, with UVs spanning them.
+  let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+    panic!("uvs");
+  };
+  assert_eq!(positions[0], [-10.0, 0.0, -10.0]);
+  assert_eq!(uvs[0], [0.0, 0.0]);
+  assert_eq!(uvs[4], [1.0, 0.5]);
+}
+
+#[test]
+fn mesh_outlines() {
+  let mesh = water_grid_mesh(Vec2::new(8.0, 12.0));
+  let outline = mesh_outline(&mesh, &GlobalTransform::from_xyz(100.0, 0.0, 0.0)).unwrap();
+  assert!((polygon_area(&outline).abs() - 96.0).abs() < 1e-3);
+  assert!(outline.iter().all(|p| (96.0..=104.0).contains(&p.x)));
+
+  let closed = Mesh::from(Cuboid::default());
+  assert!(mesh_outline(&closed, &GlobalTransform::IDENTITY).is_none());
+}
+
+#[test]
+fn no_water_outside_inland_lakes() {
+  let mut world = World::new();
+  world.init_resource::<Time>();
+  world.insert_resource(WaterSettings {
+    global_water: false,
+    ..default()
+  });
+  world.spawn(WaterBody {
+    height: 2.0,
+    amplitude: 0.0,
+    bounds: WaterBounds::Polygon(l_shape()),
+    ..default()
+  });
+
+  let mut state = SystemState::<WaterParam>::new(&mut world);
+  let water = state.get(&world);
+  let inside = Vec3::new(5.0, 0.0, 15.0);
+  assert!(water.has_water(inside));
+  assert_eq!(water.wave_height(inside), 2.0);
+  let notch = Vec3::new(15.0, 0.0, 15.0);
+  assert!(!water.has_water(notch));
+  assert_eq!(water.try_wave_height(notch), None);
+  assert_eq!(water.wave_height(notch), water.settings.height);
+  assert_eq!(water.depth_below_surface(notch - Vec3::Y * 100.0), None);
+  assert!(!water.is_underwater(notch - Vec3::Y * 100.0));
+  assert_eq!(water.wave_normal(notch), Vec3::Y);
+  assert_eq!(water.water_depth(notch), Some(0.0));
+}
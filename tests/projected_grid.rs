@@ -0,0 +1,21 @@
+//! The projected grid covers the screen and its margin.
+use bevy::{prelude::*, render::mesh::VertexAttributeValues};
+use bevy_water::*;
+
+#[test]
+fn grid_covers_the_screen_with_margin() {
+  let mesh = projected_grid_mesh(UVec2::new(4, 2), 0.1);
+  let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+    panic!("missing uvs");
+  };
+  assert_eq!(uvs.len(), 5 * 3);
+  let min = uvs
+    .iter()
+    .fold(Vec2::MAX, |min, uv| min.min(Vec2::from(*uv)));
+  let max = uvs
+    .iter()
+    .fold(Vec2::MIN, |max, uv| max.max(Vec2::from(*uv)));
+  assert!((min - Vec2::splat(-0.1)).length() < 1e-6);
+  assert!((max - Vec2::splat(1.1)).length() < 1e-6);
+  assert_eq!(mesh.indices().unwrap().len(), 4 * 2 * 6);
+}
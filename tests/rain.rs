@@ -0,0 +1,138 @@
+//! Rain and splashes.
+mod common;
+
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_water::{material::StandardWaterMaterial, *};
+use common::*;
+
+#[test]
+fn splashes_fade_out() {
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)))
+    .add_plugins(WaterRainPlugin);
+  app.update();
+  app
+    .world
+    .send_event(SplashEvent::new(Vec3::new(1.0, 5.0, 2.0), 0.5));
+  app.update();
+
+  let splashes = &app.world.resource::<WaterSplashes>().splashes;
+  assert_eq!(splashes.len(), 1);
+  assert_eq!(splashes[0].position, Vec2::new(1.0, 2.0));
+  let texels = app.world.resource::<WaterSplashes>().texels();
+  assert_eq!(texels[0], Vec4::new(1.0, 2.0, 0.0, 0.5));
+  assert_eq!(texels[1], Vec4::ZERO, "the end of the splashes");
+  assert_eq!(
+    texels[MAX_WATER_SPLASHES],
+    Vec4::new(1.0, SPLASH_DURATION, 0.0, 0.0)
+  );
+
+  for _ in 0..=(SPLASH_DURATION * 10.0) as u32 {
+    app.update();
+  }
+  assert!(app.world.resource::<WaterSplashes>().splashes.is_empty());
+}
+
+#[test]
+fn materials_only_draw_splashes_while_there_are_any() {
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)))
+    .init_resource::<Assets<Image>>()
+    .init_resource::<Assets<StandardWaterMaterial>>()
+    .add_plugins(WaterRainPlugin);
+  app.finish();
+  app.cleanup();
+  let material = app
+    .world
+    .resource_mut::<Assets<StandardWaterMaterial>>()
+    .add(WaterBody::default().material(Rect::new(0.0, 0.0, 1.0, 1.0)));
+  let splashes = |app: &App| {
+    let materials = app.world.resource::<Assets<StandardWaterMaterial>>();
+    materials.get(&material).unwrap().extension.splashes.clone()
+  };
+  app.update();
+  assert_eq!(splashes(&app), None);
+
+  app
+    .world
+    .send_event(SplashEvent::new(Vec3::new(1.0, 0.0, 2.0), 0.5));
+  app.update();
+  let image = app.world.resource::<WaterDataImage<WaterSplashes>>().image.clone();
+  assert_eq!(splashes(&app), Some(image));
+
+  for _ in 0..=(SPLASH_DURATION * 10.0) as u32 {
+    app.update();
+  }
+  assert_eq!(splashes(&app), None);
+}
+
+const ENTRY: &str = r#"
+#import bevy_water::water_rain
+
+fn eval_splash_rings(p: vec2<f32>) -> vec3<f32> {
+  let rings = water_rain::splash_rings(p);
+  return vec3<f32>(rings.foam, rings.slope);
+}
+
+@compute @workgroup_size(1)
+fn main() {}
+"#;
+
+/// Foam and slope along +x of a splash at the origin `age` seconds old.
+fn splash_rings(age: f32) -> Vec<(f32, f32)> {
+  let splashes = WaterSplashes {
+    splashes: vec![WaterSplash {
+      position: Vec2::ZERO,
+      radius: 0.5,
+      strength: 1.0,
+      age,
+    }],
+  };
+  let size = WaterSplashes::SIZE;
+  let mut texture = Texture::new(size.x, size.y);
+  for (texel, value) in texture.texels.iter_mut().zip(splashes.texels()) {
+    *texel = value.to_array();
+  }
+  let mut shader = Interpreter::new(compose_water_shader(ENTRY));
+  shader.set_global("splashes", Value::Texture(texture));
+  (0..80)
+    .map(|i| {
+      let rings = shader
+        .call("eval_splash_rings", vec![Value::vec2(i as f32 * 0.1, 0.0)])
+        .as_f32s();
+      (rings[0], rings[1])
+    })
+    .collect()
+}
+
+#[test]
+fn splash_rings_spread_and_fade() {
+  // Distance and strength of the steepest part of the rings.
+  let crest = |rings: &[(f32, f32)]| {
+    let (i, (_, slope)) = rings
+      .iter()
+      .enumerate()
+      .max_by(|a, b| a.1 .1.abs().total_cmp(&b.1 .1.abs()))
+      .unwrap();
+    (i as f32 * 0.1, slope.abs())
+  };
+  let young = splash_rings(0.1);
+  assert!(young[0].0 > 0.5, "foam where it hit");
+  let mut last = crest(&young);
+  for age in [0.5, 1.0, 2.0] {
+    let rings = splash_rings(age);
+    assert_eq!(rings[0].0, 0.0, "the foam settled at {age}");
+    let (distance, strength) = crest(&rings);
+    assert!(distance > last.0, "the rings don't spread at {age}");
+    assert!(strength < last.1, "the rings don't fade at {age}");
+    last = (distance, strength);
+  }
+  let gone = splash_rings(SPLASH_DURATION);
+  assert!(gone.iter().all(|(foam, slope)| *foam == 0.0 && *slope == 0.0));
+}
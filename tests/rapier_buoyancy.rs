@@ -0,0 +1,42 @@
+//! Checks the `bevy_rapier3d` adapter applies the buoyancy.
+#![cfg(feature = "rapier")]
+mod common;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_water::physics::rapier::RapierBuoyancyPlugin;
+use common::physics::{self, Engine};
+
+struct Rapier;
+
+impl Engine for Rapier {
+  fn add_plugins(app: &mut App) {
+    app.add_plugins((
+      RapierPhysicsPlugin::<NoUserData>::default(),
+      RapierBuoyancyPlugin,
+    ));
+  }
+
+  fn insert_cube(body: &mut EntityWorldMut) {
+    // Half extents.
+    body.insert((RigidBody::Dynamic, Collider::cuboid(0.5, 0.5, 0.5)));
+  }
+
+  fn force(app: &App, body: Entity) -> Vec3 {
+    app.world.get::<ExternalForce>(body).unwrap().force
+  }
+
+  fn torque(app: &App, body: Entity) -> Vec3 {
+    app.world.get::<ExternalForce>(body).unwrap().torque
+  }
+}
+
+#[test]
+fn submerged_body_is_pushed_up() {
+  physics::submerged_body_is_pushed_up::<Rapier>();
+}
+
+#[test]
+fn body_above_the_water_has_no_force() {
+  physics::body_above_the_water_has_no_force::<Rapier>();
+}
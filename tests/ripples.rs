@@ -0,0 +1,99 @@
+//! Steps the ripple simulation shader.
+mod common;
+
+use common::*;
+
+const SIZE: u32 = 32;
+
+fn shader() -> Interpreter {
+  let mut shader = Interpreter::new(compose_water_shader(include_str!(
+    "../assets/shaders/water_ripples.wgsl"
+  )));
+  shader.set_global_member("ripples", "size", Value::U32(SIZE));
+  shader.set_global_member("ripples", "courant2", Value::F32(0.25));
+  shader.set_global_member("ripples", "damping", Value::F32(0.98));
+  shader.set_global_member("ripples", "texel_size", Value::F32(1.0));
+  shader.set_global_member("ripples", "max_height", Value::F32(0.5));
+  shader.set_global("src", Value::Texture(Texture::new(SIZE, SIZE)));
+  shader.set_global("dst", Value::Texture(Texture::new(SIZE, SIZE)));
+  shader
+}
+
+/// Push the water down at `center` in the next step.
+fn disturb(shader: &mut Interpreter, center: (f32, f32)) {
+  let mut disturbances = vec![Value::vec4(0.0, 0.0, 0.0, 0.0); 32];
+  disturbances[0] = Value::vec4(center.0, center.1, 1.5, -0.1);
+  shader.set_global_member("ripples", "count", Value::U32(1));
+  shader.set_global_member("ripples", "disturbances", Value::Composite(disturbances));
+}
+
+/// One simulation step, the result is the source of the next one.
+fn step(shader: &mut Interpreter) -> Texture {
+  shader.dispatch("step", (SIZE, SIZE));
+  shader.set_global_member("ripples", "count", Value::U32(0));
+  shader.set_global_member("ripples", "shift", Value::Composite(vec![Value::I32(0); 2]));
+  let heights = shader.global("dst").clone();
+  shader.set_global("src", heights.clone());
+  heights.as_texture().clone()
+}
+
+fn height(heights: &Texture, x: u32, y: u32) -> f32 {
+  heights.get(x, y)[0]
+}
+
+fn energy(heights: &Texture) -> f32 {
+  heights.texels.iter().map(|texel| texel[0] * texel[0]).sum()
+}
+
+#[test]
+fn a_disturbance_spreads_and_fades() {
+  let mut shader = shader();
+  disturb(&mut shader, (16.5, 16.5));
+  let first = step(&mut shader);
+  assert!(height(&first, 16, 16) < -0.05, "the water is pushed down");
+  assert!(height(&first, 22, 16).abs() < 1e-6, "only the disturbed water moves");
+
+  let mut heights = first.clone();
+  for _ in 0..12 {
+    heights = step(&mut shader);
+  }
+  // The ring spreads out evenly and the centre springs back.
+  let ring = height(&heights, 22, 16);
+  assert!(ring.abs() > 1e-4, "the ripples didn't spread");
+  for (x, y) in [(16, 22), (10, 16), (16, 10)] {
+    assert!((height(&heights, x, y) - ring).abs() < 1e-2 * ring.abs());
+  }
+  assert!(height(&heights, 16, 16) > height(&first, 16, 16));
+
+  let spread = energy(&heights);
+  for _ in 0..40 {
+    heights = step(&mut shader);
+  }
+  let faded = energy(&heights);
+  assert!(faded < spread * 0.5, "the ripples don't fade: {spread} -> {faded}");
+}
+
+#[test]
+fn ripples_stay_in_place_when_the_grid_moves() {
+  let mut shader = shader();
+  disturb(&mut shader, (16.5, 16.5));
+  step(&mut shader);
+  // The grid moves 3 texels along +x, the ripples move 3 texels along -x in the grid.
+  shader.set_global_member("ripples", "shift", Value::Composite(vec![Value::I32(3), Value::I32(0)]));
+  shader.set_global_member("ripples", "damping", Value::F32(1.0));
+  let moved = step(&mut shader);
+  let lowest = (0..SIZE)
+    .flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+    .min_by(|a, b| height(&moved, a.0, a.1).total_cmp(&height(&moved, b.0, b.1)))
+    .unwrap();
+  assert_eq!(lowest, (13, 16));
+}
+
+#[test]
+fn ripples_are_clamped_to_the_max_height() {
+  let mut shader = shader();
+  shader.set_global_member("ripples", "max_height", Value::F32(0.02));
+  disturb(&mut shader, (16.5, 16.5));
+  let first = step(&mut shader);
+  assert_eq!(height(&first, 16, 16), -0.02);
+}
@@ -0,0 +1,114 @@
+//! Rivers along a spline.
+use bevy::{
+  ecs::system::SystemState,
+  prelude::*,
+  render::mesh::{Indices, VertexAttributeValues},
+};
+use bevy_water::*;
+
+/// A straight river flowing towards +X, down from 5 to 3 meters.
+fn straight() -> RiverSpline {
+  RiverSpline {
+    points: vec![
+      RiverPoint::new(Vec3::new(0.0, 5.0, 0.0), 4.0, 2.0),
+      RiverPoint::new(Vec3::new(20.0, 3.0, 0.0), 4.0, 2.0),
+    ],
+    ..default()
+  }
+}
+
+#[test]
+fn straight_river_sections() {
+  let surface = straight().surface();
+  assert_eq!(surface.sections.len(), 12);
+  let last = surface.sections.last().unwrap();
+  assert!((last.distance - 20.1).abs() < 0.05);
+  assert!(surface.sections.iter().all(|section| section.bank == 0.0));
+
+  let sample = surface.sample(Vec2::new(10.0, 1.0)).unwrap();
+  assert_eq!(sample.section.direction, Vec2::X);
+  assert!((sample.across - 1.0).abs() < 1e-5, "+Z is the right bank");
+  assert!((sample.height() - 4.0).abs() < 1e-5);
+  assert!((sample.depth() - 1.5).abs() < 1e-5);
+  assert!((sample.velocity(2.0) - Vec2::new(1.5, 0.0)).length() < 1e-5);
+  assert!(
+    surface.sample(Vec2::new(10.0, 3.0)).is_none(),
+    "beyond the bank"
+  );
+}
+
+#[test]
+fn outer_bank_rises_in_bends() {
+  // Turning from +X towards +Z (to the right).
+  let river = RiverSpline {
+    points: vec![
+      RiverPoint::new(Vec3::ZERO, 4.0, 2.0),
+      RiverPoint::new(Vec3::new(10.0, 0.0, 0.0), 4.0, 2.0),
+      RiverPoint::new(Vec3::new(20.0, 0.0, 10.0), 4.0, 2.0),
+    ],
+    speed: 3.0,
+    ..default()
+  };
+  let surface = river.surface();
+  let bend = surface.sample(Vec2::new(10.0, 0.0)).unwrap().section;
+  assert!(bend.bank < 0.0, "the left bank is the outer bank: {bend:?}");
+  let flat = RiverSpline {
+    banking: 0.0,
+    ..river
+  };
+  assert!(flat.surface().sections.iter().all(|s| s.bank == 0.0));
+}
+
+#[test]
+fn river_mesh_follows_the_surface() {
+  let surface = straight().surface();
+  let mesh = surface.mesh(4);
+  let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+  else {
+    panic!("positions");
+  };
+  assert_eq!(positions.len(), 12 * 5);
+  assert_eq!(positions[0], [0.0, 5.0, -2.0]);
+  assert_eq!(positions[4], [0.0, 5.0, 2.0]);
+  let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+    panic!("uvs");
+  };
+  assert_eq!(uvs[0], [0.0, 0.0]);
+  assert_eq!(uvs[uvs.len() - 1], [1.0, 1.0]);
+  let Some(Indices::U32(indices)) = mesh.indices() else {
+    panic!("indices");
+  };
+  assert_eq!(indices.len(), 11 * 4 * 6);
+
+  assert_eq!(surface.rect(), Rect::new(0.0, -2.0, 20.0, 2.0));
+  let bounds = WaterBounds::Polygon(surface.outline());
+  assert!(bounds.contains(Vec2::new(10.0, 1.0)));
+  assert!(!bounds.contains(Vec2::new(10.0, 3.0)));
+}
+
+#[test]
+fn water_param_follows_the_river() {
+  let mut world = World::new();
+  world.init_resource::<Time>();
+  world.insert_resource(WaterSettings {
+    height: 0.0,
+    ..default()
+  });
+  let surface = straight().surface();
+  world.spawn((
+    WaterBody {
+      height: 3.0,
+      amplitude: 0.0,
+      bounds: WaterBounds::Polygon(surface.outline()),
+      ..default()
+    },
+    surface,
+  ));
+
+  let mut state = SystemState::<WaterParam>::new(&mut world);
+  let water = state.get(&world);
+  let height = |x: f32, z: f32| water.wave_height(Vec3::new(x, 10.0, z));
+  assert!((height(10.0, 0.0) - 4.0).abs() < 1e-4);
+  assert!((height(15.0, -1.0) - 3.5).abs() < 1e-4);
+  assert!(water.water_body(Vec3::new(10.0, 10.0, 5.0)).is_none());
+}
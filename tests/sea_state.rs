@@ -0,0 +1,108 @@
+//! Checks the wave parameters derived from the `SeaState`.
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_water::{
+  sea_state::{SeaState, SeaStatePlugin},
+  GerstnerWave, WaterSettings, WaveModel,
+};
+
+#[test]
+fn stronger_wind_makes_bigger_waves() {
+  for force in 1..12 {
+    let calm = SeaState::beaufort(force);
+    let rough = SeaState::beaufort(force + 1);
+    assert!(rough.significant_wave_height() > calm.significant_wave_height());
+    assert!(rough.peak_wavelength() > calm.peak_wavelength());
+  }
+}
+
+#[test]
+fn beaufort_force_round_trips() {
+  for force in 0..=12 {
+    assert_eq!(SeaState::beaufort(force).beaufort_force(), force);
+  }
+}
+
+#[test]
+fn gerstner_waves_follow_the_wind() {
+  for force in 0..=12 {
+    let mut sea = SeaState::beaufort(force);
+    sea.wind_direction = Vec2::new(-2.0, 1.0);
+    let waves = sea.gerstner_waves();
+    let dominant = waves[0].direction.normalize();
+    assert!(dominant.abs_diff_eq(sea.wind_direction.normalize(), 1e-5));
+    let steepness: f32 = waves.iter().map(|wave| wave.steepness).sum();
+    assert!(steepness < 1.0, "Beaufort {force}: crests loop ({steepness})");
+  }
+}
+
+#[test]
+fn noise_waves_follow_the_wind() {
+  let mut sea = SeaState::beaufort(7);
+  sea.wind_direction = Vec2::new(0.0, -4.0);
+  let mut settings = WaterSettings::default();
+  sea.update_settings(&mut settings);
+  let WaveModel::Noise(noise) = settings.wave_model else {
+    panic!("Wave model changed");
+  };
+  assert!(noise.direction.abs_diff_eq(Vec2::NEG_Y, 1e-6));
+  assert!(noise.scale > 1.0);
+  assert!((settings.amplitude - sea.significant_wave_height() / 2.0).abs() < 1e-6);
+}
+
+#[test]
+fn transitions_cross_fade_the_waves() {
+  let from = SeaState::beaufort(3);
+  let mut to = SeaState::beaufort(6);
+  to.wind_direction = Vec2::new(1.0, 1.0);
+  let mut settings = WaterSettings {
+    wave_model: WaveModel::Gerstner(vec![]),
+    ..default()
+  };
+  let phases = |waves: &[GerstnerWave]| -> Vec<(Vec2, f32)> {
+    waves.iter().map(|w| (w.wave_vector(), w.speed)).collect()
+  };
+  let mut expected = phases(&to.gerstner_waves());
+  expected.extend(phases(&from.gerstner_waves()));
+  for t in [0.1, 0.5, 0.9] {
+    to.update_settings_from(&from, t, &mut settings);
+    let waves = settings.wave_model.gerstner_waves();
+    // The waves keep their wavelength, direction and speed, only their steepness fades.
+    assert_eq!(phases(waves), expected, "waves slide at {t}");
+    let steepness = |waves: &[GerstnerWave]| waves.iter().map(|w| w.steepness).sum::<f32>();
+    let blended =
+      steepness(&to.gerstner_waves()) * t + steepness(&from.gerstner_waves()) * (1.0 - t);
+    assert!((steepness(waves) - blended).abs() < 1e-5);
+  }
+  to.update_settings_from(&from, 1.0, &mut settings);
+  assert_eq!(settings.wave_model.gerstner_waves(), to.gerstner_waves().as_slice());
+}
+
+#[test]
+fn transitions_stop_updating_when_done() {
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)))
+    .init_resource::<WaterSettings>()
+    .insert_resource(SeaState::beaufort(2))
+    .add_plugins(SeaStatePlugin);
+  app.update();
+  app.world.resource_mut::<SeaState>().wind_speed = 15.0;
+  // 5 second transition.
+  let mut changed = Vec::new();
+  for _ in 0..80 {
+    app.update();
+    changed.push(app.world.resource_ref::<WaterSettings>().last_changed());
+  }
+  let WaveModel::Noise(noise) = &app.world.resource::<WaterSettings>().wave_model else {
+    panic!("Wave model changed");
+  };
+  assert_eq!(noise.fade, None);
+  let done = changed.iter().position(|tick| *tick == changed[changed.len() - 1]);
+  assert!(
+    done.is_some_and(|frame| frame > 40 && frame < 60),
+    "Settings changed until frame {done:?}"
+  );
+}
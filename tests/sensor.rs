@@ -0,0 +1,78 @@
+//! Water sensors and submersion queries.
+use bevy::{ecs::system::SystemState, prelude::*};
+use bevy_water::*;
+
+fn calm_water() -> WaterSettings {
+  WaterSettings {
+    height: 1.0,
+    amplitude: 0.0,
+    ..default()
+  }
+}
+
+#[test]
+fn submersion_queries() {
+  let mut world = World::new();
+  world.init_resource::<Time>();
+  world.insert_resource(calm_water());
+  let mut state = SystemState::<WaterParam>::new(&mut world);
+  let water = state.get(&world);
+
+  assert_eq!(water.depth_below_surface(Vec3::new(0.0, -2.0, 0.0)), Some(3.0));
+  assert!(water.is_underwater(Vec3::new(0.0, 0.5, 0.0)));
+  assert!(
+    !water.is_underwater(Vec3::new(0.0, 1.0, 0.0)),
+    "on the surface"
+  );
+  assert!(!water.is_underwater(Vec3::new(0.0, 3.0, 0.0)));
+}
+
+#[test]
+fn sensors_send_enter_and_exit_events() {
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    .insert_resource(calm_water())
+    .add_plugins(WaterSensorPlugin);
+  // The sensor point is 1 meter above the entity.
+  let swimmer = app
+    .world
+    .spawn((
+      WaterSensor::new(Vec3::Y),
+      GlobalTransform::from_xyz(0.0, 2.0, 0.0),
+    ))
+    .id();
+  let mut dive = |y: f32| {
+    *app.world.get_mut::<GlobalTransform>(swimmer).unwrap() =
+      GlobalTransform::from_xyz(0.0, y, 0.0);
+    app.update();
+    let entered: Vec<_> = app
+      .world
+      .resource_mut::<Events<EnteredWater>>()
+      .drain()
+      .collect();
+    let exited: Vec<_> = app
+      .world
+      .resource_mut::<Events<ExitedWater>>()
+      .drain()
+      .collect();
+    (entered, exited)
+  };
+
+  assert_eq!(dive(2.0), (vec![], vec![]));
+  assert_eq!(
+    dive(-0.5),
+    (
+      vec![EnteredWater {
+        entity: swimmer,
+        position: Vec3::new(0.0, 0.5, 0.0),
+      }],
+      vec![]
+    )
+  );
+  assert_eq!(dive(-1.0), (vec![], vec![]), "still under water");
+  let (entered, exited) = dive(0.5);
+  assert!(entered.is_empty());
+  assert_eq!(exited.len(), 1);
+  assert!(!app.world.get::<WaterSensor>(swimmer).unwrap().submerged);
+}
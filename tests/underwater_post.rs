@@ -0,0 +1,162 @@
+//! Underwater cameras, and the god rays marched through the water.
+mod common;
+
+use bevy::prelude::*;
+use bevy_water::*;
+use common::*;
+
+fn view_at(settings: WaterSettings, camera: Vec3) -> Option<UnderwaterView> {
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    .insert_resource(Assets::<Shader>::default())
+    .insert_resource(settings)
+    .add_plugins(UnderwaterPostProcessPlugin);
+  let camera = app
+    .world
+    .spawn((
+      UnderwaterPostProcess::default(),
+      Camera::default(),
+      GlobalTransform::from_translation(camera),
+    ))
+    .id();
+  app.update();
+  app.world.get::<UnderwaterView>(camera).copied()
+}
+
+#[test]
+fn cameras_below_the_waves_are_underwater() {
+  let calm = || WaterSettings {
+    height: 1.0,
+    amplitude: 0.0,
+    ..default()
+  };
+  let below = view_at(calm(), Vec3::new(3.0, -1.0, 2.0)).unwrap();
+  assert!(below.is_underwater());
+  assert!(below.is_near_plane_underwater());
+  assert_eq!(below.surface, Vec4::new(0.0, 1.0, 0.0, -1.0));
+  assert_eq!(below.depth, 2.0);
+
+  let above = view_at(calm(), Vec3::new(3.0, 4.0, 2.0)).unwrap();
+  assert!(!above.is_underwater());
+  assert!(!above.is_near_plane_underwater());
+  assert_eq!(above.depth, -3.0);
+
+  let dry = view_at(
+    WaterSettings {
+      global_water: false,
+      ..calm()
+    },
+    Vec3::new(3.0, -1.0, 2.0),
+  );
+  assert_eq!(dry, None, "no water here");
+}
+
+fn vec3(v: Vec3) -> Value {
+  Value::Composite(vec![Value::F32(v.x), Value::F32(v.y), Value::F32(v.z)])
+}
+
+/// The god rays below a flat surface at y = 0, lit straight from above.
+///
+/// The caustics texture covers x and z from -10 to 10 and focuses the light where `focus`.
+fn god_rays(absorption: f32, focus: impl Fn(Vec2) -> f32) -> Interpreter {
+  const SIZE: u32 = 32;
+  let mut shader = Interpreter::new(compose_water_shader_with_defs(
+    include_str!("../assets/shaders/underwater_post.wgsl"),
+    &["GOD_RAYS"],
+  ));
+  let set = |shader: &mut Interpreter, member, value| {
+    shader.set_global_member("underwater", member, value)
+  };
+  set(&mut shader, "surface", Value::vec4(0.0, 1.0, 0.0, 0.0));
+  set(&mut shader, "light_dir", vec3(Vec3::Y));
+  set(&mut shader, "absorption", vec3(Vec3::splat(absorption)));
+  set(&mut shader, "god_ray_steps", Value::U32(100));
+  set(&mut shader, "god_ray_distance", Value::F32(30.0));
+  set(&mut shader, "god_ray_intensity", Value::F32(0.5));
+  set(&mut shader, "god_ray_color", Value::vec4(1.0, 1.0, 1.0, 1.0));
+  let world_to_uv = Mat4::from_translation(Vec3::new(0.5, 0.0, 0.5))
+    * Mat4::from_scale(Vec3::new(1.0 / 20.0, 1.0, 1.0 / 20.0));
+  let columns = world_to_uv
+    .to_cols_array_2d()
+    .map(|[x, y, z, w]| Value::vec4(x, y, z, w));
+  set(&mut shader, "water_world_to_uv", Value::Composite(columns.to_vec()));
+
+  // The caustics material writes half the focus.
+  let mut texture = Texture::new(SIZE, SIZE);
+  for x in 0..SIZE {
+    for y in 0..SIZE {
+      let p = (Vec2::new(x as f32, y as f32) + 0.5) / SIZE as f32 * 20.0 - 10.0;
+      texture.set(x, y, [focus(p) * 0.5, 0.0, 0.0, 1.0]);
+    }
+  }
+  shader.set_global("caustics_texture", Value::Texture(texture));
+  shader.set_global(
+    "caustics_sampler",
+    Value::Sampler(Sampler {
+      linear: true,
+      repeat: false,
+    }),
+  );
+  shader
+}
+
+/// Light scattered towards a camera at `camera` looking along `direction`, up to `distance`.
+fn march(shader: &mut Interpreter, camera: Vec3, direction: Vec3, distance: f32) -> f32 {
+  shader.set_global_member("underwater", "camera_position", vec3(camera));
+  let rays = shader.call(
+    "god_rays",
+    vec![
+      Value::vec4(0.0, 0.0, 0.0, 1.0),
+      vec3(camera + direction * 0.1),
+      Value::F32(distance),
+    ],
+  );
+  let rays = rays.as_f32s();
+  assert!(rays[0] == rays[1] && rays[1] == rays[2], "white light: {rays:?}");
+  rays[0]
+}
+
+#[test]
+fn god_rays_add_up_the_light_along_the_ray() {
+  let mut shader = god_rays(0.0, |_| 1.0);
+  let camera = Vec3::new(-5.0, -2.0, 0.0);
+  // Clear water: intensity * the distance marched.
+  let rays = march(&mut shader, camera, Vec3::X, 10.0);
+  assert!((rays - 0.5 * 10.0).abs() < 1e-3, "{rays}");
+  shader.set_global_member("underwater", "god_ray_distance", Value::F32(8.0));
+  let rays = march(&mut shader, camera, Vec3::X, 10.0);
+  assert!((rays - 0.5 * 8.0).abs() < 1e-3, "only to the max distance: {rays}");
+  // Only the light under the surface.
+  let rays = march(&mut shader, camera, Vec3::Y, 10.0);
+  assert!((rays - 0.5 * 2.0).abs() < 0.1, "{rays}");
+}
+
+#[test]
+fn god_rays_follow_the_caustics() {
+  // Light focused on the half with x < 0.
+  let mut shader = god_rays(0.0, |p| if p.x < 0.0 { 1.0 } else { 0.0 });
+  let rays = march(&mut shader, Vec3::new(-5.0, -2.0, 0.0), Vec3::X, 10.0);
+  assert!((rays - 0.5 * 5.0).abs() < 0.5, "{rays}");
+  let rays = march(&mut shader, Vec3::new(5.0, -2.0, 0.0), Vec3::Z, 5.0);
+  assert_eq!(rays, 0.0, "in the shadow");
+}
+
+#[test]
+fn god_rays_fade_in_deep_water() {
+  let absorption = 0.1;
+  let mut shader = god_rays(absorption, |_| 1.0);
+  let mut light = |depth: f32| {
+    let distance = 10.0;
+    let rays = march(&mut shader, Vec3::new(-5.0, -depth, 0.0), Vec3::X, distance);
+    // Absorbed on the way down to the ray and along it to the camera.
+    let expected =
+      0.5 * (-absorption * depth).exp() * (1.0 - (-absorption * distance).exp()) / absorption;
+    assert!(
+      (rays - expected).abs() < expected * 0.05,
+      "at {depth}m: {rays}, expected {expected}"
+    );
+    rays
+  };
+  assert!(light(8.0) < light(2.0));
+}
@@ -0,0 +1,62 @@
+//! Ship wake trails.
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_water::*;
+
+#[test]
+fn moving_wakes_leave_a_fading_trail() {
+  let mut app = App::new();
+  app
+    .add_plugins(MinimalPlugins)
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs(1)))
+    .add_plugins(WaterWakePlugin);
+  let wake = Wake {
+    spacing: 2.0,
+    lifetime: 5.0,
+    ..default()
+  };
+  let ship = app.world.spawn((wake, GlobalTransform::default())).id();
+
+  // Sail 1 meter per second along +X.
+  for x in 0..10 {
+    *app.world.get_mut::<GlobalTransform>(ship).unwrap() =
+      GlobalTransform::from_xyz(x as f32, 0.0, 0.0);
+    app.update();
+  }
+
+  let wakes = app.world.resource::<WaterWakes>();
+  assert_eq!(wakes.trails.len(), 1);
+  let points = &wakes.trails[0].points;
+  assert_eq!(points[0], Vec4::new(9.0, 0.0, 0.0, 0.0), "starts at the ship");
+  for pair in points.windows(2) {
+    assert!(pair[1].z > pair[0].z, "distance grows along the trail");
+    assert!(pair[1].w > pair[0].w, "older along the trail");
+  }
+  let oldest = points.last().unwrap();
+  assert!(oldest.w <= 5.0, "points outlive the lifetime");
+  assert_eq!(oldest.z, 9.0 - oldest.x);
+
+  let texels = wakes.texels();
+  assert_eq!(texels[..points.len()], points[..]);
+  assert_eq!(
+    texels[MAX_WAKE_POINTS],
+    Vec4::new(wake.half_width, wake.foam, wake.lifetime, points.len() as f32)
+  );
+}
+
+#[test]
+fn the_last_trail_is_cut_to_the_space_left() {
+  let trail = WakeTrail {
+    wake: Wake::default(),
+    points: (0..30).map(|i| Vec4::new(0.0, 0.0, i as f32, 0.0)).collect(),
+  };
+  let wakes = WaterWakes {
+    trails: vec![trail; 9],
+  };
+  let texels = wakes.texels();
+  let last = 8 * 30;
+  let count = MAX_WAKE_POINTS - last;
+  assert_eq!(texels[MAX_WAKE_POINTS + last].w, count as f32);
+  assert_eq!(texels[last..MAX_WAKE_POINTS], wakes.trails[8].points[..count]);
+}
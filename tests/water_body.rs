@@ -0,0 +1,81 @@
+//! Water bodies: `WaterParam` resolves the body at a position, bodies update their materials.
+use bevy::{ecs::system::SystemState, prelude::*};
+use bevy_water::{
+  material::Refraction,
+  *,
+};
+
+fn world() -> World {
+  let mut world = World::new();
+  world.init_resource::<Time>();
+  world.insert_resource(WaterSettings {
+    height: 0.0,
+    amplitude: 0.0,
+    ..default()
+  });
+  world
+}
+
+fn lake(height: f32, rect: Rect) -> WaterBody {
+  WaterBody {
+    height,
+    amplitude: 0.0,
+    bounds: WaterBounds::Rect(rect),
+    ..default()
+  }
+}
+
+fn wave_height(world: &mut World, position: Vec3) -> f32 {
+  let mut state = SystemState::<WaterParam>::new(world);
+  state.get(world).wave_height(position)
+}
+
+#[test]
+fn lakes_at_different_elevations() {
+  let mut world = world();
+  world.spawn(lake(10.0, Rect::new(0.0, 0.0, 10.0, 10.0)));
+  world.spawn(lake(25.0, Rect::new(20.0, 0.0, 30.0, 10.0)));
+
+  assert_eq!(wave_height(&mut world, Vec3::new(5.0, 0.0, 5.0)), 10.0);
+  assert_eq!(wave_height(&mut world, Vec3::new(25.0, 0.0, 5.0)), 25.0);
+  // Outside the lakes the global settings are used.
+  assert_eq!(wave_height(&mut world, Vec3::new(15.0, 0.0, 5.0)), 0.0);
+}
+
+#[test]
+fn overlapping_bodies_use_the_surface_above() {
+  let mut world = world();
+  world.spawn(WaterBody {
+    height: 0.0,
+    amplitude: 0.0,
+    ..default()
+  });
+  world.spawn(lake(10.0, Rect::new(-5.0, -5.0, 5.0, 5.0)));
+
+  // Above the lake.
+  assert_eq!(wave_height(&mut world, Vec3::new(0.0, 20.0, 0.0)), 10.0);
+  // Between the lake and the sea (e.g. a cave under the lake).
+  assert_eq!(wave_height(&mut world, Vec3::new(0.0, 5.0, 0.0)), 10.0);
+  // Below the sea.
+  assert_eq!(wave_height(&mut world, Vec3::new(0.0, -5.0, 0.0)), 0.0);
+  // Next to the lake.
+  assert_eq!(wave_height(&mut world, Vec3::new(8.0, 20.0, 0.0)), 0.0);
+}
+
+#[test]
+fn refraction_draws_in_the_transmissive_pass() {
+  let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+  let mut body = WaterBody {
+    refraction: Some(Refraction::default()),
+    ..default()
+  };
+  let mut mat = body.material(rect);
+  assert_eq!(mat.base.alpha_mode, AlphaMode::Opaque);
+  assert!(mat.base.specular_transmission > 0.0);
+
+  // Back to alpha blending.
+  body.refraction = None;
+  body.update_material(&mut mat);
+  assert_eq!(mat.base.alpha_mode, AlphaMode::Blend);
+  assert_eq!(mat.base.specular_transmission, 0.0);
+}
@@ -0,0 +1,217 @@
+//! Checks that the CPU wave functions (used by `WaterParam`) match the WGSL shader.
+mod common;
+
+use bevy::prelude::*;
+use bevy_water::{
+  get_wave_height, GerstnerWave, NoiseFade, NoiseWaves, WaveModel, MAX_GERSTNER_WAVES,
+};
+use common::*;
+
+const ENTRY: &str = r#"
+#import bevy_water::water_functions as water_fn
+
+fn eval_wave_height(p: vec2<f32>) -> f32 {
+  return water_fn::get_wave_height(p);
+}
+
+fn eval_wave_displacement(p: vec2<f32>) -> vec3<f32> {
+  return water_fn::get_wave_displacement(p);
+}
+
+fn eval_wave_normal(p: vec2<f32>) -> vec3<f32> {
+  return water_fn::get_wave_normal(p);
+}
+
+@compute @workgroup_size(1)
+fn main() {}
+"#;
+
+const TIMES: &[f32] = &[0.0, 0.75, 12.5, 123.4, 3599.0];
+
+fn shader(time: f32, amplitude: f32) -> Interpreter {
+  let mut shader = Interpreter::new(compose_water_shader(ENTRY));
+  shader.set_global_member("globals", "time", Value::F32(time));
+  shader.set_global_member("material", "amplitude", Value::F32(amplitude));
+  shader.set_global_member("material", "noise_rotation", Value::vec2(1.0, 0.0));
+  shader.set_global_member("material", "noise_scale", Value::F32(1.0));
+  shader
+}
+
+fn sample_points() -> impl Iterator<Item = Vec2> {
+  (-6..=6).flat_map(|x| (-6..=6).map(move |z| Vec2::new(x as f32 * 7.3, z as f32 * 11.9 - 0.37)))
+}
+
+#[test]
+fn wave_height_matches_shader() {
+  for &amplitude in &[1.0, 2.5] {
+    for &time in TIMES {
+      let shader = shader(time, amplitude);
+      for p in sample_points() {
+        let gpu = shader.call("eval_wave_height", vec![Value::vec2(p.x, p.y)]).as_f32();
+        let cpu = get_wave_height(time, 0.0, amplitude, Vec3::new(p.x, 0.0, p.y));
+        assert!(
+          (gpu - cpu).abs() <= 1e-4 * amplitude,
+          "Wave height diverged at {p} (time {time}, amplitude {amplitude}): shader {gpu}, cpu {cpu}",
+        );
+      }
+    }
+  }
+}
+
+#[test]
+fn base_height_is_an_offset() {
+  let time = 5.0;
+  let shader = shader(time, 1.0);
+  for p in sample_points() {
+    let gpu = shader.call("eval_wave_height", vec![Value::vec2(p.x, p.y)]).as_f32();
+    let cpu = get_wave_height(time, 3.0, 1.0, Vec3::new(p.x, 0.0, p.y));
+    assert!((gpu + 3.0 - cpu).abs() <= 1e-4, "at {p}: {gpu} + 3.0 != {cpu}");
+  }
+}
+
+#[test]
+fn rotated_noise_matches_shader() {
+  let angle = 2.3_f32;
+  let noise = NoiseWaves {
+    direction: Vec2::from_angle(angle) * 3.0,
+    scale: 2.5,
+    ..default()
+  };
+  let model = WaveModel::Noise(noise);
+  for &time in TIMES {
+    let mut shader = shader(time, 1.0);
+    // Rotates the wave direction onto the diagonal of the noise.
+    let rotation = Vec2::from_angle(std::f32::consts::FRAC_PI_4 - angle);
+    shader.set_global_member("material", "noise_rotation", Value::vec2(rotation.x, rotation.y));
+    shader.set_global_member("material", "noise_scale", Value::F32(noise.scale));
+    for p in sample_points() {
+      let gpu = shader.call("eval_wave_height", vec![Value::vec2(p.x, p.y)]).as_f32();
+      let cpu = model.height(time, 1.0, p);
+      assert!(
+        (gpu - cpu).abs() <= 1e-4,
+        "Rotated noise diverged at {p} (time {time}): shader {gpu}, cpu {cpu}",
+      );
+    }
+  }
+}
+
+#[test]
+fn faded_noise_matches_shader() {
+  let (angle, fade_angle) = (0.4_f32, -1.2_f32);
+  let noise = NoiseWaves {
+    direction: Vec2::from_angle(angle),
+    scale: 1.5,
+    fade: Some(NoiseFade {
+      direction: Vec2::from_angle(fade_angle),
+      scale: 0.8,
+      weight: 0.3,
+    }),
+  };
+  let model = WaveModel::Noise(noise);
+  let rotation = |angle: f32| Vec2::from_angle(std::f32::consts::FRAC_PI_4 - angle);
+  for &time in TIMES {
+    let mut shader = shader(time, 1.0);
+    let (r, fade_r) = (rotation(angle), rotation(fade_angle));
+    shader.set_global_member("material", "noise_rotation", Value::vec2(r.x, r.y));
+    shader.set_global_member("material", "noise_scale", Value::F32(1.5));
+    shader.set_global_member("material", "noise_fade_rotation", Value::vec2(fade_r.x, fade_r.y));
+    shader.set_global_member("material", "noise_fade_scale", Value::F32(0.8));
+    shader.set_global_member("material", "noise_fade", Value::F32(0.3));
+    for p in sample_points() {
+      let gpu = shader.call("eval_wave_height", vec![Value::vec2(p.x, p.y)]).as_f32();
+      let cpu = model.height(time, 1.0, p);
+      assert!(
+        (gpu - cpu).abs() <= 1e-4,
+        "Faded noise diverged at {p} (time {time}): shader {gpu}, cpu {cpu}",
+      );
+    }
+  }
+}
+
+fn gerstner_shader(time: f32, amplitude: f32, model: &WaveModel) -> Interpreter {
+  let mut shader = shader(time, amplitude);
+  let waves = model.gerstner_waves();
+  let mut packed: Vec<Value> = waves
+    .iter()
+    .map(|wave| {
+      let k = wave.wave_vector();
+      Value::vec4(k.x, k.y, wave.steepness, wave.speed)
+    })
+    .collect();
+  packed.resize(MAX_GERSTNER_WAVES, Value::vec4(0.0, 0.0, 0.0, 0.0));
+  shader.set_global_member("material", "wave_mode", Value::U32(1));
+  shader.set_global_member("material", "wave_count", Value::U32(waves.len() as u32));
+  shader.set_global_member("material", "waves", Value::Composite(packed));
+  shader
+}
+
+#[test]
+fn gerstner_displacement_matches_shader() {
+  let model = WaveModel::gerstner_swell();
+  for &amplitude in &[1.0, 0.5] {
+    for &time in TIMES {
+      let shader = gerstner_shader(time, amplitude, &model);
+      for p in sample_points() {
+        let gpu = shader
+          .call("eval_wave_displacement", vec![Value::vec2(p.x, p.y)])
+          .as_f32s();
+        let cpu = model.displacement(time, p) * amplitude;
+        let gpu = Vec3::new(gpu[0], gpu[1], gpu[2]);
+        assert!(
+          gpu.abs_diff_eq(cpu, 1e-3 * amplitude),
+          "Gerstner displacement diverged at {p} (time {time}): shader {gpu}, cpu {cpu}",
+        );
+      }
+    }
+  }
+}
+
+#[test]
+fn gerstner_height_follows_displaced_surface() {
+  let model = WaveModel::gerstner_swell();
+  let time = 7.0;
+  for p in sample_points() {
+    // The displaced point on the surface must report its own height.
+    let d = model.displacement(time, p);
+    let surface = p + Vec2::new(d.x, d.z);
+    let height = model.height(time, 1.0, surface);
+    assert!((height - d.y).abs() < 1e-2, "at {surface}: {height} != {}", d.y);
+  }
+}
+
+#[test]
+fn gerstner_height_stays_finite_where_the_waves_fold() {
+  // Too steep, the crests fold over and the Newton search meets singular Jacobians.
+  for steepness in [1.0, 1.5] {
+    let model = WaveModel::Gerstner(vec![GerstnerWave::new(
+      Vec2::X,
+      std::f32::consts::TAU,
+      steepness,
+    )]);
+    for i in 0..20000 {
+      let p = Vec2::new(i as f32 * 0.00097, 0.0);
+      let height = model.height(0.0, 1.0, p);
+      assert!(height.is_finite(), "NaN height at {p}, steepness {steepness}");
+    }
+  }
+}
+
+#[test]
+fn gerstner_ignores_waves_without_a_direction() {
+  let mut waves = WaveModel::gerstner_swell().gerstner_waves().to_vec();
+  waves.push(GerstnerWave::new(Vec2::ZERO, 20.0, 0.2));
+  let model = WaveModel::Gerstner(waves);
+  let shader = gerstner_shader(3.0, 1.0, &model);
+  for p in sample_points() {
+    let gpu = shader
+      .call("eval_wave_displacement", vec![Value::vec2(p.x, p.y)])
+      .as_f32s();
+    let gpu = Vec3::new(gpu[0], gpu[1], gpu[2]);
+    assert!(gpu.is_finite(), "NaN displacement at {p}: {gpu}");
+    assert!(gpu.abs_diff_eq(model.displacement(3.0, p), 1e-3));
+    let normal = shader
+      .call("eval_wave_normal", vec![Value::vec2(p.x, p.y)])
+      .as_f32s();
+    assert!(normal.iter().all(|n| n.is_finite()), "NaN normal at {p}");
+  }
+}
@@ -0,0 +1,39 @@
+//! Checks the `bevy_xpbd_3d` adapter applies the buoyancy.
+#![cfg(feature = "xpbd")]
+mod common;
+
+use bevy::prelude::*;
+use bevy_water::physics::xpbd::XpbdBuoyancyPlugin;
+use bevy_xpbd_3d::prelude::*;
+use common::physics::{self, Engine};
+
+struct Xpbd;
+
+impl Engine for Xpbd {
+  fn add_plugins(app: &mut App) {
+    app.add_plugins((PhysicsPlugins::default(), XpbdBuoyancyPlugin));
+  }
+
+  fn insert_cube(body: &mut EntityWorldMut) {
+    // Full extents.
+    body.insert((RigidBody::Dynamic, Collider::cuboid(1.0, 1.0, 1.0)));
+  }
+
+  fn force(app: &App, body: Entity) -> Vec3 {
+    app.world.get::<ExternalForce>(body).unwrap().force()
+  }
+
+  fn torque(app: &App, body: Entity) -> Vec3 {
+    app.world.get::<ExternalTorque>(body).unwrap().torque()
+  }
+}
+
+#[test]
+fn submerged_body_is_pushed_up() {
+  physics::submerged_body_is_pushed_up::<Xpbd>();
+}
+
+#[test]
+fn body_above_the_water_has_no_force() {
+  physics::body_above_the_water_has_no_force::<Xpbd>();
+}